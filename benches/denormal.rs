@@ -0,0 +1,37 @@
+use basic_audio_filters::first_order_iir::{IIR1Coefficients, IIR1};
+use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+// Processes the tail after an impulse, where the state decays through the denormal range.
+// Compare against `--no-default-features` to see the cost of unprotected denormals.
+fn decaying_tail(c: &mut Criterion) {
+    let mut group = c.benchmark_group("impulse + 100k zeros");
+
+    group.bench_function("IIR2", |b| {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, 48000.0);
+        b.iter(|| {
+            let mut filter = IIR2::from(coeffs);
+            black_box(filter.process(1.0));
+            for _ in 0..100_000 {
+                black_box(filter.process(black_box(0.0)));
+            }
+        })
+    });
+
+    group.bench_function("IIR1", |b| {
+        let coeffs = IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0);
+        b.iter(|| {
+            let mut filter = IIR1::from(coeffs);
+            black_box(filter.process(1.0));
+            for _ in 0..100_000 {
+                black_box(filter.process(black_box(0.0)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decaying_tail);
+criterion_main!(benches);
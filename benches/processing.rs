@@ -0,0 +1,175 @@
+use basic_audio_filters::cascade_iir::IIR2Cascade;
+use basic_audio_filters::first_order_iir::{IIR1Coefficients, IIR1};
+use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_complex::Complex;
+use std::hint::black_box;
+
+// Typical host buffer sizes, a small low latency one and a large one
+const BLOCK_SIZES: [usize; 2] = [64, 512];
+
+fn rand(x: f32) -> f32 {
+    ((x * 12.9898).sin() * 43_758.547).fract()
+}
+
+/// Noise in -1..1, generated once so the benchmarks only measure filtering
+fn noise(length: usize) -> Vec<f32> {
+    (0..length).map(|i| rand(i as f32) * 2.0 - 1.0).collect()
+}
+
+fn eq_bands() -> [IIR2Coefficients; 4] {
+    [
+        IIR2Coefficients::highpass(40.0, 0.0, 0.707, 48000.0),
+        IIR2Coefficients::lowshelf(200.0, 3.0, 0.7, 48000.0),
+        IIR2Coefficients::bell(1000.0, -4.0, 1.5, 48000.0),
+        IIR2Coefficients::highshelf(8000.0, 2.0, 0.7, 48000.0),
+    ]
+}
+
+fn per_sample_vs_block(c: &mut Criterion) {
+    let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+    let mut group = c.benchmark_group("IIR2");
+    for size in BLOCK_SIZES {
+        let input = noise(size);
+        group.throughput(Throughput::Elements(size as u64));
+
+        let mut filter = IIR2::from(coeffs);
+        group.bench_with_input(BenchmarkId::new("process", size), &input, |b, input| {
+            b.iter(|| {
+                for sample in input.iter() {
+                    black_box(filter.process(*sample));
+                }
+            })
+        });
+
+        let mut buffer = input.clone();
+        group.bench_with_input(
+            BenchmarkId::new("process_block", size),
+            &input,
+            |b, input| {
+                b.iter(|| {
+                    buffer.copy_from_slice(input);
+                    filter.process_block(black_box(&mut buffer));
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn four_band_chain(c: &mut Criterion) {
+    let bands = eq_bands();
+    let mut group = c.benchmark_group("4 band chain");
+    for size in BLOCK_SIZES {
+        let input = noise(size);
+        let mut buffer = input.clone();
+        group.throughput(Throughput::Elements(size as u64));
+
+        let mut filters = bands.map(IIR2::from);
+        group.bench_with_input(BenchmarkId::new("4x IIR2", size), &input, |b, input| {
+            b.iter(|| {
+                buffer.copy_from_slice(input);
+                for filter in filters.iter_mut() {
+                    filter.process_block(black_box(&mut buffer));
+                }
+            })
+        });
+
+        let mut cascade = IIR2Cascade::from(bands);
+        group.bench_with_input(BenchmarkId::new("IIR2Cascade", size), &input, |b, input| {
+            b.iter(|| {
+                buffer.copy_from_slice(input);
+                cascade.process_block(black_box(&mut buffer));
+            })
+        });
+    }
+    group.finish();
+}
+
+fn first_vs_second_order(c: &mut Criterion) {
+    let mut group = c.benchmark_group("low pass order");
+    for size in BLOCK_SIZES {
+        let input = noise(size);
+        let mut buffer = input.clone();
+        group.throughput(Throughput::Elements(size as u64));
+
+        let mut first = IIR1::from(IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0));
+        group.bench_with_input(BenchmarkId::new("IIR1", size), &input, |b, input| {
+            b.iter(|| {
+                buffer.copy_from_slice(input);
+                first.process_block(black_box(&mut buffer));
+            })
+        });
+
+        let mut second = IIR2::from(IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, 48000.0));
+        group.bench_with_input(BenchmarkId::new("IIR2", size), &input, |b, input| {
+            b.iter(|| {
+                buffer.copy_from_slice(input);
+                second.process_block(black_box(&mut buffer));
+            })
+        });
+    }
+    group.finish();
+}
+
+// Designing coefficients goes through tan() and for gains powf(), this is the cost of every
+// parameter change
+fn coefficient_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("coefficients");
+    group.bench_function("IIR2 lowpass", |b| {
+        b.iter(|| {
+            IIR2Coefficients::lowpass(
+                black_box(1000.0),
+                black_box(0.0),
+                black_box(0.707),
+                black_box(48000.0),
+            )
+        })
+    });
+    group.bench_function("IIR2 bell", |b| {
+        b.iter(|| {
+            IIR2Coefficients::bell(
+                black_box(1000.0),
+                black_box(6.0),
+                black_box(1.0),
+                black_box(48000.0),
+            )
+        })
+    });
+    group.bench_function("IIR1 lowpass", |b| {
+        b.iter(|| IIR1Coefficients::lowpass(black_box(1000.0), black_box(0.0), black_box(48000.0)))
+    });
+    group.finish();
+}
+
+// Redrawing a response curve, once per point with cos() and sin() against the batched path
+fn bode_samples(c: &mut Criterion) {
+    let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+    let frequencies: Vec<f32> = (0..512)
+        .map(|i| 20.0 * 1000.0f32.powf(i as f32 / 511.0))
+        .collect();
+    let mut out = vec![Complex::new(0.0, 0.0); frequencies.len()];
+    let mut group = c.benchmark_group("bode");
+    group.throughput(Throughput::Elements(frequencies.len() as u64));
+    group.bench_function("get_bode_sample", |b| {
+        b.iter(|| {
+            for (f, out) in frequencies.iter().zip(out.iter_mut()) {
+                *out = coeffs.get_bode_sample(black_box(*f), 48000.0);
+            }
+        })
+    });
+    group.bench_function("get_bode_samples", |b| {
+        b.iter(|| coeffs.get_bode_samples(black_box(&frequencies), 48000.0, &mut out))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    per_sample_vs_block,
+    four_band_chain,
+    first_vs_second_order,
+    coefficient_construction,
+    bode_samples
+);
+criterion_main!(benches);
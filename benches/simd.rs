@@ -0,0 +1,44 @@
+use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+use basic_audio_filters::simd_iir::IIR2x8;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use wide::f32x8;
+
+fn rand(x: f32) -> f32 {
+    ((x * 12.9898).sin() * 43_758.547).fract()
+}
+
+fn eight_channels(c: &mut Criterion) {
+    let coeffs = [0, 1, 2, 3, 4, 5, 6, 7]
+        .map(|i| IIR2Coefficients::bell(100.0 * (i + 1) as f32, 6.0, 1.0, 48000.0));
+    let frames: Vec<[f32; 8]> = (0..512)
+        .map(|i| [0, 1, 2, 3, 4, 5, 6, 7].map(|lane| rand((i * 8 + lane) as f32)))
+        .collect();
+
+    let mut group = c.benchmark_group("8 channels x 512 samples");
+
+    let mut scalar = coeffs.map(IIR2::from);
+    group.bench_function("8x IIR2", |b| {
+        b.iter(|| {
+            for frame in frames.iter() {
+                for (filter, sample) in scalar.iter_mut().zip(frame.iter()) {
+                    black_box(filter.process(*sample));
+                }
+            }
+        })
+    });
+
+    let mut simd = IIR2x8::from(coeffs);
+    group.bench_function("IIR2x8", |b| {
+        b.iter(|| {
+            for frame in frames.iter() {
+                black_box(simd.process(f32x8::new(*frame)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, eight_channels);
+criterion_main!(benches);
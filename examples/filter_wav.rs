@@ -0,0 +1,30 @@
+//! Filters a WAV file with a chain of second order filters.
+//!
+//! ```text
+//! cargo run --release --example filter_wav --features wav -- input.wav output.wav \
+//!     --highpass 40 0.707 --bell 1000 6 1.0
+//! ```
+//!
+//! Shelves, bells, the constant Q bell (`--constantqbell`) and tilts take cutoff, gain and Q,
+//! the other filters cutoff and Q. The filters run in the order given.
+
+use std::process::ExitCode;
+
+use basic_audio_filters::offline::{filter_wav_file, parse_filter_chain};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: filter_wav <input.wav> <output.wav> [--<filter> <cutoff> [gain] <q>]...");
+        return ExitCode::FAILURE;
+    }
+    let result = parse_filter_chain(&args[2..])
+        .and_then(|chain| filter_wav_file(&args[0], &args[1], &chain));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("filter_wav: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
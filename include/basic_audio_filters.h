@@ -0,0 +1,176 @@
+#ifndef BASIC_AUDIO_FILTERS_H
+#define BASIC_AUDIO_FILTERS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Opaque handle to a second order filter processing `float` samples
+typedef struct RbafIIR2 RbafIIR2;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a filter with `IIR2Coefficients::lowpass`
+struct RbafIIR2 *rbaf_iir2_new_lowpass(float cutoff_hz,
+                                       float gain_db,
+                                       float q_value,
+                                       float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::lowpass`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_lowpass(struct RbafIIR2 *handle,
+                              float cutoff_hz,
+                              float gain_db,
+                              float q_value,
+                              float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::highpass`
+struct RbafIIR2 *rbaf_iir2_new_highpass(float cutoff_hz,
+                                        float gain_db,
+                                        float q_value,
+                                        float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::highpass`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_highpass(struct RbafIIR2 *handle,
+                               float cutoff_hz,
+                               float gain_db,
+                               float q_value,
+                               float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::bandpass`
+struct RbafIIR2 *rbaf_iir2_new_bandpass(float cutoff_hz,
+                                        float gain_db,
+                                        float q_value,
+                                        float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::bandpass`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_bandpass(struct RbafIIR2 *handle,
+                               float cutoff_hz,
+                               float gain_db,
+                               float q_value,
+                               float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::notch`
+struct RbafIIR2 *rbaf_iir2_new_notch(float cutoff_hz,
+                                     float gain_db,
+                                     float q_value,
+                                     float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::notch`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_notch(struct RbafIIR2 *handle,
+                            float cutoff_hz,
+                            float gain_db,
+                            float q_value,
+                            float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::allpass`
+struct RbafIIR2 *rbaf_iir2_new_allpass(float cutoff_hz,
+                                       float gain_db,
+                                       float q_value,
+                                       float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::allpass`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_allpass(struct RbafIIR2 *handle,
+                              float cutoff_hz,
+                              float gain_db,
+                              float q_value,
+                              float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::lowshelf`
+struct RbafIIR2 *rbaf_iir2_new_lowshelf(float cutoff_hz,
+                                        float gain_db,
+                                        float q_value,
+                                        float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::lowshelf`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_lowshelf(struct RbafIIR2 *handle,
+                               float cutoff_hz,
+                               float gain_db,
+                               float q_value,
+                               float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::highshelf`
+struct RbafIIR2 *rbaf_iir2_new_highshelf(float cutoff_hz,
+                                         float gain_db,
+                                         float q_value,
+                                         float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::highshelf`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_highshelf(struct RbafIIR2 *handle,
+                                float cutoff_hz,
+                                float gain_db,
+                                float q_value,
+                                float sample_rate_hz);
+
+// Creates a filter with `IIR2Coefficients::bell`
+struct RbafIIR2 *rbaf_iir2_new_bell(float cutoff_hz,
+                                    float gain_db,
+                                    float q_value,
+                                    float sample_rate_hz);
+
+// Switches to `IIR2Coefficients::bell`, keeping the state
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_update_bell(struct RbafIIR2 *handle,
+                           float cutoff_hz,
+                           float gain_db,
+                           float q_value,
+                           float sample_rate_hz);
+
+// Creates a filter that passes its input unchanged
+struct RbafIIR2 *rbaf_iir2_new_bypass(void);
+
+// Filters a single sample
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+float rbaf_iir2_process(struct RbafIIR2 *handle, float input);
+
+// Filters len samples at samples in place
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors, and samples has to
+// point to len writable floats unless it is null
+void rbaf_iir2_process_block(struct RbafIIR2 *handle, float *samples, uintptr_t len);
+
+// Clears the state of the filter, keeping its coefficients
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors
+void rbaf_iir2_reset(struct RbafIIR2 *handle);
+
+// Destroys a filter, the handle must not be used afterwards. Null is ignored.
+//
+// # Safety
+// handle has to be null or a live pointer from one of the constructors, and is freed only once
+void rbaf_iir2_free(struct RbafIIR2 *handle);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BASIC_AUDIO_FILTERS_H */
@@ -0,0 +1,186 @@
+use core::f64::consts::TAU;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, flush_denormal};
+
+/// Normalized direct form biquad coefficients, realizing
+/// `H(z) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2)`
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BiquadCoefficients<T = f32> {
+    pub b0: T,
+    pub b1: T,
+    pub b2: T,
+    pub a1: T,
+    pub a2: T,
+}
+
+impl<T: Float> BiquadCoefficients<T> {
+    #[inline]
+    pub fn get_bode_sample(self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        //Use y.norm() for amplitude and y.arg().to_degrees() for phase. Add to combine phase.
+
+        let z = -cast::<T>(TAU) * frequency_hz / sample_rate_hz;
+        let z = Complex::new(z.cos(), z.sin());
+        let zpow2 = z * z;
+
+        let numerator = zpow2 * self.b2 + z * self.b1 + self.b0;
+        let denominator = zpow2 * self.a2 + z * self.a1 + T::one();
+
+        numerator / denominator
+    }
+}
+
+/// Internal states and coefficients of a transposed direct form II biquad.
+///
+/// This runs any normalized biquad exactly as given, for coefficients from other libraries or
+/// designs the SVF can't represent. `IIR2` is the better choice when coefficients change while
+/// processing: its states are the integrator values of the circuit and stay meaningful for new
+/// coefficients, while the states here hold partial sums of the old ones, so an abrupt change
+/// leaves a transient even on a steady input, and fast modulation of resonant filters can
+/// become unstable. Use `from_svf` and `to_svf` to move designs between the two.
+#[derive(Copy, Clone, Debug)]
+pub struct Biquad<T = f32> {
+    s1: T,
+    s2: T,
+    pub coeffs: BiquadCoefficients<T>,
+}
+
+impl<T: Float> Biquad<T> {
+    /// Creates a biquad from a set of filter coefficients
+    #[inline]
+    pub fn from(coefficients: BiquadCoefficients<T>) -> Self {
+        Biquad {
+            s1: T::zero(),
+            s2: T::zero(),
+            coeffs: coefficients,
+        }
+    }
+
+    /// Creates a biquad from coefficients normalized to a0 = 1
+    #[inline]
+    pub fn from_coefficients(b0: T, b1: T, b2: T, a1: T, a2: T) -> Self {
+        Biquad::from(BiquadCoefficients { b0, b1, b2, a1, a2 })
+    }
+
+    /// Creates a biquad with the same transfer function as the SVF coefficients
+    #[inline]
+    pub fn from_svf(coefficients: IIR2Coefficients<T>) -> Self {
+        Biquad::from(coefficients.to_biquad())
+    }
+
+    /// SVF coefficients with the same transfer function, `None` where `from_biquad` has no
+    /// equivalent
+    #[inline]
+    pub fn to_svf(&self) -> Option<IIR2Coefficients<T>> {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.coeffs;
+        IIR2Coefficients::from_biquad(b0, b1, b2, a1, a2)
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.coeffs;
+        let output = b0 * input_sample + self.s1;
+        self.s1 = flush_denormal(b1 * input_sample - a1 * output + self.s2);
+        self.s2 = flush_denormal(b2 * input_sample - a2 * output);
+        output
+    }
+
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    #[inline]
+    pub fn update(&mut self, new_coefficients: BiquadCoefficients<T>) {
+        self.coeffs = new_coefficients;
+    }
+
+    /// Clears the internal state, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    #[inline]
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::IIR2;
+
+    fn designs() -> [IIR2Coefficients<f64>; 4] {
+        [
+            IIR2Coefficients::lowpass(500.0, 0.0, 4.0, 48000.0),
+            IIR2Coefficients::highpass(80.0, 0.0, 0.707, 48000.0),
+            IIR2Coefficients::bell(3000.0, -9.0, 2.0, 48000.0),
+            IIR2Coefficients::highshelf(10000.0, 6.0, 0.7, 48000.0),
+        ]
+    }
+
+    #[test]
+    fn test_biquad_matches_svf() {
+        for coeffs in designs() {
+            let mut biquad = Biquad::from_svf(coeffs);
+            for f in [20.0, 200.0, 1000.0, 5000.0, 20000.0] {
+                let expected = coeffs.get_bode_sample(f, 48000.0);
+                let actual = biquad.get_bode_sample(f, 48000.0);
+                assert!((actual.norm().log10() - expected.norm().log10()).abs() < 1e-9);
+                assert!((actual.arg() - expected.arg()).abs() < 1e-9);
+            }
+
+            // Same impulse response
+            let mut svf = IIR2::from(coeffs);
+            for i in 0..2000 {
+                let x = if i == 0 { 1.0 } else { 0.0 };
+                assert!((biquad.process(x) - svf.process(x)).abs() < 1e-9);
+            }
+
+            let round_trip = biquad.to_svf().unwrap();
+            assert!((round_trip.k - coeffs.k).abs() < 1e-9);
+            assert!((round_trip.m1 - coeffs.m1).abs() < 1e-9);
+        }
+
+        let mut biquad = Biquad::from_coefficients(0.5, 0.0, 0.0, 0.0, 0.0);
+        let mut buffer = [1.0, -2.0];
+        biquad.process_block(&mut buffer);
+        assert_eq!(buffer, [0.5, -1.0]);
+    }
+
+    #[test]
+    fn test_biquad_coefficient_jump() {
+        // Settle both on DC, then jump to a resonant low pass. The SVF integrators already hold
+        // the steady state of every low pass, the direct form states don't.
+        let before = IIR2Coefficients::<f64>::lowpass(8000.0, 0.0, 0.707, 48000.0);
+        let after = IIR2Coefficients::<f64>::lowpass(100.0, 0.0, 8.0, 48000.0);
+        let mut svf = IIR2::from(before);
+        let mut biquad = Biquad::from_svf(before);
+        for _ in 0..1000 {
+            svf.process(1.0);
+            biquad.process(1.0);
+        }
+        svf.update(after);
+        biquad.update(after.to_biquad());
+
+        let (mut svf_error, mut biquad_error) = (0.0f64, 0.0f64);
+        for _ in 0..48000 {
+            svf_error = svf_error.max((svf.process(1.0) - 1.0).abs());
+            biquad_error = biquad_error.max((biquad.process(1.0) - 1.0).abs());
+        }
+        assert!(svf_error < 1e-9);
+        assert!(biquad_error > 0.1);
+
+        biquad.reset();
+        assert_eq!(biquad.process(0.0), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::cast;
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+/// Q of each second order section of an even order Butterworth response
+fn section_q_values<T: Float>(order: usize) -> impl Iterator<Item = T> {
+    assert!(
+        order > 0 && order.is_multiple_of(2),
+        "Butterworth order must be even"
+    );
+    (0..order / 2).map(move |i| {
+        let angle = (2 * i + 1) as f64 * PI / (2 * order) as f64;
+        cast::<T>(1.0 / (2.0 * angle.cos()))
+    })
+}
+
+/// Sections of an even order Butterworth low pass, -3 dB at cutoff_hz
+pub fn butterworth_lowpass<T: Float>(
+    order: usize,
+    cutoff_hz: T,
+    sample_rate_hz: T,
+) -> Vec<IIR2Coefficients<T>> {
+    section_q_values(order)
+        .map(|q| IIR2Coefficients::lowpass(cutoff_hz, T::zero(), q, sample_rate_hz))
+        .collect()
+}
+
+/// Sections of an even order Butterworth high pass, -3 dB at cutoff_hz
+pub fn butterworth_highpass<T: Float>(
+    order: usize,
+    cutoff_hz: T,
+    sample_rate_hz: T,
+) -> Vec<IIR2Coefficients<T>> {
+    section_q_values(order)
+        .map(|q| IIR2Coefficients::highpass(cutoff_hz, T::zero(), q, sample_rate_hz))
+        .collect()
+}
+
+/// Series of SVFs forming a Butterworth low or high pass of order 2, 4, 6, 8...
+#[derive(Clone, Debug)]
+pub struct ButterworthCascade<T = f32> {
+    sections: Vec<IIR2<T>>,
+}
+
+impl<T: Float> ButterworthCascade<T> {
+    pub fn lowpass(order: usize, cutoff_hz: T, sample_rate_hz: T) -> Self {
+        Self::from_sections(butterworth_lowpass(order, cutoff_hz, sample_rate_hz))
+    }
+
+    pub fn highpass(order: usize, cutoff_hz: T, sample_rate_hz: T) -> Self {
+        Self::from_sections(butterworth_highpass(order, cutoff_hz, sample_rate_hz))
+    }
+
+    fn from_sections(coeffs: Vec<IIR2Coefficients<T>>) -> Self {
+        ButterworthCascade {
+            sections: coeffs.into_iter().map(IIR2::from).collect(),
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.sections.len() * 2
+    }
+
+    /// Coefficients of each section, in processing order
+    pub fn coefficients(&self) -> impl Iterator<Item = IIR2Coefficients<T>> + '_ {
+        self.sections.iter().map(|section| section.coeffs)
+    }
+
+    /// Combined response of all sections
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.coefficients()
+            .fold(Complex::new(T::one(), T::zero()), |acc, coeffs| {
+                acc * coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            })
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        self.sections
+            .iter_mut()
+            .fold(input_sample, |x, section| section.process(x))
+    }
+
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(filter: &ButterworthCascade<f64>, frequency_hz: f64) -> f64 {
+        20.0 * filter.get_bode_sample(frequency_hz, 48000.0).norm().log10()
+    }
+
+    #[test]
+    fn test_butterworth_cutoff_and_slope() {
+        let cutoff_hz = 1000.0;
+        for &order in &[2, 4, 6, 8] {
+            let lowpass = ButterworthCascade::lowpass(order, cutoff_hz, 48000.0);
+            let highpass = ButterworthCascade::highpass(order, cutoff_hz, 48000.0);
+            assert_eq!(lowpass.order(), order);
+
+            let half_power_db = -10.0 * 2.0f64.log10();
+            assert!((db(&lowpass, cutoff_hz) - half_power_db).abs() < 1e-6);
+            assert!((db(&highpass, cutoff_hz) - half_power_db).abs() < 1e-6);
+
+            // One octave away the analog prototype is down 10 * log10(1 + 2^(2N)) ~ 6N dB
+            let expected_db = -10.0 * (1.0 + 4.0f64.powi(order as i32)).log10();
+            assert!((db(&lowpass, cutoff_hz * 2.0) - expected_db).abs() < 1.0);
+            assert!((db(&highpass, cutoff_hz / 2.0) - expected_db).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_butterworth_process() {
+        let mut filter = ButterworthCascade::<f32>::lowpass(4, 1000.0, 48000.0);
+        let mut output = 0.0;
+        for _ in 0..4800 {
+            output = filter.process(1.0);
+        }
+        assert!((output - 1.0).abs() < 1e-4);
+        filter.reset();
+        assert_eq!(filter.process(0.0), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_butterworth_odd_order() {
+        butterworth_lowpass::<f32>(3, 1000.0, 48000.0);
+    }
+}
@@ -0,0 +1,416 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::first_order_iir::IIR1Coefficients;
+use crate::response::{combined_bode_sample, SectionCoefficients};
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, group_delay, sanitize_cutoff};
+
+/// Highest order `CascadeDesign::new` accepts
+pub const MAX_CASCADE_ORDER: usize = 16;
+
+/// Smallest Chebyshev passband ripple, lower values are raised to it
+pub const MIN_RIPPLE_DB: f64 = 0.001;
+
+/// Shape of the response of a `CascadeDesign`
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterFamily<T = f32> {
+    /// Maximally flat passband, -3 dB at the cutoff
+    Butterworth,
+    /// Maximally flat group delay, for the least overshoot. Phase normalized, so the stopband
+    /// lines up with a Butterworth of the same order and cutoff, but the -3 dB point is lower.
+    Bessel,
+    /// Steeper than Butterworth in exchange for a passband that ripples by ripple_db. The
+    /// passband peaks at 0 dB and the response is down ripple_db at the cutoff.
+    ChebyshevI { ripple_db: T },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+}
+
+/// Poles of a normalized analog low pass, a cutoff of 1 rad/s. Only the poles in the upper half
+/// plane of each conjugate pair are listed, plus the real pole of odd orders, whose imaginary
+/// part is only 0 to within rounding.
+pub(crate) fn prototype_poles<T: Float>(
+    family: FilterFamily<T>,
+    order: usize,
+) -> Vec<Complex<f64>> {
+    match family {
+        FilterFamily::Butterworth => (0..order.div_ceil(2))
+            .map(|i| {
+                let angle = PI * (2 * i + 1 + order) as f64 / (2 * order) as f64;
+                Complex::new(angle.cos(), angle.sin().abs())
+            })
+            .collect(),
+        FilterFamily::Bessel => bessel_poles(order)
+            .into_iter()
+            .filter(|pole| pole.im > -1e-9)
+            .collect(),
+        FilterFamily::ChebyshevI { ripple_db } => {
+            let ripple_db = ripple_db
+                .to_f64()
+                .unwrap_or(MIN_RIPPLE_DB)
+                .max(MIN_RIPPLE_DB);
+            let epsilon = (10.0f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+            let mu = (1.0 / epsilon).asinh() / order as f64;
+            (0..order.div_ceil(2))
+                .map(|i| {
+                    let angle = PI * (2 * i + 1) as f64 / (2 * order) as f64;
+                    Complex::new(-mu.sinh() * angle.sin(), mu.cosh() * angle.cos())
+                })
+                .collect()
+        }
+    }
+}
+
+/// Roots of the reverse Bessel polynomial, scaled so their product is 1. Found with the
+/// Durand-Kerner iteration, which converges well here since the scaled polynomial has both its
+/// leading and constant coefficients at 1.
+fn bessel_poles(order: usize) -> Vec<Complex<f64>> {
+    // a_k = (2n - k)! / (2^(n - k) k! (n - k)!), from a_n = 1 down
+    let mut coefficients = alloc::vec![0.0f64; order + 1];
+    coefficients[order] = 1.0;
+    for k in (0..order).rev() {
+        coefficients[k] = coefficients[k + 1] * (2 * order - k) as f64 * (k + 1) as f64
+            / (2.0 * (order - k) as f64);
+    }
+    let scale = coefficients[0].powf(1.0 / order as f64);
+    for (k, coefficient) in coefficients.iter_mut().enumerate() {
+        *coefficient *= scale.powi(k as i32) / (scale.powi(order as i32));
+    }
+
+    let evaluate = |s: Complex<f64>| {
+        coefficients
+            .iter()
+            .rev()
+            .fold(Complex::new(0.0, 0.0), |acc, &c| acc * s + c)
+    };
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex<f64>> = (0..order).map(|i| seed.powu(i as u32)).collect();
+    for _ in 0..500 {
+        let mut largest_step = 0.0f64;
+        for i in 0..order {
+            let denominator = (0..order)
+                .filter(|&j| j != i)
+                .fold(Complex::new(1.0, 0.0), |acc, j| acc * (roots[i] - roots[j]));
+            let step = evaluate(roots[i]) / denominator;
+            roots[i] -= step;
+            largest_step = largest_step.max(step.norm());
+        }
+        if largest_step < 1e-15 {
+            break;
+        }
+    }
+    roots
+}
+
+/// Low or high pass of any order from 1 to `MAX_CASCADE_ORDER` in the Butterworth, Bessel or
+/// Chebyshev type I family, as second order sections plus a first order one for odd orders.
+///
+/// Each pair of prototype poles becomes a `lowpass` or `highpass` section with the frequency and
+/// Q of the pair. The section frequencies are placed through the prewarp of the overall cutoff,
+/// so the digital response matches the bilinear transform of the analog prototype and not just
+/// each section at its own frequency.
+#[derive(Clone, Debug)]
+pub struct CascadeDesign<T = f32> {
+    family: FilterFamily<T>,
+    kind: FilterKind,
+    sections: Vec<IIR2Coefficients<T>>,
+    first_order: Option<IIR1Coefficients<T>>,
+}
+
+impl<T: Float> CascadeDesign<T> {
+    /// Designs the sections, in order of increasing Q, `None` if the sample rate is not usable
+    ///
+    /// # Panics
+    ///
+    /// If order is 0 or above `MAX_CASCADE_ORDER`.
+    pub fn new(
+        family: FilterFamily<T>,
+        kind: FilterKind,
+        order: usize,
+        cutoff_hz: T,
+        sample_rate_hz: T,
+    ) -> Option<Self> {
+        assert!(
+            order > 0 && order <= MAX_CASCADE_ORDER,
+            "cascade order must be from 1 to MAX_CASCADE_ORDER"
+        );
+        let cutoff_hz = sanitize_cutoff(cutoff_hz, sample_rate_hz)?;
+        let mut design = CascadeDesign {
+            family,
+            kind,
+            sections: Vec::new(),
+            first_order: None,
+        };
+        let warped = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        // Frequency whose prewarp is the cutoff's scaled by the pole magnitude, inverted for
+        // high passes
+        let section_hz = |magnitude: f64| {
+            let magnitude = cast::<T>(magnitude);
+            let g = match kind {
+                FilterKind::LowPass => warped * magnitude,
+                FilterKind::HighPass => warped / magnitude,
+            };
+            g.atan() * sample_rate_hz / cast(PI)
+        };
+
+        let mut pairs = Vec::new();
+        for pole in prototype_poles(family, order) {
+            let magnitude = pole.norm();
+            if pole.im.abs() < 1e-9 {
+                let frequency_hz = section_hz(magnitude);
+                design.first_order = Some(match kind {
+                    FilterKind::LowPass => {
+                        IIR1Coefficients::lowpass(frequency_hz, T::zero(), sample_rate_hz)
+                    }
+                    FilterKind::HighPass => {
+                        IIR1Coefficients::highpass(frequency_hz, T::zero(), sample_rate_hz)
+                    }
+                });
+            } else {
+                pairs.push((section_hz(magnitude), magnitude / (-2.0 * pole.re)));
+            }
+        }
+        pairs.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // Even order Chebyshevs are at the bottom of the ripple at DC, where every section has
+        // unity gain, so the first one brings the peaks back to 0 dB
+        let mut gain_db = match family {
+            FilterFamily::ChebyshevI { ripple_db } if order.is_multiple_of(2) => {
+                -ripple_db.max(cast(MIN_RIPPLE_DB))
+            }
+            _ => T::zero(),
+        };
+        for (frequency_hz, q_value) in pairs {
+            let q_value = cast::<T>(q_value);
+            design.sections.push(match kind {
+                FilterKind::LowPass => {
+                    IIR2Coefficients::lowpass(frequency_hz, gain_db, q_value, sample_rate_hz)
+                }
+                FilterKind::HighPass => {
+                    IIR2Coefficients::highpass(frequency_hz, gain_db, q_value, sample_rate_hz)
+                }
+            });
+            gain_db = T::zero();
+        }
+        Some(design)
+    }
+
+    pub fn family(&self) -> FilterFamily<T> {
+        self.family
+    }
+
+    pub fn kind(&self) -> FilterKind {
+        self.kind
+    }
+
+    pub fn order(&self) -> usize {
+        self.sections.len() * 2 + self.first_order.map_or(0, |_| 1)
+    }
+
+    /// Second order sections, in processing order
+    pub fn sections(&self) -> &[IIR2Coefficients<T>] {
+        &self.sections
+    }
+
+    /// The first order section of odd orders
+    pub fn first_order(&self) -> Option<IIR1Coefficients<T>> {
+        self.first_order
+    }
+
+    /// All sections as one list, for `combined_frequency_response` and mixed order chains
+    pub fn section_coefficients(&self) -> Vec<SectionCoefficients<T>> {
+        self.sections
+            .iter()
+            .map(|&coeffs| coeffs.into())
+            .chain(self.first_order.map(SectionCoefficients::from))
+            .collect()
+    }
+
+    /// Combined response of all sections
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        let response = combined_bode_sample(&self.sections, frequency_hz, sample_rate_hz);
+        match self.first_order {
+            Some(coeffs) => response * coeffs.get_bode_sample(frequency_hz, sample_rate_hz),
+            None => response,
+        }
+    }
+
+    /// Group delay in samples of all sections together
+    pub fn group_delay_at(&self, frequency_hz: T, sample_rate_hz: T) -> T {
+        group_delay(
+            |f| self.get_bode_sample(f, sample_rate_hz),
+            frequency_hz,
+            sample_rate_hz,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn db(design: &CascadeDesign<f64>, frequency_hz: f64) -> f64 {
+        20.0 * design
+            .get_bode_sample(frequency_hz, SAMPLE_RATE)
+            .norm()
+            .log10()
+    }
+
+    fn log_grid(start_hz: f64, end_hz: f64) -> impl Iterator<Item = f64> {
+        (0..200).map(move |i| start_hz * (end_hz / start_hz).powf(i as f64 / 199.0))
+    }
+
+    #[test]
+    fn test_cascade_butterworth() {
+        let half_power_db = -10.0 * 2.0f64.log10();
+        for order in 1..=MAX_CASCADE_ORDER {
+            for kind in [FilterKind::LowPass, FilterKind::HighPass] {
+                let design =
+                    CascadeDesign::new(FilterFamily::Butterworth, kind, order, 1000.0, SAMPLE_RATE)
+                        .unwrap();
+                assert_eq!(design.order(), order);
+                assert_eq!(design.first_order().is_some(), order % 2 == 1);
+                assert!((db(&design, 1000.0) - half_power_db).abs() < 1e-6);
+            }
+        }
+
+        // Matches the dedicated Butterworth sections
+        let design = CascadeDesign::<f64>::new(
+            FilterFamily::Butterworth,
+            FilterKind::HighPass,
+            6,
+            300.0,
+            SAMPLE_RATE,
+        )
+        .unwrap();
+        let expected = crate::butterworth::butterworth_highpass(6, 300.0, SAMPLE_RATE);
+        for (section, expected) in design.sections().iter().zip(&expected) {
+            assert!((section.k - expected.k).abs() < 1e-12);
+            assert!((section.g - expected.g).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_cascade_chebyshev_ripple() {
+        for ripple_db in [0.1, 0.5, 1.0, 3.0] {
+            for order in 1..=8 {
+                let family = FilterFamily::ChebyshevI { ripple_db };
+                let lowpass =
+                    CascadeDesign::new(family, FilterKind::LowPass, order, 2000.0, SAMPLE_RATE)
+                        .unwrap();
+                for f in log_grid(10.0, 2000.0) {
+                    let level = db(&lowpass, f);
+                    assert!(level < 1e-6 && level > -ripple_db - 1e-6);
+                }
+                assert!((db(&lowpass, 2000.0) + ripple_db).abs() < 1e-6);
+
+                let highpass =
+                    CascadeDesign::new(family, FilterKind::HighPass, order, 200.0, SAMPLE_RATE)
+                        .unwrap();
+                for f in log_grid(200.0, 20000.0) {
+                    let level = db(&highpass, f);
+                    assert!(level < 1e-6 && level > -ripple_db - 1e-6);
+                }
+            }
+        }
+
+        // Steeper than Butterworth just past the cutoff
+        let chebyshev = CascadeDesign::new(
+            FilterFamily::ChebyshevI { ripple_db: 1.0 },
+            FilterKind::LowPass,
+            4,
+            1000.0,
+            SAMPLE_RATE,
+        )
+        .unwrap();
+        let butterworth = CascadeDesign::new(
+            FilterFamily::Butterworth,
+            FilterKind::LowPass,
+            4,
+            1000.0,
+            SAMPLE_RATE,
+        )
+        .unwrap();
+        assert!(db(&chebyshev, 2000.0) < db(&butterworth, 2000.0) - 8.0);
+    }
+
+    #[test]
+    fn test_cascade_bessel_group_delay() {
+        // Spread of the group delay over the lower half of the passband, relative to its value at DC
+        let delay_spread = |family: FilterFamily<f64>, order: usize| {
+            let design =
+                CascadeDesign::new(family, FilterKind::LowPass, order, 1000.0, SAMPLE_RATE)
+                    .unwrap();
+            let dc = design.group_delay_at(0.0, SAMPLE_RATE);
+            log_grid(10.0, 500.0)
+                .map(|f| (design.group_delay_at(f, SAMPLE_RATE) - dc).abs() / dc)
+                .fold(0.0f64, f64::max)
+        };
+        for order in 2..=8 {
+            let bessel = delay_spread(FilterFamily::Bessel, order);
+            let butterworth = delay_spread(FilterFamily::Butterworth, order);
+            assert!(bessel < butterworth / 2.0);
+        }
+
+        // The phase normalized fourth order one from the usual tables
+        let design = CascadeDesign::<f64>::new(
+            FilterFamily::Bessel,
+            FilterKind::LowPass,
+            4,
+            1000.0,
+            SAMPLE_RATE,
+        )
+        .unwrap();
+        let q_values: Vec<f64> = design.sections().iter().map(|s| 1.0 / s.k).collect();
+        assert!((q_values[0] - 0.5219).abs() < 1e-3);
+        assert!((q_values[1] - 0.8055).abs() < 1e-3);
+        assert!(CascadeDesign::<f64>::new(
+            FilterFamily::Bessel,
+            FilterKind::HighPass,
+            5,
+            1000.0,
+            SAMPLE_RATE
+        )
+        .unwrap()
+        .first_order()
+        .is_some());
+    }
+
+    #[test]
+    fn test_cascade_invalid_sample_rate() {
+        for sample_rate_hz in [0.0, -48000.0, f64::NAN, f64::INFINITY] {
+            let design = CascadeDesign::new(
+                FilterFamily::Butterworth,
+                FilterKind::LowPass,
+                4,
+                1000.0,
+                sample_rate_hz,
+            );
+            assert!(design.is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cascade order must be from 1 to MAX_CASCADE_ORDER")]
+    fn test_cascade_order_out_of_range() {
+        CascadeDesign::new(
+            FilterFamily::Butterworth,
+            FilterKind::LowPass,
+            MAX_CASCADE_ORDER + 1,
+            1000.0,
+            SAMPLE_RATE,
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+use crate::{response_frequencies, BodePoint};
+
+/// N SVFs processed in series
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2Cascade<const N: usize, T = f32> {
+    pub sections: [IIR2<T>; N],
+}
+
+impl<const N: usize, T: Float> IIR2Cascade<N, T> {
+    /// Creates a cascade from the coefficients of each section, in processing order
+    #[inline]
+    pub fn from(coefficients: [IIR2Coefficients<T>; N]) -> Self {
+        IIR2Cascade {
+            sections: coefficients.map(IIR2::from),
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        self.sections
+            .iter_mut()
+            .fold(input_sample, |x, section| section.process(x))
+    }
+
+    /// Processes the buffer in place, one section at a time
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for section in self.sections.iter_mut() {
+            section.process_block(buffer);
+        }
+    }
+
+    #[inline]
+    pub fn update_section(&mut self, index: usize, new_coefficients: IIR2Coefficients<T>) {
+        self.sections[index].update(new_coefficients);
+    }
+
+    /// Clears the internal state of every section, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+
+    /// Combined response of all sections
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.sections
+            .iter()
+            .fold(Complex::new(T::one(), T::zero()), |acc, section| {
+                acc * section.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            })
+    }
+
+    /// Samples the combined response at num_points logarithmically spaced frequencies from
+    /// start_hz to end_hz. start_hz is raised to at least `MIN_RESPONSE_HZ` and end_hz lowered to
+    /// at most `MAX_CUTOFF_RATIO` of the sample rate.
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Vec<BodePoint<T>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| BodePoint::from_complex(f, self.get_bode_sample(f, sample_rate_hz)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    #[test]
+    fn test_iir2_cascade_matches_measured_response() {
+        let sample_rate_hz = 48000.0f64;
+        let mut cascade = IIR2Cascade::from([
+            IIR2Coefficients::highpass(80.0, 0.0, 0.7, sample_rate_hz),
+            IIR2Coefficients::bell(1000.0, 6.0, 2.0, sample_rate_hz),
+            IIR2Coefficients::highshelf(8000.0, -4.0, 0.7, sample_rate_hz),
+        ]);
+        let mut impulse = vec![0.0; 48000];
+        impulse[0] = 1.0;
+        cascade.process_block(&mut impulse);
+
+        for &f in &[50.0, 200.0, 1000.0, 3000.0, 12000.0] {
+            let w = TAU * f / sample_rate_hz;
+            let measured = impulse
+                .iter()
+                .enumerate()
+                .fold(Complex::new(0.0, 0.0), |acc, (n, &h)| {
+                    acc + Complex::from_polar(h, -w * n as f64)
+                });
+            let expected = cascade.get_bode_sample(f, sample_rate_hz);
+            assert!((measured - expected).norm() < 1e-6);
+        }
+
+        cascade.reset();
+        let mut sample_by_sample = IIR2Cascade::from(cascade.sections.map(|s| s.coeffs));
+        let mut block = [1.0, 0.5, -0.25, 0.0, 0.75];
+        let expected: Vec<f64> = block.iter().map(|&x| sample_by_sample.process(x)).collect();
+        cascade.process_block(&mut block);
+        for (y, e) in block.iter().zip(expected.iter()) {
+            assert!((y - e).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_iir2_cascade_update_section() {
+        let sample_rate_hz = 48000.0;
+        let mut cascade = IIR2Cascade::<2>::from([IIR2Coefficients::bypass(); 2]);
+        assert_eq!(cascade.process(0.5), 0.5);
+
+        let lowpass = IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, sample_rate_hz);
+        cascade.update_section(1, lowpass);
+        let response = cascade.frequency_response(20.0, 20000.0, 10, sample_rate_hz);
+        let single = lowpass.frequency_response(20.0, 20000.0, 10, sample_rate_hz);
+        for (a, b) in response.iter().zip(single.iter()) {
+            assert!((a.magnitude_db - b.magnitude_db).abs() < 1e-4);
+        }
+    }
+}
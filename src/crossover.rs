@@ -0,0 +1,479 @@
+use alloc::vec::Vec;
+use core::f64::consts::FRAC_1_SQRT_2;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::cast;
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+/// 4th order Linkwitz-Riley crossover, each band is two Butterworth sections in series. The low
+/// and high outputs are in phase and sum to an all pass.
+#[derive(Copy, Clone, Debug)]
+pub struct LinkwitzRiley4<T = f32> {
+    low: [IIR2<T>; 2],
+    high: [IIR2<T>; 2],
+}
+
+impl<T: Float> LinkwitzRiley4<T> {
+    pub fn new(crossover_hz: T, sample_rate_hz: T) -> Self {
+        let (low, high) = Self::coefficients(crossover_hz, sample_rate_hz);
+        LinkwitzRiley4 {
+            low: [IIR2::from(low); 2],
+            high: [IIR2::from(high); 2],
+        }
+    }
+
+    /// Coefficients of one low pass and one high pass section
+    fn coefficients(
+        crossover_hz: T,
+        sample_rate_hz: T,
+    ) -> (IIR2Coefficients<T>, IIR2Coefficients<T>) {
+        let q = cast::<T>(FRAC_1_SQRT_2);
+        (
+            IIR2Coefficients::lowpass(crossover_hz, T::zero(), q, sample_rate_hz),
+            IIR2Coefficients::highpass(crossover_hz, T::zero(), q, sample_rate_hz),
+        )
+    }
+
+    /// Moves the crossover, keeping the filter states
+    pub fn set_frequency(&mut self, crossover_hz: T, sample_rate_hz: T) {
+        let (low, high) = Self::coefficients(crossover_hz, sample_rate_hz);
+        for (l, h) in self.low.iter_mut().zip(self.high.iter_mut()) {
+            l.update(low);
+            h.update(high);
+        }
+    }
+
+    /// Returns the (low, high) band samples
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> (T, T) {
+        let low = self.low[0].process(input_sample);
+        let low = self.low[1].process(low);
+        let high = self.high[0].process(input_sample);
+        let high = self.high[1].process(high);
+        (low, high)
+    }
+
+    /// Responses of the (low, high) bands
+    pub fn get_bode_samples(&self, frequency_hz: T, sample_rate_hz: T) -> (Complex<T>, Complex<T>) {
+        let low = self.low[0]
+            .coeffs
+            .get_bode_sample(frequency_hz, sample_rate_hz);
+        let high = self.high[0]
+            .coeffs
+            .get_bode_sample(frequency_hz, sample_rate_hz);
+        (low * low, high * high)
+    }
+
+    pub fn reset(&mut self) {
+        for section in self.low.iter_mut().chain(self.high.iter_mut()) {
+            section.reset();
+        }
+    }
+}
+
+/// The sum of the two bands of an LR4 is a second order all pass with Q = 1/sqrt(2)
+fn lr4_allpass<T: Float>(crossover_hz: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+    let q = cast::<T>(FRAC_1_SQRT_2);
+    IIR2Coefficients::allpass(crossover_hz, T::zero(), q, sample_rate_hz)
+}
+
+/// 2nd order Linkwitz-Riley crossover. The high band is inverted so the outputs sum to an all pass
+/// instead of cancelling at the crossover frequency.
+#[derive(Copy, Clone, Debug)]
+pub struct LinkwitzRiley2<T = f32> {
+    low: IIR2<T>,
+    high: IIR2<T>,
+}
+
+impl<T: Float> LinkwitzRiley2<T> {
+    pub fn new(crossover_hz: T, sample_rate_hz: T) -> Self {
+        let (low, high) = Self::coefficients(crossover_hz, sample_rate_hz);
+        LinkwitzRiley2 {
+            low: IIR2::from(low),
+            high: IIR2::from(high),
+        }
+    }
+
+    fn coefficients(
+        crossover_hz: T,
+        sample_rate_hz: T,
+    ) -> (IIR2Coefficients<T>, IIR2Coefficients<T>) {
+        let q = cast::<T>(0.5);
+        let mut high = IIR2Coefficients::highpass(crossover_hz, T::zero(), q, sample_rate_hz);
+        high.m0 = -high.m0;
+        high.m1 = -high.m1;
+        high.m2 = -high.m2;
+        (
+            IIR2Coefficients::lowpass(crossover_hz, T::zero(), q, sample_rate_hz),
+            high,
+        )
+    }
+
+    /// Moves the crossover, keeping the filter states
+    pub fn set_frequency(&mut self, crossover_hz: T, sample_rate_hz: T) {
+        let (low, high) = Self::coefficients(crossover_hz, sample_rate_hz);
+        self.low.update(low);
+        self.high.update(high);
+    }
+
+    /// Returns the (low, high) band samples, the high band has inverted polarity
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> (T, T) {
+        (
+            self.low.process(input_sample),
+            self.high.process(input_sample),
+        )
+    }
+
+    /// Responses of the (low, high) bands
+    pub fn get_bode_samples(&self, frequency_hz: T, sample_rate_hz: T) -> (Complex<T>, Complex<T>) {
+        (
+            self.low
+                .coeffs
+                .get_bode_sample(frequency_hz, sample_rate_hz),
+            self.high
+                .coeffs
+                .get_bode_sample(frequency_hz, sample_rate_hz),
+        )
+    }
+
+    pub fn reset(&mut self) {
+        self.low.reset();
+        self.high.reset();
+    }
+}
+
+/// Splits into low, mid and high bands with two 4th order Linkwitz-Riley crossovers. The upper
+/// crossover turns the mid and high bands into an all pass, so the low band goes through the same
+/// all pass to keep the three bands in phase and summing flat.
+#[derive(Copy, Clone, Debug)]
+pub struct Crossover3Way<T = f32> {
+    low_split: LinkwitzRiley4<T>,
+    high_split: LinkwitzRiley4<T>,
+    compensation: IIR2<T>,
+    sample_rate_hz: T,
+}
+
+impl<T: Float> Crossover3Way<T> {
+    pub fn new(low_hz: T, high_hz: T, sample_rate_hz: T) -> Self {
+        Crossover3Way {
+            low_split: LinkwitzRiley4::new(low_hz, sample_rate_hz),
+            high_split: LinkwitzRiley4::new(high_hz, sample_rate_hz),
+            compensation: IIR2::from(lr4_allpass(high_hz, sample_rate_hz)),
+            sample_rate_hz,
+        }
+    }
+
+    /// Moves both crossovers, keeping the filter states
+    pub fn set_frequencies(&mut self, low_hz: T, high_hz: T) {
+        self.low_split.set_frequency(low_hz, self.sample_rate_hz);
+        self.high_split.set_frequency(high_hz, self.sample_rate_hz);
+        self.compensation
+            .update(lr4_allpass(high_hz, self.sample_rate_hz));
+    }
+
+    /// Returns the (low, mid, high) band samples
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> (T, T, T) {
+        let (low, rest) = self.low_split.process(input_sample);
+        let (mid, high) = self.high_split.process(rest);
+        (self.compensation.process(low), mid, high)
+    }
+
+    /// Responses of the (low, mid, high) bands
+    pub fn get_bode_samples(
+        &self,
+        frequency_hz: T,
+        sample_rate_hz: T,
+    ) -> (Complex<T>, Complex<T>, Complex<T>) {
+        let (low, rest) = self
+            .low_split
+            .get_bode_samples(frequency_hz, sample_rate_hz);
+        let (mid, high) = self
+            .high_split
+            .get_bode_samples(frequency_hz, sample_rate_hz);
+        let compensation = self
+            .compensation
+            .coeffs
+            .get_bode_sample(frequency_hz, sample_rate_hz);
+        (low * compensation, rest * mid, rest * high)
+    }
+
+    pub fn reset(&mut self) {
+        self.low_split.reset();
+        self.high_split.reset();
+        self.compensation.reset();
+    }
+}
+
+/// Splits into any number of bands with 4th order Linkwitz-Riley crossovers, for multiband
+/// processing.
+///
+/// Like `Crossover3Way` the input is split at the lowest crossover first and the high band is
+/// split again at the next one. Each band below the top goes through the all passes of every
+/// crossover above its own, so all bands end up in phase and sum to an all pass.
+#[derive(Clone, Debug)]
+pub struct BandSplitter<T = f32> {
+    splits: Vec<LinkwitzRiley4<T>>,
+    /// The all passes of band i, for crossovers i + 1 and up
+    compensation: Vec<Vec<IIR2<T>>>,
+    crossovers_hz: Vec<T>,
+    sample_rate_hz: T,
+}
+
+impl<T: Float> BandSplitter<T> {
+    /// Creates crossovers_hz.len() + 1 bands. The crossovers should be in ascending order, the
+    /// bands sum flat either way but only then hold contiguous frequency ranges.
+    pub fn new(crossovers_hz: &[T], sample_rate_hz: T) -> Self {
+        BandSplitter {
+            splits: crossovers_hz
+                .iter()
+                .map(|&hz| LinkwitzRiley4::new(hz, sample_rate_hz))
+                .collect(),
+            compensation: (0..crossovers_hz.len())
+                .map(|band| {
+                    crossovers_hz[band + 1..]
+                        .iter()
+                        .map(|&hz| IIR2::from(lr4_allpass(hz, sample_rate_hz)))
+                        .collect()
+                })
+                .collect(),
+            crossovers_hz: crossovers_hz.to_vec(),
+            sample_rate_hz,
+        }
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.splits.len() + 1
+    }
+
+    pub fn crossovers_hz(&self) -> &[T] {
+        &self.crossovers_hz
+    }
+
+    /// Moves crossover index, keeping the filter states
+    ///
+    /// # Panics
+    ///
+    /// Panics if index is not below the number of crossovers
+    pub fn set_crossover(&mut self, index: usize, crossover_hz: T) {
+        self.crossovers_hz[index] = crossover_hz;
+        self.splits[index].set_frequency(crossover_hz, self.sample_rate_hz);
+        let allpass = lr4_allpass(crossover_hz, self.sample_rate_hz);
+        for (band, compensation) in self.compensation[..index].iter_mut().enumerate() {
+            compensation[index - band - 1].update(allpass);
+        }
+    }
+
+    /// Writes the sample of each band, lowest first, to bands
+    ///
+    /// # Panics
+    ///
+    /// Panics if bands.len() is not `num_bands`
+    #[inline]
+    pub fn process(&mut self, input_sample: T, bands: &mut [T]) {
+        assert_eq!(bands.len(), self.num_bands());
+        let mut rest = input_sample;
+        for ((split, compensation), band) in self
+            .splits
+            .iter_mut()
+            .zip(self.compensation.iter_mut())
+            .zip(bands.iter_mut())
+        {
+            let (low, high) = split.process(rest);
+            *band = compensation
+                .iter_mut()
+                .fold(low, |x, allpass| allpass.process(x));
+            rest = high;
+        }
+        bands[self.splits.len()] = rest;
+    }
+
+    /// Responses of the bands, lowest first
+    pub fn get_bode_samples(&self, frequency_hz: T, sample_rate_hz: T) -> Vec<Complex<T>> {
+        let mut responses = Vec::with_capacity(self.num_bands());
+        let mut rest = Complex::new(T::one(), T::zero());
+        for (split, compensation) in self.splits.iter().zip(&self.compensation) {
+            let (low, high) = split.get_bode_samples(frequency_hz, sample_rate_hz);
+            responses.push(compensation.iter().fold(rest * low, |x, allpass| {
+                x * allpass.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            }));
+            rest = rest * high;
+        }
+        responses.push(rest);
+        responses
+    }
+
+    pub fn reset(&mut self) {
+        for split in self.splits.iter_mut() {
+            split.reset();
+        }
+        for allpass in self.compensation.iter_mut().flatten() {
+            allpass.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_frequencies;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    #[test]
+    fn test_linkwitz_riley4_sums_flat() {
+        let sample_rate_hz = 48000.0;
+        let crossover = LinkwitzRiley4::new(1000.0, sample_rate_hz);
+        for f in log_frequencies(20.0, 20000.0, 200) {
+            let (low, high) = crossover.get_bode_samples(f, sample_rate_hz);
+            assert!((20.0 * (low + high).norm().log10()).abs() < 0.1);
+        }
+        let (low, high) = crossover.get_bode_samples(1000.0, sample_rate_hz);
+        assert!((20.0 * low.norm().log10() + 6.02).abs() < 0.01);
+        assert!((20.0 * high.norm().log10() + 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_linkwitz_riley4_process() {
+        let sample_rate_hz = 48000.0;
+        let mut crossover = LinkwitzRiley4::new(1000.0f32, sample_rate_hz);
+        let coeffs = LinkwitzRiley4::<f32>::coefficients(1000.0, sample_rate_hz);
+        let mut low_sections = [IIR2::from(coeffs.0); 2];
+
+        // The summed impulse response of an all pass keeps all of the energy
+        let mut energy = 0.0;
+        for i in 0..48000 {
+            let input = if i == 0 { 1.0 } else { 0.0 };
+            let (low, high) = crossover.process(input);
+            energy += (low + high) * (low + high);
+        }
+        assert!((energy - 1.0).abs() < 1e-3);
+
+        crossover.reset();
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            let expected = low_sections[0].process(x);
+            let expected = low_sections[1].process(expected);
+            assert_eq!(crossover.process(x).0, expected);
+        }
+    }
+
+    #[test]
+    fn test_crossover_3way() {
+        let sample_rate_hz = 48000.0;
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        let mut crossover = Crossover3Way::new(200.0, 4000.0, sample_rate_hz);
+        for _ in 0..2 {
+            for f in log_frequencies(20.0, 20000.0, 200) {
+                let (low, mid, high) = crossover.get_bode_samples(f, sample_rate_hz);
+                assert!(db(low + mid + high).abs() < 0.1);
+            }
+            crossover.set_frequencies(300.0, 3000.0);
+        }
+
+        let (low, mid, _) = crossover.get_bode_samples(300.0, sample_rate_hz);
+        assert!((db(low) + 6.02).abs() < 0.1);
+        assert!((db(mid) + 6.02).abs() < 0.1);
+        let (_, mid, high) = crossover.get_bode_samples(3000.0, sample_rate_hz);
+        assert!((db(mid) + 6.02).abs() < 0.1);
+        assert!((db(high) + 6.02).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_crossover_3way_process() {
+        let mut crossover = Crossover3Way::new(200.0f32, 4000.0, 48000.0);
+        let mut energy = 0.0;
+        for i in 0..48000 {
+            let input = if i == 0 { 1.0 } else { 0.0 };
+            let (low, mid, high) = crossover.process(input);
+            energy += (low + mid + high) * (low + mid + high);
+        }
+        assert!((energy - 1.0).abs() < 1e-3);
+        crossover.reset();
+        assert_eq!(crossover.process(0.0), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_linkwitz_riley2_sums_flat() {
+        let sample_rate_hz = 48000.0;
+        let mut crossover = LinkwitzRiley2::new(1000.0, sample_rate_hz);
+        for f in log_frequencies(20.0, 20000.0, 200) {
+            let (low, high) = crossover.get_bode_samples(f, sample_rate_hz);
+            assert!((20.0 * (low + high).norm().log10()).abs() < 0.1);
+        }
+
+        crossover.set_frequency(200.0, sample_rate_hz);
+        let (low, high) = crossover.get_bode_samples(200.0, sample_rate_hz);
+        assert!((20.0 * low.norm().log10() + 6.02).abs() < 0.01);
+        assert!((20.0 * high.norm().log10() + 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_band_splitter_sums_flat() {
+        let sample_rate_hz = 48000.0;
+        let crossover_sets: [&[f64]; 3] = [&[1000.0], &[150.0, 2500.0], &[100.0, 800.0, 6000.0]];
+        for crossovers_hz in crossover_sets {
+            let mut splitter = BandSplitter::new(crossovers_hz, sample_rate_hz);
+            assert_eq!(splitter.num_bands(), crossovers_hz.len() + 1);
+
+            // Recombined impulse response, measured at log spaced frequencies
+            let mut bands = vec![0.0; splitter.num_bands()];
+            let impulse_response: Vec<f64> = (0..48000)
+                .map(|i| {
+                    splitter.process(if i == 0 { 1.0 } else { 0.0 }, &mut bands);
+                    bands.iter().sum()
+                })
+                .collect();
+            for f in log_frequencies(20.0, 20000.0, 100) {
+                let w = core::f64::consts::TAU * f / sample_rate_hz;
+                let measured = impulse_response
+                    .iter()
+                    .enumerate()
+                    .fold(Complex::new(0.0, 0.0), |acc, (n, &h)| {
+                        acc + Complex::from_polar(h, -w * n as f64)
+                    });
+                assert!((20.0 * measured.norm().log10()).abs() < 0.2);
+            }
+
+            // Each band is -6 dB at its edges
+            for (index, &hz) in crossovers_hz.iter().enumerate() {
+                let responses = splitter.get_bode_samples(hz, sample_rate_hz);
+                assert!((20.0 * responses[index].norm().log10() + 6.02).abs() < 0.1);
+                assert!((20.0 * responses[index + 1].norm().log10() + 6.02).abs() < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_band_splitter_set_crossover() {
+        let sample_rate_hz = 48000.0;
+        let mut splitter = BandSplitter::new(&[100.0, 800.0, 6000.0], sample_rate_hz);
+        splitter.set_crossover(1, 1200.0);
+        splitter.set_crossover(2, 9000.0);
+        assert_eq!(splitter.crossovers_hz(), &[100.0, 1200.0, 9000.0]);
+
+        let fresh = BandSplitter::new(&[100.0, 1200.0, 9000.0], sample_rate_hz);
+        for f in log_frequencies(20.0, 20000.0, 50) {
+            let responses = splitter.get_bode_samples(f, sample_rate_hz);
+            let sum: Complex<f64> = responses.iter().sum();
+            assert!((20.0 * sum.norm().log10()).abs() < 0.1);
+            for (a, b) in responses
+                .iter()
+                .zip(fresh.get_bode_samples(f, sample_rate_hz))
+            {
+                assert!((a - b).norm() < 1e-12);
+            }
+        }
+
+        let mut bands = [1.0; 4];
+        splitter.process(0.5, &mut bands);
+        splitter.reset();
+        splitter.process(0.0, &mut bands);
+        assert_eq!(bands, [0.0; 4]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Filters as `dasp_signal::Signal` adapters.
+//!
+//! `SignalFilterExt::filtered` wraps a signal so each frame goes through a filter. Mono signals
+//! work with anything implementing `Filter` of the same sample type, stereo `[T; 2]` frames with
+//! `IIR2Stereo` and `[T; N]` frames with `IIR2Multi`, where all channels share one set of
+//! coefficients.
+//!
+//! ```
+//! use basic_audio_filters::dasp::SignalFilterExt;
+//! use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+//! use dasp_signal::{self as signal, Signal};
+//!
+//! let coeffs = IIR2Coefficients::<f64>::lowpass(500.0, 0.0, 0.707, 48000.0);
+//! let block: Vec<f64> = signal::rate(48000.0)
+//!     .const_hz(100.0)
+//!     .sine()
+//!     .filtered(IIR2::from(coeffs))
+//!     .take(480)
+//!     .collect();
+//! ```
+
+use dasp_frame::Frame;
+use dasp_signal::Signal;
+use num_traits::Float;
+
+use crate::filter::Filter;
+use crate::multi_iir::IIR2Multi;
+use crate::stereo_iir::IIR2Stereo;
+
+/// A filter that processes whole dasp frames of type F
+pub trait FrameFilter<F: Frame> {
+    fn process_frame(&mut self, frame: F) -> F;
+}
+
+impl<T: Float + Frame, F: Filter<Sample = T>> FrameFilter<T> for F {
+    #[inline]
+    fn process_frame(&mut self, frame: T) -> T {
+        self.process(frame)
+    }
+}
+
+impl<T: Float> FrameFilter<[T; 2]> for IIR2Stereo<T>
+where
+    [T; 2]: Frame,
+{
+    #[inline]
+    fn process_frame(&mut self, frame: [T; 2]) -> [T; 2] {
+        let (left, right) = self.process(frame[0], frame[1]);
+        [left, right]
+    }
+}
+
+impl<const N: usize, T: Float> FrameFilter<[T; N]> for IIR2Multi<N, T>
+where
+    [T; N]: Frame,
+{
+    #[inline]
+    fn process_frame(&mut self, frame: [T; N]) -> [T; N] {
+        self.process(frame)
+    }
+}
+
+/// Signal adapter returned by `SignalFilterExt::filtered`
+#[derive(Clone, Debug)]
+pub struct FilteredSignal<S, F> {
+    signal: S,
+    filter: F,
+}
+
+impl<S, F> FilteredSignal<S, F> {
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// Access to the filter, for changing its coefficients while the signal runs
+    pub fn filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// The source signal and the filter
+    pub fn into_inner(self) -> (S, F) {
+        (self.signal, self.filter)
+    }
+}
+
+impl<S: Signal, F: FrameFilter<S::Frame>> Signal for FilteredSignal<S, F> {
+    type Frame = S::Frame;
+
+    #[inline]
+    fn next(&mut self) -> Self::Frame {
+        self.filter.process_frame(self.signal.next())
+    }
+
+    #[inline]
+    fn is_exhausted(&self) -> bool {
+        self.signal.is_exhausted()
+    }
+}
+
+pub trait SignalFilterExt: Signal + Sized {
+    /// Passes every frame of the signal through filter
+    fn filtered<F: FrameFilter<Self::Frame>>(self, filter: F) -> FilteredSignal<Self, F> {
+        FilteredSignal {
+            signal: self,
+            filter,
+        }
+    }
+}
+
+impl<S: Signal> SignalFilterExt for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::{IIR2Coefficients, IIR2};
+    use dasp_signal as signal;
+
+    fn peak(signal: &mut impl Signal<Frame = f64>, skip: usize, length: usize) -> f64 {
+        (0..skip).for_each(|_| {
+            signal.next();
+        });
+        (0..length).fold(0.0, |m, _| m.max(signal.next().abs()))
+    }
+
+    #[test]
+    fn test_dasp_notch_mono() {
+        let notch = IIR2Coefficients::<f64>::notch(1000.0, 0.0, 2.0, 48000.0);
+        let mut filtered = signal::rate(48000.0)
+            .const_hz(1000.0)
+            .sine()
+            .filtered(IIR2::from(notch));
+        assert!(peak(&mut filtered, 4800, 4800) < 1e-3);
+
+        // Off the notch the sine passes
+        let mut filtered = signal::rate(48000.0)
+            .const_hz(200.0)
+            .sine()
+            .filtered(IIR2::from(notch));
+        assert!(peak(&mut filtered, 4800, 4800) > 0.95);
+    }
+
+    #[test]
+    fn test_dasp_notch_stereo() {
+        let notch = IIR2Coefficients::<f64>::notch(1000.0, 0.0, 2.0, 48000.0);
+        let left = signal::rate(48000.0).const_hz(1000.0).sine();
+        let right = signal::rate(48000.0).const_hz(3000.0).sine();
+        let stereo = signal::from_iter(left.zip_map(right, |l, r| [l, r]).until_exhausted());
+        let mut filtered = stereo.filtered(IIR2Stereo::from(notch));
+
+        let frames: Vec<[f64; 2]> = filtered.by_ref().take(9600).collect();
+        let tail = &frames[4800..];
+        assert!(tail.iter().all(|frame| frame[0].abs() < 1e-3));
+        assert!(tail.iter().any(|frame| frame[1].abs() > 0.5));
+
+        // The multichannel filter handles the same frames identically
+        let mut multi = IIR2Multi::<2, f64>::from(notch);
+        let mut stereo = IIR2Stereo::from(notch);
+        for i in 0..64 {
+            let frame = [(i % 5) as f64 - 2.0, (i % 3) as f64];
+            assert_eq!(multi.process_frame(frame), stereo.process_frame(frame));
+        }
+    }
+}
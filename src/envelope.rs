@@ -0,0 +1,97 @@
+use num_traits::Float;
+
+use crate::first_order_iir::{IIR1Coefficients, IIR1};
+use crate::smoother::time_constant_coefficients;
+
+/// Peak envelope follower. The rectified input is smoothed by a one pole low pass that uses the
+/// attack time constant while the input is above the envelope and the release time constant
+/// while it is below.
+#[derive(Copy, Clone, Debug)]
+pub struct EnvelopeFollower<T = f32> {
+    filter: IIR1<T>,
+    attack: IIR1Coefficients<T>,
+    release: IIR1Coefficients<T>,
+    envelope: T,
+}
+
+impl<T: Float> EnvelopeFollower<T> {
+    pub fn new(attack_ms: T, release_ms: T, sample_rate_hz: T) -> Self {
+        let attack = time_constant_coefficients(attack_ms, sample_rate_hz);
+        EnvelopeFollower {
+            filter: IIR1::from(attack),
+            attack,
+            release: time_constant_coefficients(release_ms, sample_rate_hz),
+            envelope: T::zero(),
+        }
+    }
+
+    /// Changes the time constants, keeping the current envelope
+    pub fn set_times(&mut self, attack_ms: T, release_ms: T, sample_rate_hz: T) {
+        self.attack = time_constant_coefficients(attack_ms, sample_rate_hz);
+        self.release = time_constant_coefficients(release_ms, sample_rate_hz);
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let rectified = input_sample.abs();
+        self.filter.coeffs = if rectified > self.envelope {
+            self.attack
+        } else {
+            self.release
+        };
+        self.envelope = self.filter.process(rectified);
+        self.envelope
+    }
+
+    /// Last value returned by `process`
+    #[inline]
+    pub fn envelope(&self) -> T {
+        self.envelope
+    }
+
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.envelope = T::zero();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    #[test]
+    fn test_envelope_follower() {
+        let sample_rate_hz = 48000.0;
+        let (attack_ms, release_ms) = (10.0, 100.0);
+        let mut follower = EnvelopeFollower::<f64>::new(attack_ms, release_ms, sample_rate_hz);
+
+        // 0.5 s of a 2 kHz sine followed by silence
+        let gate = 24000;
+        let envelope: Vec<f64> = (0..48000)
+            .map(|i| {
+                let x = if i < gate {
+                    (TAU * 2000.0 * i as f64 / sample_rate_hz).sin()
+                } else {
+                    0.0
+                };
+                follower.process(x)
+            })
+            .collect();
+
+        let held = envelope[gate - 1];
+        let crossing = |level: f64| envelope.iter().position(|&e| e >= level * held).unwrap();
+        let rise_ms = (crossing(0.9) - crossing(0.1)) as f64 * 1000.0 / sample_rate_hz;
+        // A one pole rises from 10% to 90% in ln(9) time constants. On a sine the attack only runs
+        // while the rectified input is above the envelope, which stretches the rise somewhat.
+        let expected_ms = attack_ms * 9.0f64.ln();
+        assert!(rise_ms > expected_ms && rise_ms < expected_ms * 2.0);
+
+        let release_samples = (release_ms * sample_rate_hz / 1000.0) as usize;
+        let decayed = envelope[gate - 1 + release_samples] / held;
+        assert!((decayed - (-1.0f64).exp()).abs() < 0.01);
+
+        follower.reset();
+        assert_eq!(follower.process(0.0), 0.0);
+    }
+}
@@ -0,0 +1,209 @@
+//! C interface to `IIR2`, for use from C and C++ hosts.
+//!
+//! A filter is an opaque `RbafIIR2` handle created by one of the `rbaf_iir2_new_*` functions and
+//! owned by the caller until it is passed to `rbaf_iir2_free`. Every constructor of
+//! `IIR2Coefficients` that takes a cutoff, gain and Q has a `new` and an `update` function with
+//! the same arguments in the same order. The header is `include/basic_audio_filters.h`. It is
+//! generated from the macro expanded crate, which needs nightly, with
+//! `RUSTUP_TOOLCHAIN=nightly cbindgen --config cbindgen.toml -o include/basic_audio_filters.h`
+//! and CI checks that it is up to date.
+//!
+//! Build the library for linking with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`).
+//!
+//! No panic unwinds into the caller. Constructors return null if one happens, `rbaf_iir2_process`
+//! returns 0, and the other functions leave the filter as it was. Functions taking a handle do
+//! nothing when it is null, with `rbaf_iir2_process` returning its input unchanged.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+/// Opaque handle to a second order filter processing `float` samples
+pub struct RbafIIR2(IIR2);
+
+fn new_handle(design: impl FnOnce() -> IIR2Coefficients) -> *mut RbafIIR2 {
+    catch_unwind(AssertUnwindSafe(|| {
+        Box::into_raw(Box::new(RbafIIR2(IIR2::from(design()))))
+    }))
+    .unwrap_or(core::ptr::null_mut())
+}
+
+/// Runs f on the filter behind handle, if there is one and f does not panic
+///
+/// # Safety
+/// handle has to be null or a live pointer from one of the constructors
+unsafe fn with_filter<R>(handle: *mut RbafIIR2, f: impl FnOnce(&mut IIR2) -> R) -> Option<R> {
+    let filter = &mut handle.as_mut()?.0;
+    catch_unwind(AssertUnwindSafe(|| f(filter))).ok()
+}
+
+/// Defines the `new` and `update` functions for constructors of `IIR2Coefficients` taking a
+/// cutoff, gain and Q
+macro_rules! design_functions {
+    ($($design:ident: $new:ident, $update:ident;)*) => {
+        $(
+            #[doc = concat!(
+                " Creates a filter with `IIR2Coefficients::", stringify!($design), "`"
+            )]
+            #[no_mangle]
+            pub extern "C" fn $new(
+                cutoff_hz: f32,
+                gain_db: f32,
+                q_value: f32,
+                sample_rate_hz: f32,
+            ) -> *mut RbafIIR2 {
+                new_handle(|| {
+                    IIR2Coefficients::$design(cutoff_hz, gain_db, q_value, sample_rate_hz)
+                })
+            }
+
+            #[doc = concat!(
+                " Switches to `IIR2Coefficients::", stringify!($design), "`, keeping the state"
+            )]
+            ///
+            /// # Safety
+            /// handle has to be null or a live pointer from one of the constructors
+            #[no_mangle]
+            pub unsafe extern "C" fn $update(
+                handle: *mut RbafIIR2,
+                cutoff_hz: f32,
+                gain_db: f32,
+                q_value: f32,
+                sample_rate_hz: f32,
+            ) {
+                let coeffs =
+                    || IIR2Coefficients::$design(cutoff_hz, gain_db, q_value, sample_rate_hz);
+                with_filter(handle, |filter| filter.update(coeffs()));
+            }
+        )*
+    };
+}
+
+design_functions! {
+    lowpass: rbaf_iir2_new_lowpass, rbaf_iir2_update_lowpass;
+    highpass: rbaf_iir2_new_highpass, rbaf_iir2_update_highpass;
+    bandpass: rbaf_iir2_new_bandpass, rbaf_iir2_update_bandpass;
+    notch: rbaf_iir2_new_notch, rbaf_iir2_update_notch;
+    allpass: rbaf_iir2_new_allpass, rbaf_iir2_update_allpass;
+    lowshelf: rbaf_iir2_new_lowshelf, rbaf_iir2_update_lowshelf;
+    highshelf: rbaf_iir2_new_highshelf, rbaf_iir2_update_highshelf;
+    bell: rbaf_iir2_new_bell, rbaf_iir2_update_bell;
+}
+
+/// Creates a filter that passes its input unchanged
+#[no_mangle]
+pub extern "C" fn rbaf_iir2_new_bypass() -> *mut RbafIIR2 {
+    new_handle(IIR2Coefficients::bypass)
+}
+
+/// Filters a single sample
+///
+/// # Safety
+/// handle has to be null or a live pointer from one of the constructors
+#[no_mangle]
+pub unsafe extern "C" fn rbaf_iir2_process(handle: *mut RbafIIR2, input: f32) -> f32 {
+    if handle.is_null() {
+        return input;
+    }
+    with_filter(handle, |filter| filter.process(input)).unwrap_or(0.0)
+}
+
+/// Filters len samples at samples in place
+///
+/// # Safety
+/// handle has to be null or a live pointer from one of the constructors, and samples has to
+/// point to len writable floats unless it is null
+#[no_mangle]
+pub unsafe extern "C" fn rbaf_iir2_process_block(
+    handle: *mut RbafIIR2,
+    samples: *mut f32,
+    len: usize,
+) {
+    if samples.is_null() || len == 0 {
+        return;
+    }
+    let buffer = core::slice::from_raw_parts_mut(samples, len);
+    with_filter(handle, |filter| filter.process_block(buffer));
+}
+
+/// Clears the state of the filter, keeping its coefficients
+///
+/// # Safety
+/// handle has to be null or a live pointer from one of the constructors
+#[no_mangle]
+pub unsafe extern "C" fn rbaf_iir2_reset(handle: *mut RbafIIR2) {
+    with_filter(handle, |filter| filter.reset());
+}
+
+/// Destroys a filter, the handle must not be used afterwards. Null is ignored.
+///
+/// # Safety
+/// handle has to be null or a live pointer from one of the constructors, and is freed only once
+#[no_mangle]
+pub unsafe extern "C" fn rbaf_iir2_free(handle: *mut RbafIIR2) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_round_trip() {
+        let handle = rbaf_iir2_new_bell(1000.0, 6.0, 1.0, 48000.0);
+        assert!(!handle.is_null());
+        let mut filter = IIR2::from(IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0));
+
+        let input: Vec<f32> = (0..256)
+            .map(|i| ((i * 37) % 17) as f32 / 17.0 - 0.5)
+            .collect();
+        let mut block = input.clone();
+        unsafe {
+            rbaf_iir2_process_block(handle, block.as_mut_ptr(), block.len());
+        }
+        for (x, y) in input.iter().zip(&block) {
+            assert_eq!(*y, filter.process(*x));
+        }
+
+        unsafe {
+            rbaf_iir2_update_lowpass(handle, 200.0, 0.0, 0.707, 48000.0);
+        }
+        filter.update(IIR2Coefficients::lowpass(200.0, 0.0, 0.707, 48000.0));
+        for x in &input {
+            assert_eq!(unsafe { rbaf_iir2_process(handle, *x) }, filter.process(*x));
+        }
+
+        unsafe {
+            rbaf_iir2_reset(handle);
+        }
+        filter.reset();
+        assert_eq!(
+            unsafe { rbaf_iir2_process(handle, 1.0) },
+            filter.process(1.0)
+        );
+        unsafe {
+            rbaf_iir2_free(handle);
+        }
+    }
+
+    #[test]
+    fn test_ffi_null_handles() {
+        let mut block = [0.25f32; 4];
+        unsafe {
+            assert_eq!(rbaf_iir2_process(core::ptr::null_mut(), 0.5), 0.5);
+            rbaf_iir2_process_block(core::ptr::null_mut(), block.as_mut_ptr(), block.len());
+            rbaf_iir2_update_bell(core::ptr::null_mut(), 1000.0, 6.0, 1.0, 48000.0);
+            rbaf_iir2_reset(core::ptr::null_mut());
+            rbaf_iir2_free(core::ptr::null_mut());
+
+            let handle = rbaf_iir2_new_bypass();
+            rbaf_iir2_process_block(handle, core::ptr::null_mut(), 8);
+            assert_eq!(rbaf_iir2_process(handle, 0.5), 0.5);
+            rbaf_iir2_free(handle);
+        }
+        assert_eq!(block, [0.25; 4]);
+    }
+}
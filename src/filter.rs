@@ -0,0 +1,594 @@
+//! A common interface over the mono filters, for code that is generic over the filter order,
+//! and `FilterChain` for series of filters that are only known at runtime. Sample iterators can
+//! be run through a filter lazily with `FilterIteratorExt::filtered`.
+//!
+//! The sample type is an associated type rather than a parameter, since every filter is built
+//! for exactly one `T`. A chain of trait objects has to pick one, so `f32` and `f64` filters can
+//! not be mixed in the same chain, just like they can not be chained directly.
+//!
+//! ```
+//! use basic_audio_filters::filter::Filter;
+//! use basic_audio_filters::first_order_iir::{IIR1Coefficients, IIR1};
+//! use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+//!
+//! let fs = 48000.0;
+//! let mut chain: Vec<Box<dyn Filter<Sample = f32>>> = vec![
+//!     Box::new(IIR2::from(IIR2Coefficients::highpass(30.0, 0.0, 0.707, fs))),
+//!     Box::new(IIR2::from(IIR2Coefficients::bell(2000.0, -3.0, 1.0, fs))),
+//!     Box::new(IIR1::from(IIR1Coefficients::lowpass(12000.0, 0.0, fs))),
+//! ];
+//!
+//! let mut buffer = [0.5; 64];
+//! for filter in chain.iter_mut() {
+//!     filter.process_block(&mut buffer);
+//! }
+//! chain.iter_mut().for_each(|filter| filter.reset());
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::biquad::Biquad;
+use crate::butterworth::ButterworthCascade;
+use crate::cascade_iir::IIR2Cascade;
+use crate::first_order_iir::IIR1;
+use crate::parametric_eq::ParametricEq;
+use crate::pinking::PinkingFilter;
+use crate::second_order_iir::IIR2;
+use crate::smoothed_iir::{CrossfadingIIR2, CutoffSmoother, SmoothedIIR2};
+use crate::{response_frequencies, soft_clip, BodePoint};
+
+/// A mono filter processing one sample at a time
+pub trait Filter {
+    type Sample: Float;
+
+    fn process(&mut self, input_sample: Self::Sample) -> Self::Sample;
+
+    /// Clears the state, as if no samples had been processed
+    fn reset(&mut self);
+
+    /// Processes the buffer in place, the same as calling `process` on every sample
+    fn process_block(&mut self, buffer: &mut [Self::Sample]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Like `process_block`, with each output sample passed through `soft_clip` to keep it
+    /// within ±ceiling
+    fn process_block_clipped(&mut self, buffer: &mut [Self::Sample], ceiling: Self::Sample) {
+        for sample in buffer.iter_mut() {
+            *sample = soft_clip(self.process(*sample), ceiling);
+        }
+    }
+
+    /// Response at frequency_hz, or `None` for filters that do not expose their coefficients
+    fn bode_sample(
+        &self,
+        _frequency_hz: Self::Sample,
+        _sample_rate_hz: Self::Sample,
+    ) -> Option<Complex<Self::Sample>> {
+        None
+    }
+}
+
+impl<T: Float> Filter for IIR1<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        IIR1::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        IIR1::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(self.coeffs.get_bode_sample(frequency_hz, sample_rate_hz))
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        IIR1::process_block(self, buffer)
+    }
+
+    fn process_block_clipped(&mut self, buffer: &mut [T], ceiling: T) {
+        IIR1::process_block_clipped(self, buffer, ceiling)
+    }
+}
+
+impl<T: Float> Filter for Biquad<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        Biquad::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        Biquad::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(Biquad::get_bode_sample(self, frequency_hz, sample_rate_hz))
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        Biquad::process_block(self, buffer)
+    }
+}
+
+impl<T: Float> Filter for IIR2<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        IIR2::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        IIR2::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(self.coeffs.get_bode_sample(frequency_hz, sample_rate_hz))
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        IIR2::process_block(self, buffer)
+    }
+
+    fn process_block_clipped(&mut self, buffer: &mut [T], ceiling: T) {
+        IIR2::process_block_clipped(self, buffer, ceiling)
+    }
+}
+
+impl<const N: usize, T: Float> Filter for IIR2Cascade<N, T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        IIR2Cascade::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        IIR2Cascade::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(IIR2Cascade::get_bode_sample(
+            self,
+            frequency_hz,
+            sample_rate_hz,
+        ))
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        IIR2Cascade::process_block(self, buffer)
+    }
+}
+
+impl<T: Float> Filter for ButterworthCascade<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        ButterworthCascade::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        ButterworthCascade::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(ButterworthCascade::get_bode_sample(
+            self,
+            frequency_hz,
+            sample_rate_hz,
+        ))
+    }
+}
+
+impl<const N: usize, T: Float> Filter for ParametricEq<N, T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        ParametricEq::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        ParametricEq::reset(self)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        Some(ParametricEq::get_bode_sample(
+            self,
+            frequency_hz,
+            sample_rate_hz,
+        ))
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        ParametricEq::process_block(self, buffer)
+    }
+}
+
+impl<T: Float> Filter for SmoothedIIR2<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        SmoothedIIR2::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        SmoothedIIR2::reset(self)
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        SmoothedIIR2::process_block(self, buffer)
+    }
+}
+
+impl<T: Float> Filter for CrossfadingIIR2<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        CrossfadingIIR2::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        CrossfadingIIR2::reset(self)
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        CrossfadingIIR2::process_block(self, buffer)
+    }
+}
+
+impl<T: Float> Filter for CutoffSmoother<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        CutoffSmoother::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        CutoffSmoother::reset(self)
+    }
+}
+
+impl<T: Float> Filter for PinkingFilter<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        PinkingFilter::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        PinkingFilter::reset(self)
+    }
+}
+
+/// Filters owned as trait objects and processed in series, in the order they were added. The
+/// list can be edited while the chain is in use, removing or moving a filter leaves the state of
+/// every other filter as it was.
+pub struct FilterChain<T = f32> {
+    filters: Vec<Box<dyn Filter<Sample = T> + Send>>,
+}
+
+impl<T: Float> FilterChain<T> {
+    pub fn new() -> Self {
+        FilterChain {
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Adds a filter at the end of the chain
+    pub fn push(&mut self, filter: impl Filter<Sample = T> + Send + 'static) {
+        self.filters.push(Box::new(filter));
+    }
+
+    /// Inserts a filter so it ends up at index, panics if index > len
+    pub fn insert(&mut self, index: usize, filter: impl Filter<Sample = T> + Send + 'static) {
+        self.filters.insert(index, Box::new(filter));
+    }
+
+    /// Takes the filter at index out of the chain, panics if index >= len
+    pub fn remove(&mut self, index: usize) -> Box<dyn Filter<Sample = T> + Send> {
+        self.filters.remove(index)
+    }
+
+    /// Moves the filter at from so it ends up at to, shifting the ones in between
+    pub fn move_filter(&mut self, from: usize, to: usize) {
+        let filter = self.filters.remove(from);
+        self.filters.insert(to, filter);
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        self.filters
+            .iter_mut()
+            .fold(input_sample, |x, filter| filter.process(x))
+    }
+
+    /// Processes the buffer in place, one filter at a time
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for filter in self.filters.iter_mut() {
+            filter.process_block(buffer);
+        }
+    }
+
+    /// Clears the state of every filter
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.reset();
+        }
+    }
+
+    /// Combined response of all filters, `None` if any of them does not expose its response
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        self.filters
+            .iter()
+            .try_fold(Complex::new(T::one(), T::zero()), |acc, filter| {
+                Some(acc * filter.bode_sample(frequency_hz, sample_rate_hz)?)
+            })
+    }
+
+    /// Samples the combined response at num_points logarithmically spaced frequencies from
+    /// start_hz to end_hz, `None` if any filter does not expose its response
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Option<Vec<BodePoint<T>>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| {
+                self.get_bode_sample(f, sample_rate_hz)
+                    .map(|response| BodePoint::from_complex(f, response))
+            })
+            .collect()
+    }
+}
+
+impl<T: Float> Default for FilterChain<T> {
+    fn default() -> Self {
+        FilterChain::new()
+    }
+}
+
+impl<T: Float> Filter for FilterChain<T> {
+    type Sample = T;
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        FilterChain::process(self, input_sample)
+    }
+
+    fn reset(&mut self) {
+        FilterChain::reset(self)
+    }
+
+    fn process_block(&mut self, buffer: &mut [T]) {
+        FilterChain::process_block(self, buffer)
+    }
+
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Option<Complex<T>> {
+        self.get_bode_sample(frequency_hz, sample_rate_hz)
+    }
+}
+
+/// Lazily processes the samples of an iterator, see `FilterIteratorExt::filtered`
+pub struct Filtered<'a, I, F> {
+    iter: I,
+    filter: &'a mut F,
+}
+
+impl<I, F> Iterator for Filtered<'_, I, F>
+where
+    I: Iterator<Item = F::Sample>,
+    F: Filter,
+{
+    type Item = F::Sample;
+
+    #[inline]
+    fn next(&mut self) -> Option<F::Sample> {
+        self.iter.next().map(|x| self.filter.process(x))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I, F> ExactSizeIterator for Filtered<'_, I, F>
+where
+    I: ExactSizeIterator<Item = F::Sample>,
+    F: Filter,
+{
+}
+
+/// Adds `filtered` to every iterator
+pub trait FilterIteratorExt: Iterator + Sized {
+    /// Runs every sample through the filter as it is pulled, like
+    /// `samples.iter().copied().filtered(&mut filter)`. The filter is borrowed, so after the
+    /// iterator is dropped it holds the state after the last sample that was pulled, and can keep
+    /// processing from there.
+    fn filtered<F: Filter<Sample = Self::Item>>(self, filter: &mut F) -> Filtered<'_, Self, F> {
+        Filtered { iter: self, filter }
+    }
+}
+
+impl<I: Iterator> FilterIteratorExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::first_order_iir::IIR1Coefficients;
+    use crate::parametric_eq::EqBand;
+    use crate::second_order_iir::{IIR2Coefficients, IIR2FilterType};
+
+    fn run<F: Filter<Sample = f64>>(filter: &mut F, input: &[f64]) -> Vec<f64> {
+        input.iter().map(|&x| filter.process(x)).collect()
+    }
+
+    #[test]
+    fn test_filter_trait_matches_inherent() {
+        let fs = 48000.0;
+        let input: Vec<f64> = (0..256)
+            .map(|i| ((i * 37) % 101) as f64 / 50.0 - 1.0)
+            .collect();
+        let mut first = IIR1::from(IIR1Coefficients::lowpass(1000.0, 0.0, fs));
+        let mut second = IIR2::from(IIR2Coefficients::bell(1000.0, 6.0, 2.0, fs));
+        let mut first_reference = first;
+        let mut second_reference = second;
+
+        let first_output = run(&mut first, &input);
+        let second_output = run(&mut second, &input);
+        for (i, &x) in input.iter().enumerate() {
+            assert_eq!(first_output[i], first_reference.process(x));
+            assert_eq!(second_output[i], second_reference.process(x));
+        }
+
+        // process_block and reset go through the trait objects too
+        let mut chain: Vec<Box<dyn Filter<Sample = f64>>> = vec![Box::new(first), Box::new(second)];
+        for filter in chain.iter_mut() {
+            filter.reset();
+        }
+        let mut buffer = input.clone();
+        for filter in chain.iter_mut() {
+            filter.process_block(&mut buffer);
+        }
+        let expected = run(
+            &mut IIR2::from(IIR2Coefficients::bell(1000.0, 6.0, 2.0, fs)),
+            &run(
+                &mut IIR1::from(IIR1Coefficients::lowpass(1000.0, 0.0, fs)),
+                &input,
+            ),
+        );
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_filter_chain_edits_keep_state() {
+        let fs = 48000.0;
+        let highpass = IIR2::from(IIR2Coefficients::highpass(80.0, 0.0, 0.707, fs));
+        let bell = IIR2::from(IIR2Coefficients::bell(1000.0, 6.0, 2.0, fs));
+        let shelf = IIR1::from(IIR1Coefficients::highshelf(5000.0, -3.0, fs));
+        let mut chain = FilterChain::new();
+        chain.push(highpass);
+        chain.push(shelf);
+        chain.insert(1, bell);
+        let (mut highpass, mut bell, mut shelf) = (highpass, bell, shelf);
+
+        let mut input = (0..).map(|i: i32| ((i * 37) % 101) as f64 / 50.0 - 1.0);
+        for x in input.by_ref().take(300) {
+            assert_eq!(
+                chain.process(x),
+                shelf.process(bell.process(highpass.process(x)))
+            );
+        }
+
+        // The bell leaves, highpass and shelf carry on from where they were
+        chain.remove(1);
+        for x in input.by_ref().take(300) {
+            assert_eq!(chain.process(x), shelf.process(highpass.process(x)));
+        }
+
+        // The shelf moves in front of the highpass
+        chain.move_filter(1, 0);
+        let mut buffer: Vec<f64> = input.by_ref().take(300).collect();
+        let expected: Vec<f64> = buffer
+            .iter()
+            .map(|&x| highpass.process(shelf.process(x)))
+            .collect();
+        chain.process_block(&mut buffer);
+        assert_eq!(buffer, expected);
+
+        let f = 3000.0;
+        let response = chain.get_bode_sample(f, fs).unwrap();
+        let expected = shelf.coeffs.get_bode_sample(f, fs) * highpass.coeffs.get_bode_sample(f, fs);
+        assert!((response - expected).norm() < 1e-12);
+        assert_eq!(
+            chain
+                .frequency_response(20.0, 20000.0, 10, fs)
+                .unwrap()
+                .len(),
+            10
+        );
+
+        chain.push(PinkingFilter::new(fs).unwrap());
+        assert!(chain.get_bode_sample(f, fs).is_none());
+    }
+
+    #[test]
+    fn test_filter_chain_parametric_eq_response() {
+        let fs = 48000.0;
+        let mut eq = ParametricEq::<2, f64>::new(fs);
+        eq.set_band(
+            1,
+            EqBand {
+                filter_type: IIR2FilterType::Bell,
+                frequency_hz: 1000.0,
+                gain_db: 6.0,
+                q_value: 1.0,
+                enabled: true,
+            },
+        );
+        let highpass = IIR2::from(IIR2Coefficients::highpass(80.0, 0.0, 0.707, fs));
+        let mut chain = FilterChain::new();
+        chain.push(eq);
+        chain.push(highpass);
+        for f in [50.0, 1000.0, 5000.0] {
+            let expected = eq.get_bode_sample(f, fs) * highpass.coeffs.get_bode_sample(f, fs);
+            assert!((chain.get_bode_sample(f, fs).unwrap() - expected).norm() < 1e-12);
+        }
+        let response = eq.get_bode_sample(1000.0, fs);
+        assert!((20.0 * response.norm().log10() - 6.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_filtered_iterator() {
+        let fs = 48000.0;
+        let input: Vec<f64> = (0..500)
+            .map(|i| ((i * 37) % 101) as f64 / 50.0 - 1.0)
+            .collect();
+        let mut filter = IIR2::from(IIR2Coefficients::lowpass(500.0, 0.0, 2.0, fs));
+        let mut first_order = IIR1::from(IIR1Coefficients::highpass(100.0, 0.0, fs));
+        let mut reference = filter;
+        let mut first_order_reference = first_order;
+
+        let filtered = input[..300].iter().copied().filtered(&mut filter);
+        assert_eq!(filtered.len(), 300);
+        let output: Vec<f64> = filtered.filtered(&mut first_order).collect();
+        for (i, &x) in input[..300].iter().enumerate() {
+            assert_eq!(
+                output[i],
+                first_order_reference.process(reference.process(x))
+            );
+        }
+
+        // Both filters pick up where the iterator left them
+        for &x in &input[300..] {
+            assert_eq!(filter.process(x), reference.process(x));
+            assert_eq!(first_order.process(x), first_order_reference.process(x));
+        }
+    }
+}
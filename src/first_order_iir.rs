@@ -1,139 +1,687 @@
-use std::f32::consts::{PI, TAU};
+use alloc::vec::Vec;
+use core::f64::consts::{PI, TAU};
+use core::ops::Deref;
+use core::str::FromStr;
 
 use num_complex::Complex;
+use num_traits::Float;
 
-#[derive(Copy, Clone, Debug)]
-pub struct IIR1Coefficients {
-    pub a: f32,
-    pub g: f32,
-    pub a1: f32,
-    pub m0: f32,
-    pub m1: f32,
+use crate::response;
+use crate::{
+    cast, check_design, clamp_cutoff, fast_tan_parts, fields_approx_eq, flush_denormal,
+    group_delay, max_response_deviation_db, normalize_filter_name, response_frequencies,
+    sanitize_cutoff, sanitize_gain, soft_clip, BodePoint, FilterDesignError, ParseFilterTypeError,
+};
+
+/// Corner frequency of `IIR1::dc_blocker`
+pub const DC_BLOCKER_HZ: f32 = 5.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IIR1FilterType {
+    LowPass,
+    HighPass,
+    AllPass,
+    LowShelf,
+    HighShelf,
+    Tilt,
+}
+
+impl IIR1FilterType {
+    pub const ALL: [IIR1FilterType; 6] = [
+        IIR1FilterType::LowPass,
+        IIR1FilterType::HighPass,
+        IIR1FilterType::AllPass,
+        IIR1FilterType::LowShelf,
+        IIR1FilterType::HighShelf,
+        IIR1FilterType::Tilt,
+    ];
+}
+
+/// Parses names like "LowPass", "low_pass" or "lowpass", ignoring case
+impl FromStr for IIR1FilterType {
+    type Err = ParseFilterTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_filter_name(s).as_str() {
+            "lowpass" => Ok(IIR1FilterType::LowPass),
+            "highpass" => Ok(IIR1FilterType::HighPass),
+            "allpass" => Ok(IIR1FilterType::AllPass),
+            "lowshelf" => Ok(IIR1FilterType::LowShelf),
+            "highshelf" => Ok(IIR1FilterType::HighShelf),
+            "tilt" => Ok(IIR1FilterType::Tilt),
+            _ => Err(ParseFilterTypeError),
+        }
+    }
 }
 
-impl IIR1Coefficients {
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR1Coefficients<T = f32> {
+    pub a: T,
+    pub g: T,
+    pub a1: T,
+    pub m0: T,
+    pub m1: T,
+}
+
+impl<T: Float> IIR1Coefficients<T> {
+    /// Creates coefficients for any filter type, dispatching to the dedicated constructor
+    #[inline]
+    pub fn new(
+        filter_type: IIR1FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        sample_rate_hz: T,
+    ) -> IIR1Coefficients<T> {
+        let constructor = match filter_type {
+            IIR1FilterType::LowPass => IIR1Coefficients::lowpass,
+            IIR1FilterType::HighPass => IIR1Coefficients::highpass,
+            IIR1FilterType::AllPass => IIR1Coefficients::allpass,
+            IIR1FilterType::LowShelf => IIR1Coefficients::lowshelf,
+            IIR1FilterType::HighShelf => IIR1Coefficients::highshelf,
+            IIR1FilterType::Tilt => IIR1Coefficients::tilt,
+        };
+        constructor(cutoff_hz, gain_db, sample_rate_hz)
+    }
+
     #[inline]
-    pub fn get_bode_sample(self, frequency_hz: f32, sample_rate_hz: f32) -> Complex<f32> {
+    pub fn get_bode_sample(self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
         //Use y.norm() for amplitude and y.arg().to_degrees() for phase. Add to combine phase.
 
-        let z = -TAU * frequency_hz / sample_rate_hz;
-        let z = z.cos() + z.sin() * Complex::<f32>::new(0.0, 1.0);
+        let z = -cast::<T>(TAU) * frequency_hz / sample_rate_hz;
+        let z = Complex::new(z.cos(), z.sin());
+
+        let denominator = z * (self.g - T::one()) + self.g + T::one();
+
+        (z + T::one()) * (self.m1 * self.g) / denominator + self.m0
+    }
+
+    /// Evaluates `get_bode_sample` at every frequency in frequencies, writing the results to out.
+    /// Works like `IIR2Coefficients::get_bode_samples`, within 0.01 dB of the scalar method and
+    /// keeping its precision in f32 close to DC and Nyquist.
+    pub fn get_bode_samples(&self, frequencies: &[T], sample_rate_hz: T, out: &mut [Complex<T>]) {
+        assert_eq!(frequencies.len(), out.len());
+        let n0 = (self.m0 + self.m1) * self.g;
+        let angle_per_hz = cast::<T>(PI) / sample_rate_hz;
+
+        for (frequency_hz, out) in frequencies.iter().zip(out.iter_mut()) {
+            let (sin, cos) = fast_tan_parts(angle_per_hz * *frequency_hz);
+            let numerator = Complex::new(n0 * cos, self.m0 * sin);
+            *out = numerator / Complex::new(self.g * cos, sin);
+        }
+    }
+
+    /// Moves coefficients designed at sample_rate_hz to new_sample_rate_hz, see
+    /// `IIR2Coefficients::at_sample_rate`
+    pub fn at_sample_rate(&self, sample_rate_hz: T, new_sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let pi = cast::<T>(PI);
+        let warped_hz = self.g.atan() * sample_rate_hz / pi;
+        let warped_hz = clamp_cutoff(warped_hz, new_sample_rate_hz);
+        let g = (pi * warped_hz / new_sample_rate_hz).tan();
+        IIR1Coefficients {
+            g,
+            a1: g / (T::one() + g),
+            ..*self
+        }
+    }
+
+    /// Frequency where the response falls 3 dB below the passband, see
+    /// `response::effective_cutoff_hz` for how the passband is chosen
+    pub fn effective_cutoff_hz(&self, sample_rate_hz: T) -> Option<T> {
+        response::effective_cutoff_hz(
+            |f| self.get_bode_sample(f, sample_rate_hz).norm(),
+            sample_rate_hz,
+        )
+    }
+
+    /// Pole and zero of the transfer function in the z plane, as `(pole, zero)`. A zero that moves
+    /// to infinity is returned as infinite.
+    pub fn poles_zeros(&self) -> (Complex<T>, Complex<T>) {
+        let pole = (T::one() - self.g) / (T::one() + self.g);
+        let b0 = self.m1 * self.g + self.m0 * (self.g + T::one());
+        let b1 = self.m1 * self.g + self.m0 * (self.g - T::one());
+        let zero = if b0 == T::zero() {
+            T::infinity()
+        } else {
+            -b1 / b0
+        };
+        (Complex::new(pole, T::zero()), Complex::new(zero, T::zero()))
+    }
+
+    /// True if the pole is strictly inside the unit circle, which is exactly when g is positive
+    pub fn is_stable(&self) -> bool {
+        self.is_finite() && self.g > T::zero()
+    }
+
+    /// Interpolates between two coefficient sets, t = 0 returns self and t = 1 returns other.
+    /// g and the mix are interpolated and a1 is re-derived.
+    pub fn lerp(&self, other: &IIR1Coefficients<T>, t: T) -> IIR1Coefficients<T> {
+        let mix = |x: T, y: T| x + (y - x) * t;
+        let g = mix(self.g, other.g);
+        IIR1Coefficients {
+            a: mix(self.a, other.a),
+            g,
+            a1: g / (T::one() + g),
+            m0: mix(self.m0, other.m0),
+            m1: mix(self.m1, other.m1),
+        }
+    }
+
+    /// Returns false if any coefficient is NaN or infinite, e.g. when built from a NaN cutoff
+    pub fn is_finite(&self) -> bool {
+        [self.a, self.g, self.a1, self.m0, self.m1]
+            .iter()
+            .all(|x| x.is_finite())
+    }
+
+    /// Group delay in samples at frequency_hz, divide by the sample rate for seconds
+    pub fn group_delay_at(&self, frequency_hz: T, sample_rate_hz: T) -> T {
+        group_delay(
+            |f| self.get_bode_sample(f, sample_rate_hz),
+            frequency_hz,
+            sample_rate_hz,
+        )
+    }
+
+    /// Whether every coefficient is within tolerance of the one in other, relative to the larger
+    /// of the two, or absolutely for coefficients below 1
+    pub fn approx_eq(&self, other: &IIR1Coefficients<T>, tolerance: T) -> bool {
+        fields_approx_eq(
+            &[self.a, self.g, self.a1, self.m0, self.m1],
+            &[other.a, other.g, other.a1, other.m0, other.m1],
+            tolerance,
+        )
+    }
+
+    /// Worst magnitude difference in dB between the responses of self and other from
+    /// `MIN_RESPONSE_HZ` to `MAX_CUTOFF_RATIO` of the sample rate, with both floored at
+    /// `DEVIATION_FLOOR_DB`. Tells if two sets of coefficients sound the same, even when they
+    /// differ in ways `approx_eq` would flag.
+    pub fn max_response_deviation_db(&self, other: &IIR1Coefficients<T>, sample_rate_hz: T) -> T {
+        max_response_deviation_db(
+            |f| self.get_bode_sample(f, sample_rate_hz),
+            |f| other.get_bode_sample(f, sample_rate_hz),
+            sample_rate_hz,
+        )
+    }
 
-        let denominator = self.g + z * (self.g - 1.0) + 1.0;
+    /// Phase in degrees at frequency_hz, wrapped to -180..180
+    pub fn phase_degrees_at(&self, frequency_hz: T, sample_rate_hz: T) -> T {
+        self.get_bode_sample(frequency_hz, sample_rate_hz)
+            .arg()
+            .to_degrees()
+    }
 
-        let y = self.m0 + (self.m1 * self.g * (z + 1.0)) / denominator;
+    /// Samples the response at num_points logarithmically spaced frequencies from start_hz to
+    /// end_hz. start_hz is raised to at least `MIN_RESPONSE_HZ` and end_hz lowered to at most
+    /// `MAX_CUTOFF_RATIO` of the sample rate.
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Vec<BodePoint<T>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| BodePoint::from_complex(f, self.get_bode_sample(f, sample_rate_hz)))
+            .collect()
+    }
 
-        y
+    #[inline]
+    pub fn empty() -> IIR1Coefficients<T> {
+        IIR1Coefficients {
+            a: T::zero(),
+            g: T::zero(),
+            a1: T::zero(),
+            m0: T::zero(),
+            m1: T::zero(),
+        }
     }
 
+    /// Passes the input through unchanged. The state never moves away from zero and g is chosen
+    /// so `get_bode_sample` stays well defined.
     #[inline]
-    pub fn empty() -> IIR1Coefficients {
+    pub fn bypass() -> IIR1Coefficients<T> {
         IIR1Coefficients {
-            a: 0.0,
-            g: 0.0,
-            a1: 0.0,
-            m0: 0.0,
-            m1: 0.0,
+            a: T::one(),
+            g: T::one(),
+            a1: T::zero(),
+            m0: T::one(),
+            m1: T::zero(),
         }
     }
 
     #[inline]
-    pub fn lowpass(cutoff_hz: f32, _gain_db: f32, sample_rate_hz: f32) -> IIR1Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let a1 = g / (1.0 + g);
-        let m0 = 0.0;
-        let m1 = 1.0;
+    pub fn lowpass(cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let a1 = g / (T::one() + g);
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = T::zero();
+        let m1 = gain;
         IIR1Coefficients { a, g, a1, m0, m1 }
     }
 
     #[inline]
-    pub fn highpass(cutoff_hz: f32, _gain_db: f32, sample_rate_hz: f32) -> IIR1Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let a1 = g / (1.0 + g);
-        let m0 = 1.0;
-        let m1 = -1.0;
+    pub fn highpass(cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let a1 = g / (T::one() + g);
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = gain;
+        let m1 = -gain;
         IIR1Coefficients { a, g, a1, m0, m1 }
     }
 
     #[inline]
-    pub fn allpass(cutoff_hz: f32, _gain_db: f32, sample_rate_hz: f32) -> IIR1Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let a1 = g / (1.0 + g);
-        let m0 = 1.0;
-        let m1 = -2.0;
+    pub fn allpass(cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let a1 = g / (T::one() + g);
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = gain;
+        let m1 = -cast::<T>(2.0) * gain;
         IIR1Coefficients { a, g, a1, m0, m1 }
     }
 
+    /// All pass with its phase at exactly -90 degrees at frequency_hz, falling from 0 at DC to
+    /// -180 at Nyquist. `allpass` is the same filter inverted, going from 180 down to 0 and
+    /// crossing +90 at its cutoff. This is the usual building block for phasers and all pass
+    /// chains like Hilbert transformers, where the -90 degree point is what gets placed.
     #[inline]
-    pub fn lowshelf(cutoff_hz: f32, gain_db: f32, sample_rate_hz: f32) -> IIR1Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 10.0f32.powf(gain_db / 20.0);
-        let g = (PI * cutoff_hz / sample_rate_hz).tan() / (a).sqrt();
-        let a1 = g / (1.0 + g);
-        let m0 = 1.0;
-        let m1 = a - 1.0;
+    pub fn allpass_phase90(frequency_hz: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(frequency_hz) = sanitize_cutoff(frequency_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        // The prewarped cutoff lands the analog -90 degree point exactly on frequency_hz
+        let a = T::one();
+        let g = (cast::<T>(PI) * frequency_hz / sample_rate_hz).tan();
+        let a1 = g / (T::one() + g);
+        let m0 = -T::one();
+        let m1 = cast::<T>(2.0);
         IIR1Coefficients { a, g, a1, m0, m1 }
     }
 
     #[inline]
-    pub fn highshelf(cutoff_hz: f32, gain_db: f32, sample_rate_hz: f32) -> IIR1Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 10.0f32.powf(gain_db / 20.0);
-        let g = (PI * cutoff_hz / sample_rate_hz).tan() * (a).sqrt();
-        let a1 = g / (1.0 + g);
+    pub fn lowshelf(cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan() / (a).sqrt();
+        let a1 = g / (T::one() + g);
+        let m0 = T::one();
+        let m1 = a - T::one();
+        IIR1Coefficients { a, g, a1, m0, m1 }
+    }
+
+    #[inline]
+    pub fn highshelf(cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan() * (a).sqrt();
+        let a1 = g / (T::one() + g);
+        let m0 = a;
+        let m1 = T::one() - a;
+        IIR1Coefficients { a, g, a1, m0, m1 }
+    }
+
+    /// Tilts the spectrum around pivot_hz, with -gain_db/2 at DC and +gain_db/2 at Nyquist.
+    #[inline]
+    pub fn tilt(pivot_hz: T, gain_db: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        let Some(pivot_hz) = sanitize_cutoff(pivot_hz, sample_rate_hz) else {
+            return IIR1Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * pivot_hz / sample_rate_hz).tan() * a;
+        let a1 = g / (T::one() + g);
         let m0 = a;
-        let m1 = 1.0 - a;
+        let m1 = (T::one() - a * a) / a;
         IIR1Coefficients { a, g, a1, m0, m1 }
     }
+
+    /// First order high pass for removing DC offsets. With the default 5 Hz corner, 20 Hz is
+    /// attenuated by 0.26 dB and shifted by +14 degrees, with a 10 Hz corner by 0.97 dB and +26.6
+    /// degrees. See `dc_settle_samples` for how fast an offset is removed.
+    #[inline]
+    pub fn dc_blocker(corner_hz: T, sample_rate_hz: T) -> IIR1Coefficients<T> {
+        IIR1Coefficients::highpass(corner_hz, T::zero(), sample_rate_hz)
+    }
+
+    /// Number of samples after which the output of a high pass driven by a constant input stays
+    /// below level times that input. The output decays as (1 - a1) * ((1 - g) / (1 + g))^n.
+    pub fn dc_settle_samples(&self, level: T) -> usize {
+        let pole = (T::one() - self.g) / (T::one() + self.g);
+        let samples = (level / (T::one() - self.a1)).ln() / pole.ln();
+        samples
+            .ceil()
+            .max(T::zero())
+            .to_usize()
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// Defines `try_*` versions of the constructors that validate their parameters
+macro_rules! checked_constructors {
+    ($($checked:ident => $constructor:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Like `", stringify!($constructor), "`, but returns an error for invalid parameters instead of sanitizing them")]
+            pub fn $checked(
+                cutoff_hz: T,
+                gain_db: T,
+                sample_rate_hz: T,
+            ) -> Result<IIR1Coefficients<T>, FilterDesignError> {
+                check_design(cutoff_hz, gain_db, sample_rate_hz)?;
+                Ok(IIR1Coefficients::$constructor(cutoff_hz, gain_db, sample_rate_hz))
+            }
+        )*
+    };
+}
+
+/// Checked constructors, see the `IIR2Coefficients` equivalents for how the plain constructors
+/// sanitize their parameters
+impl<T: Float> IIR1Coefficients<T> {
+    /// Like `new`, but returns an error for invalid parameters instead of sanitizing them
+    pub fn try_new(
+        filter_type: IIR1FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        sample_rate_hz: T,
+    ) -> Result<IIR1Coefficients<T>, FilterDesignError> {
+        check_design(cutoff_hz, gain_db, sample_rate_hz)?;
+        Ok(IIR1Coefficients::new(
+            filter_type,
+            cutoff_hz,
+            gain_db,
+            sample_rate_hz,
+        ))
+    }
+
+    checked_constructors!(
+        try_lowpass => lowpass,
+        try_highpass => highpass,
+        try_allpass => allpass,
+        try_lowshelf => lowshelf,
+        try_highshelf => highshelf,
+        try_tilt => tilt,
+    );
+}
+
+/// The parameters a set of `IIR1Coefficients` was designed from
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR1Design<T = f32> {
+    pub filter_type: IIR1FilterType,
+    pub cutoff_hz: T,
+    pub gain_db: T,
+    pub sample_rate_hz: T,
+}
+
+impl<T: Float> IIR1Design<T> {
+    #[inline]
+    pub fn coefficients(&self) -> IIR1Coefficients<T> {
+        IIR1Coefficients::new(
+            self.filter_type,
+            self.cutoff_hz,
+            self.gain_db,
+            self.sample_rate_hz,
+        )
+    }
+}
+
+/// Coefficients that remember their design parameters, see `DesignedIIR2`. Derefs to the raw
+/// `IIR1Coefficients`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesignedIIR1<T = f32> {
+    design: IIR1Design<T>,
+    coeffs: IIR1Coefficients<T>,
+}
+
+impl<T: Float> DesignedIIR1<T> {
+    #[inline]
+    pub fn new(filter_type: IIR1FilterType, cutoff_hz: T, gain_db: T, sample_rate_hz: T) -> Self {
+        DesignedIIR1::from_design(IIR1Design {
+            filter_type,
+            cutoff_hz,
+            gain_db,
+            sample_rate_hz,
+        })
+    }
+
+    #[inline]
+    pub fn from_design(design: IIR1Design<T>) -> Self {
+        DesignedIIR1 {
+            design,
+            coeffs: design.coefficients(),
+        }
+    }
+
+    #[inline]
+    pub fn design(&self) -> IIR1Design<T> {
+        self.design
+    }
+
+    #[inline]
+    pub fn coefficients(&self) -> IIR1Coefficients<T> {
+        self.coeffs
+    }
+
+    /// Redesigns the same filter for another sample rate, keeping the requested cutoff
+    #[inline]
+    pub fn with_sample_rate(&self, sample_rate_hz: T) -> Self {
+        DesignedIIR1::from_design(IIR1Design {
+            sample_rate_hz,
+            ..self.design
+        })
+    }
+}
+
+impl<T> Deref for DesignedIIR1<T> {
+    type Target = IIR1Coefficients<T>;
+
+    fn deref(&self) -> &IIR1Coefficients<T> {
+        &self.coeffs
+    }
+}
+
+/// Snapshot of the internal state of an `IIR1`
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR1State<T = f32> {
+    pub ic1eq: T,
 }
 
 /// Internal states and coefficients of the SVF form
 #[derive(Copy, Clone, Debug)]
-pub struct IIR1 {
-    ic1eq: f32,
-    pub coeffs: IIR1Coefficients,
+pub struct IIR1<T = f32> {
+    ic1eq: T,
+    pub coeffs: IIR1Coefficients<T>,
+}
+
+/// The SVF step shared by every processing loop of `IIR1`, so a change to the core arithmetic
+/// reaches all of them and they stay bit identical to each other
+impl<T: Float> IIR1Coefficients<T> {
+    /// Advances the integrator state ic1eq by one input sample and returns the filter output
+    #[inline(always)]
+    pub(crate) fn tick(&self, ic1eq: &mut T, input_sample: T) -> T {
+        let v1 = self.a1 * (input_sample - *ic1eq);
+        let v2 = v1 + *ic1eq;
+        *ic1eq = flush_denormal(v2 + v1);
+
+        self.m0 * input_sample + self.m1 * v2
+    }
 }
 
-impl IIR1 {
+impl<T: Float> IIR1<T> {
     /// Creates a SVF from a set of filter coefficients
     #[inline]
-    pub fn from(coefficients: IIR1Coefficients) -> Self {
+    pub fn from(coefficients: IIR1Coefficients<T>) -> Self {
         IIR1 {
-            ic1eq: 0.0,
+            ic1eq: T::zero(),
             coeffs: coefficients,
         }
     }
 
     #[inline]
-    pub fn process(&mut self, input_sample: f32) -> f32 {
-        let v1 = self.coeffs.a1 * (input_sample - self.ic1eq);
-        let v2 = v1 + self.ic1eq;
-        self.ic1eq = v2 + v1;
+    pub fn process(&mut self, input_sample: T) -> T {
+        self.coeffs.tick(&mut self.ic1eq, input_sample)
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+
+        for sample in buffer.iter_mut() {
+            *sample = coeffs.tick(&mut ic1eq, *sample);
+        }
+
+        self.ic1eq = ic1eq;
+    }
+
+    /// Like `process_block`, with the output passed through `soft_clip` in the same loop so it
+    /// stays within ±ceiling. Samples below `SOFT_CLIP_KNEE` times the ceiling are unchanged.
+    #[inline]
+    pub fn process_block_clipped(&mut self, buffer: &mut [T], ceiling: T) {
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+
+        for sample in buffer.iter_mut() {
+            *sample = soft_clip(coeffs.tick(&mut ic1eq, *sample), ceiling);
+        }
+
+        self.ic1eq = ic1eq;
+    }
+
+    /// Processes input into output, leaving input untouched. Output is identical to calling
+    /// `process` on each sample. Both can not be the same buffer, use `process_block` to process
+    /// in place.
+    ///
+    /// # Panics
+    ///
+    /// If input and output have different lengths.
+    #[inline]
+    pub fn process_to(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input and output lengths differ");
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+
+        for (&input_sample, sample) in input.iter().zip(output.iter_mut()) {
+            *sample = coeffs.tick(&mut ic1eq, input_sample);
+        }
 
-        self.coeffs.m0 * input_sample + self.coeffs.m1 * v2
+        self.ic1eq = ic1eq;
     }
 
     #[inline]
-    pub fn update(&mut self, new_coefficients: IIR1Coefficients) {
+    pub fn update(&mut self, new_coefficients: IIR1Coefficients<T>) {
         self.coeffs = new_coefficients;
     }
+
+    /// Clears the internal state, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        self.ic1eq = T::zero();
+    }
+
+    #[inline]
+    pub fn state(&self) -> IIR1State<T> {
+        IIR1State { ic1eq: self.ic1eq }
+    }
+
+    /// Restores a state saved with `state`, processing continues exactly where it left off
+    #[inline]
+    pub fn set_state(&mut self, state: IIR1State<T>) {
+        self.ic1eq = state.ic1eq;
+    }
+
+    /// Resets the state if it has become NaN or infinite, returning true if it did
+    #[inline]
+    pub fn check_and_recover(&mut self) -> bool {
+        if self.ic1eq.is_finite() {
+            false
+        } else {
+            self.reset();
+            true
+        }
+    }
+
+    /// Like `process_block`, but checks the state once at the end of the block. If it had to be
+    /// recovered the buffer is silenced and true is returned.
+    #[inline]
+    pub fn process_block_protected(&mut self, buffer: &mut [T]) -> bool {
+        self.process_block(buffer);
+        let recovered = self.check_and_recover();
+        if recovered {
+            buffer.fill(T::zero());
+        }
+        recovered
+    }
+
+    /// Sets the state the filter reaches after processing dc_value forever, so starting on a
+    /// constant signal gives no transient. At DC the integrator settles to the input.
+    #[inline]
+    pub fn settle_to_dc(&mut self, dc_value: T) {
+        self.ic1eq = dc_value;
+    }
+
+    /// DC blocker with the corner at `DC_BLOCKER_HZ`
+    pub fn dc_blocker(sample_rate_hz: T) -> Self {
+        IIR1::from(IIR1Coefficients::dc_blocker(
+            T::from(DC_BLOCKER_HZ).unwrap(),
+            sample_rate_hz,
+        ))
+    }
+
+    /// Response of the current coefficients to a unit impulse, starting from a cleared state.
+    /// The state of this filter is not touched.
+    pub fn impulse_response(&self, length: usize) -> Vec<T> {
+        let mut filter = IIR1::from(self.coeffs);
+        (0..length)
+            .map(|i| filter.process(if i == 0 { T::one() } else { T::zero() }))
+            .collect()
+    }
+
+    /// Response of the current coefficients to a unit step, starting from a cleared state.
+    /// The state of this filter is not touched.
+    pub fn step_response(&self, length: usize) -> Vec<T> {
+        let mut filter = IIR1::from(self.coeffs);
+        (0..length).map(|_| filter.process(T::one())).collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::log_frequencies;
 
+    #[allow(clippy::excessive_precision)]
     fn rand(x: f32) -> f32 {
         ((x * 12.9898).sin() * 43758.5453).fract()
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn test_iir1() {
         let mut audio: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
 
@@ -151,4 +699,559 @@ mod tests {
 
         assert_eq!(audio[500], -0.4137441)
     }
+
+    #[test]
+    fn test_iir1_f64() {
+        let constructors64: [Constructor<f64>; 6] = [
+            IIR1Coefficients::lowpass,
+            IIR1Coefficients::highpass,
+            IIR1Coefficients::allpass,
+            IIR1Coefficients::lowshelf,
+            IIR1Coefficients::highshelf,
+            IIR1Coefficients::tilt,
+        ];
+
+        for (constructor32, constructor64) in CONSTRUCTORS.iter().zip(constructors64.iter()) {
+            let mut filter32 = IIR1::from(constructor32(1000.0, 6.0, 48000.0));
+            let mut filter64 = IIR1::from(constructor64(1000.0, 6.0, 48000.0));
+            for i in 0..1000 {
+                let x = rand(i as f32);
+                let y32 = filter32.process(x);
+                let y64 = filter64.process(x as f64);
+                assert!((y32 as f64 - y64).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir1_process_block() {
+        let audio: Vec<f32> = (0..1001).map(|x| rand(x as f32)).collect();
+
+        let coeffs = IIR1Coefficients::lowshelf(1000.0, 6.0, 48000.0);
+
+        let mut scalar_filter = IIR1::from(coeffs);
+        let expected: Vec<f32> = audio.iter().map(|&x| scalar_filter.process(x)).collect();
+
+        let mut block_filter = IIR1::from(coeffs);
+        let mut block = audio.clone();
+        block_filter.process_block(&mut block[..0]);
+        block_filter.process_block(&mut block[..333]);
+        block_filter.process_block(&mut block[333..]);
+
+        assert_eq!(block, expected);
+        assert_eq!(block_filter.process(1.0), scalar_filter.process(1.0));
+    }
+
+    #[test]
+    fn test_iir1_process_to() {
+        let audio: Vec<f32> = (0..1001).map(|x| rand(x as f32)).collect();
+
+        let coeffs = IIR1Coefficients::lowshelf(1000.0, 6.0, 48000.0);
+
+        let mut scalar_filter = IIR1::from(coeffs);
+        let expected: Vec<f32> = audio.iter().map(|&x| scalar_filter.process(x)).collect();
+
+        let mut block_filter = IIR1::from(coeffs);
+        let mut output = vec![0.0; audio.len()];
+        block_filter.process_to(&audio[..333], &mut output[..333]);
+        block_filter.process_to(&audio[333..], &mut output[333..]);
+
+        assert_eq!(output, expected);
+        assert_eq!(block_filter.process(1.0), scalar_filter.process(1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "input and output lengths differ")]
+    fn test_iir1_process_to_length_mismatch() {
+        let mut filter = IIR1::from(IIR1Coefficients::lowshelf(1000.0, 6.0, 48000.0));
+        filter.process_to(&[0.0; 64], &mut [0.0; 63]);
+    }
+
+    #[test]
+    fn test_iir1_bypass() {
+        let coeffs = IIR1Coefficients::bypass();
+        let mut filter = IIR1::from(coeffs);
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            assert_eq!(filter.process(x), x);
+        }
+        for &f in &[0.0, 1000.0, 24000.0] {
+            assert_eq!(coeffs.get_bode_sample(f, 48000.0).norm(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_iir1_impulse_response() {
+        let coefficients = [
+            IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0),
+            IIR1Coefficients::highshelf(1000.0, -6.0, 48000.0),
+        ];
+        for coeffs in coefficients.iter() {
+            let mut filter = IIR1::from(*coeffs);
+            filter.process(1.0);
+            let mut before = filter;
+
+            let impulse_response = filter.impulse_response(48000);
+            let dc_gain = coeffs.get_bode_sample(0.01, 48000.0).norm();
+            let sum: f32 = impulse_response.iter().sum();
+            assert!((sum - dc_gain).abs() < 1e-3);
+            assert_eq!(filter.process(0.0), before.process(0.0));
+        }
+    }
+
+    #[test]
+    fn test_iir1_step_response() {
+        let coeffs = IIR1Coefficients::lowpass(1000.0, -6.0, 48000.0);
+        let filter = IIR1::from(coeffs);
+        let step_response = filter.step_response(48000);
+        let dc_gain = coeffs.get_bode_sample(0.0, 48000.0).norm();
+
+        assert_eq!(step_response.len(), 48000);
+        assert!((step_response[47999] - dc_gain).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_iir1_reset() {
+        let coeffs = IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0);
+        let mut filter = IIR1::from(coeffs);
+
+        for i in 0..100 {
+            filter.process(rand(i as f32));
+        }
+
+        filter.reset();
+
+        let mut fresh = IIR1::from(coeffs);
+        for _ in 0..100 {
+            assert_eq!(filter.process(1.0), fresh.process(1.0));
+        }
+    }
+
+    fn bode_db(coeffs: IIR1Coefficients, frequency_hz: f32, sample_rate_hz: f32) -> f32 {
+        20.0 * coeffs
+            .get_bode_sample(frequency_hz, sample_rate_hz)
+            .norm()
+            .log10()
+    }
+
+    #[test]
+    fn test_iir1_coefficients_comparison() {
+        let fs = 48000.0;
+        let shelf = IIR1Coefficients::lowshelf(200.0, -4.0, fs);
+        assert_eq!(shelf, IIR1Coefficients::lowshelf(200.0, -4.0, fs));
+
+        let nudged = IIR1Coefficients::lowshelf(200.01, -4.0, fs);
+        assert_ne!(shelf, nudged);
+        assert!(shelf.approx_eq(&nudged, 1e-3));
+        assert!(shelf.max_response_deviation_db(&nudged, fs) < 0.001);
+        let deeper = IIR1Coefficients::lowshelf(200.0, -5.0, fs);
+        assert!((shelf.max_response_deviation_db(&deeper, fs) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_iir1_passband_gain() {
+        let sample_rate_hz = 48000.0;
+        let gain_db = -6.0;
+
+        let lowpass = IIR1Coefficients::lowpass(1000.0, gain_db, sample_rate_hz);
+        let highpass = IIR1Coefficients::highpass(1000.0, gain_db, sample_rate_hz);
+        let allpass = IIR1Coefficients::allpass(1000.0, gain_db, sample_rate_hz);
+
+        assert!((bode_db(lowpass, 0.0, sample_rate_hz) - gain_db).abs() < 0.01);
+        assert!((bode_db(highpass, sample_rate_hz * 0.5, sample_rate_hz) - gain_db).abs() < 0.01);
+        for &f in &[0.0, 100.0, 1000.0, 10000.0] {
+            assert!((bode_db(allpass, f, sample_rate_hz) - gain_db).abs() < 0.01);
+        }
+    }
+
+    type Constructor<T = f32> = fn(T, T, T) -> IIR1Coefficients<T>;
+
+    const CONSTRUCTORS: [Constructor; 6] = [
+        IIR1Coefficients::lowpass,
+        IIR1Coefficients::highpass,
+        IIR1Coefficients::allpass,
+        IIR1Coefficients::lowshelf,
+        IIR1Coefficients::highshelf,
+        IIR1Coefficients::tilt,
+    ];
+
+    #[test]
+    fn test_iir1_cutoff_limits() {
+        let sample_rate_hz = 48000.0;
+        for constructor in CONSTRUCTORS.iter() {
+            for &cutoff_hz in &[sample_rate_hz * 0.5, 0.0, -10.0] {
+                let c = constructor(cutoff_hz, 6.0, sample_rate_hz);
+                let fields = [c.a, c.g, c.a1, c.m0, c.m1];
+                assert!(fields.iter().all(|x| x.is_finite()));
+
+                let mut filter = IIR1::from(c);
+                for i in 0..1000 {
+                    assert!(filter.process(rand(i as f32)).is_finite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir1_filter_type() {
+        let names = [
+            "LowPass",
+            "high_pass",
+            "all-pass",
+            "LOWSHELF",
+            "HighShelf",
+            "tilt",
+        ];
+        for ((filter_type, constructor), name) in IIR1FilterType::ALL
+            .iter()
+            .zip(CONSTRUCTORS.iter())
+            .zip(names.iter())
+        {
+            let expected = constructor(1000.0, 6.0, 48000.0);
+            let coeffs = IIR1Coefficients::<f32>::new(*filter_type, 1000.0, 6.0, 48000.0);
+            assert_eq!(format!("{:?}", coeffs), format!("{:?}", expected));
+            assert_eq!(name.parse::<IIR1FilterType>(), Ok(*filter_type));
+        }
+        assert!("bell".parse::<IIR1FilterType>().is_err());
+    }
+
+    #[test]
+    fn test_iir1_frequency_response() {
+        let coeffs = IIR1Coefficients::lowshelf(1000.0, 12.0, 48000.0);
+        let response = coeffs.frequency_response(1.0, 20000.0, 32, 48000.0);
+
+        assert_eq!(response.len(), 32);
+        assert!((response[0].magnitude_db - 12.0).abs() < 0.01);
+        assert!((response[31].frequency_hz - 20000.0).abs() < 0.1);
+        assert!(response[31].magnitude_db.abs() < 0.1);
+    }
+
+    #[test]
+    fn test_iir1_group_delay() {
+        let coeffs = IIR1Coefficients::<f64>::lowpass(1000.0, 0.0, 48000.0);
+        let impulse_response = IIR1::from(coeffs).impulse_response(10000);
+        let sum: f64 = impulse_response.iter().sum();
+        let centroid: f64 = impulse_response
+            .iter()
+            .enumerate()
+            .map(|(n, h)| n as f64 * h)
+            .sum::<f64>()
+            / sum;
+        assert!((coeffs.group_delay_at(0.0, 48000.0) - centroid).abs() < 1e-3);
+
+        let allpass = IIR1Coefficients::allpass(1000.0, 0.0, 48000.0);
+        let low = allpass.group_delay_at(100.0, 48000.0);
+        let high = allpass.group_delay_at(10000.0, 48000.0);
+        assert!(low > high && high > 0.0);
+    }
+
+    #[test]
+    fn test_iir1_allpass_phase90() {
+        for frequency_hz in [50.0, 1000.0, 10000.0, 20000.0] {
+            let coeffs = IIR1Coefficients::allpass_phase90(frequency_hz, 48000.0);
+            assert!((coeffs.phase_degrees_at(frequency_hz, 48000.0) + 90.0).abs() < 0.5);
+            assert!(coeffs.phase_degrees_at(frequency_hz / 4.0, 48000.0) > -90.0);
+            assert!(coeffs.phase_degrees_at(frequency_hz * 1.1, 48000.0) < -90.0);
+            for f in [10.0, frequency_hz, 23000.0] {
+                assert!(coeffs.get_bode_sample(f, 48000.0).norm().log10().abs() < 1e-5);
+            }
+        }
+
+        // Same filter as allpass, inverted
+        let coeffs = IIR1Coefficients::allpass_phase90(1000.0, 48000.0);
+        let allpass = IIR1Coefficients::allpass(1000.0, 0.0, 48000.0);
+        assert!((allpass.phase_degrees_at(1000.0, 48000.0) - 90.0).abs() < 0.5);
+        for f in [100.0, 1000.0, 10000.0] {
+            let sum = coeffs.get_bode_sample(f, 48000.0) + allpass.get_bode_sample(f, 48000.0);
+            assert!(sum.norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_iir1_tilt() {
+        let sample_rate_hz = 48000.0;
+        let coeffs = IIR1Coefficients::tilt(1000.0, 6.0, sample_rate_hz);
+
+        assert!((bode_db(coeffs, 0.0, sample_rate_hz) + 3.0).abs() < 0.01);
+        assert!(bode_db(coeffs, 1000.0, sample_rate_hz).abs() < 0.01);
+        assert!((bode_db(coeffs, sample_rate_hz * 0.5, sample_rate_hz) - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_iir1_state() {
+        let input: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
+        let coeffs = IIR1Coefficients::lowshelf(300.0, 9.0, 48000.0);
+
+        let mut straight = IIR1::from(coeffs);
+        let expected: Vec<f32> = input.iter().map(|&x| straight.process(x)).collect();
+
+        let mut first = IIR1::from(coeffs);
+        let mut output: Vec<f32> = input[..500].iter().map(|&x| first.process(x)).collect();
+        let saved = first.state();
+        let mut second = IIR1::from(coeffs);
+        second.set_state(saved);
+        output.extend(input[500..].iter().map(|&x| second.process(x)));
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_iir1_try_constructors() {
+        use FilterDesignError::*;
+        let fs = 48000.0;
+        assert!(IIR1Coefficients::try_lowshelf(200.0, 6.0, fs).is_ok());
+        for filter_type in IIR1FilterType::ALL {
+            let checked = |cutoff_hz: f32, gain_db: f32, fs: f32| {
+                IIR1Coefficients::try_new(filter_type, cutoff_hz, gain_db, fs).err()
+            };
+            assert_eq!(checked(f32::NAN, 0.0, fs), Some(InvalidCutoff));
+            assert_eq!(checked(0.0, 0.0, fs), Some(InvalidCutoff));
+            assert_eq!(checked(1000.0, f32::INFINITY, fs), Some(InvalidGain));
+            assert_eq!(checked(1000.0, 0.0, -1.0), Some(InvalidSampleRate));
+
+            for (cutoff_hz, gain_db, fs) in [
+                (f32::NAN, 6.0, fs),
+                (f32::INFINITY, 6.0, fs),
+                (1000.0, f32::NAN, fs),
+                (1000.0, f32::INFINITY, fs),
+                (1000.0, 6.0, 0.0),
+            ] {
+                assert!(IIR1Coefficients::new(filter_type, cutoff_hz, gain_db, fs).is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir1_nan_recovery() {
+        let sample_rate_hz = 48000.0;
+        let coeffs = IIR1Coefficients::lowpass(1000.0, 0.0, sample_rate_hz);
+        assert!(coeffs.is_finite());
+        let mut poisoned = coeffs;
+        poisoned.a1 = f32::NAN;
+        assert!(!poisoned.is_finite());
+
+        let mut filter = IIR1::from(coeffs);
+        filter.process(f32::NAN);
+        assert!(filter.check_and_recover());
+        let mut clean = IIR1::from(coeffs);
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            assert_eq!(filter.process(x), clean.process(x));
+        }
+
+        let mut buffer = [1.0, f32::NAN, 1.0, 1.0];
+        assert!(filter.process_block_protected(&mut buffer));
+        assert_eq!(buffer, [0.0; 4]);
+    }
+
+    #[cfg(feature = "denormal-protection")]
+    #[test]
+    fn test_iir1_denormal_protection() {
+        let coeffs = IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0);
+        let is_normal_or_zero = |x: f32| x == 0.0 || x.abs() >= f32::MIN_POSITIVE;
+        let mut filter = IIR1::from(coeffs);
+        let mut block = IIR1::from(coeffs);
+        filter.process(1.0);
+        let mut buffer = vec![0.0; 100_000];
+        buffer[0] = 1.0;
+        for chunk in buffer.chunks_mut(64) {
+            block.process_block(chunk);
+            assert!(is_normal_or_zero(block.state().ic1eq));
+        }
+        for _ in 0..100_000 {
+            filter.process(0.0);
+            assert!(is_normal_or_zero(filter.state().ic1eq));
+        }
+        assert_eq!(filter.state().ic1eq, 0.0);
+    }
+
+    #[test]
+    fn test_iir1_settle_to_dc() {
+        let sample_rate_hz = 48000.0;
+        for coeffs in [
+            IIR1Coefficients::lowshelf(200.0, 12.0, sample_rate_hz),
+            IIR1Coefficients::highpass(20.0, 0.0, sample_rate_hz),
+        ] {
+            let dc_gain = coeffs.get_bode_sample(0.0, sample_rate_hz).re;
+            let mut filter = IIR1::from(coeffs);
+            filter.settle_to_dc(-0.25);
+            for _ in 0..1000 {
+                assert!((filter.process(-0.25) + 0.25 * dc_gain).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir1_dc_blocker() {
+        for &sample_rate_hz in &[8000.0, 48000.0, 192000.0] {
+            let mut filter = IIR1::<f64>::dc_blocker(sample_rate_hz);
+            let settle = filter.coeffs.dc_settle_samples(1e-6);
+            let output: Vec<f64> = (0..settle + 1000).map(|_| filter.process(1.0)).collect();
+            assert!(output[settle - 2].abs() > 1e-6);
+            assert!(output[settle..].iter().all(|y| y.abs() < 1e-6));
+
+            // The attenuation and phase at 20 Hz quoted for the 5 and 10 Hz corners
+            for (corner_hz, expected_db, expected_degrees) in
+                [(5.0, -0.26, 14.0), (10.0, -0.97, 26.6)]
+            {
+                let coeffs = IIR1Coefficients::dc_blocker(corner_hz, sample_rate_hz);
+                let response = coeffs.get_bode_sample(20.0, sample_rate_hz);
+                assert!((20.0 * response.norm().log10() - expected_db).abs() < 0.01);
+                assert!((response.arg().to_degrees() - expected_degrees).abs() < 0.1);
+            }
+        }
+
+        // f32 at a low sample rate, where the normalized corner is tiny
+        let sample_rate_hz = 8000.0f32;
+        let mut filter = IIR1::<f32>::dc_blocker(sample_rate_hz);
+        let settle = filter.coeffs.dc_settle_samples(1e-6);
+        let mut peak = 0.0f32;
+        for i in 0..settle * 2 {
+            let twenty_hz = (core::f32::consts::TAU * 20.0 * i as f32 / sample_rate_hz).sin();
+            let y = filter.process(0.5 + twenty_hz);
+            assert!(y.is_finite());
+            if i > settle {
+                peak = peak.max(y.abs());
+            }
+        }
+        assert!((20.0 * peak.log10()).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_iir1_designed() {
+        let designed = DesignedIIR1::new(IIR1FilterType::HighShelf, 3000.0, -6.0, 44100.0);
+        assert_eq!(designed.design().cutoff_hz, 3000.0);
+        let moved = designed.with_sample_rate(88200.0);
+        for f in [300.0, 3000.0] {
+            let before = bode_db(*designed, f, 44100.0);
+            let after = bode_db(*moved, f, 88200.0);
+            assert!((before - after).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_iir1_at_sample_rate() {
+        for filter_type in IIR1FilterType::ALL {
+            let coeffs = IIR1Coefficients::new(filter_type, 1000.0, 6.0, 48000.0);
+            for new_sample_rate_hz in [44100.0, 96000.0] {
+                let moved = coeffs.at_sample_rate(48000.0, new_sample_rate_hz);
+                for f in [50.0, 1000.0, 2000.0] {
+                    let before = bode_db(coeffs, f, 48000.0);
+                    let after = bode_db(moved, f, new_sample_rate_hz);
+                    assert!((before - after).abs() < 0.1, "{:?} {}", filter_type, f);
+                }
+            }
+        }
+        let lowpass = IIR1Coefficients::lowpass(40000.0, 0.0, 96000.0);
+        assert!(lowpass.at_sample_rate(96000.0, 44100.0).is_stable());
+    }
+
+    #[test]
+    fn test_iir1_effective_cutoff() {
+        let sample_rate_hz = 48000.0;
+        for cutoff_hz in [20.0, 1000.0, 10000.0] {
+            let lowpass = IIR1Coefficients::lowpass(cutoff_hz, 0.0, sample_rate_hz);
+            let highpass = IIR1Coefficients::highpass(cutoff_hz, 0.0, sample_rate_hz);
+            for coeffs in [lowpass, highpass] {
+                let measured = coeffs.effective_cutoff_hz(sample_rate_hz).unwrap();
+                assert!((measured - cutoff_hz).abs() < cutoff_hz * 1e-3);
+            }
+        }
+        let allpass = IIR1Coefficients::allpass(1000.0, 0.0, sample_rate_hz);
+        assert_eq!(allpass.effective_cutoff_hz(sample_rate_hz), None);
+    }
+
+    #[test]
+    fn test_iir1_poles_zeros() {
+        let sample_rate_hz = 48000.0;
+        for filter_type in IIR1FilterType::ALL {
+            for cutoff_hz in log_frequencies(10.0, 23000.0, 20) {
+                let coeffs = IIR1Coefficients::new(filter_type, cutoff_hz, 12.0, sample_rate_hz);
+                assert!(coeffs.is_stable());
+                let (pole, zero) = coeffs.poles_zeros();
+                assert!(pole.norm() < 1.0);
+                // The response is infinite at the pole and zero at the zero
+                let response = |z: Complex<f64>| {
+                    let z = z.inv();
+                    (z + 1.0) * (coeffs.m1 * coeffs.g) / (z * (coeffs.g - 1.0) + coeffs.g + 1.0)
+                        + coeffs.m0
+                };
+                if zero.is_finite() {
+                    assert!(response(zero).norm() < 1e-9);
+                }
+            }
+        }
+        let lowpass = IIR1Coefficients::<f64>::lowpass(1000.0, 0.0, sample_rate_hz);
+        assert_eq!(lowpass.poles_zeros().1, Complex::new(-1.0, 0.0));
+        let mut poisoned = IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0);
+        poisoned.g = f32::NAN;
+        assert!(!poisoned.is_stable());
+    }
+
+    #[test]
+    fn test_iir1_get_bode_samples() {
+        let sample_rate_hz = 48000.0;
+        let frequencies: Vec<f64> = log_frequencies(1.0, 20000.0, 512).collect();
+        let mut out = vec![Complex::new(0.0, 0.0); frequencies.len()];
+        for filter_type in IIR1FilterType::ALL {
+            let coeffs = IIR1Coefficients::new(filter_type, 1000.0, -12.0, sample_rate_hz);
+            coeffs.get_bode_samples(&frequencies, sample_rate_hz, &mut out);
+            for (&f, batched) in frequencies.iter().zip(out.iter()) {
+                let scalar = coeffs.get_bode_sample(f, sample_rate_hz);
+                assert!((batched - scalar).norm() < 1e-6 * scalar.norm());
+            }
+        }
+
+        // In f32 close to DC and Nyquist the batched response stays with the f64 one
+        let frequencies: Vec<f32> = log_frequencies(1.0, 23900.0, 512).collect();
+        let mut out = vec![Complex::new(0.0, 0.0); frequencies.len()];
+        for filter_type in IIR1FilterType::ALL {
+            for cutoff_hz in [20.0, 20000.0] {
+                let coeffs = IIR1Coefficients::new(filter_type, cutoff_hz, -12.0, 48000.0);
+                let exact = IIR1Coefficients::new(filter_type, cutoff_hz as f64, -12.0, 48000.0);
+                coeffs.get_bode_samples(&frequencies, 48000.0, &mut out);
+                for (&f, batched) in frequencies.iter().zip(out.iter()) {
+                    let expected = exact.get_bode_sample(f as f64, 48000.0);
+                    let batched = Complex::new(batched.re as f64, batched.im as f64);
+                    assert!((batched - expected).norm() < 1e-5 * expected.norm());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir1_lerp() {
+        let sample_rate_hz = 48000.0;
+        let start = IIR1Coefficients::lowpass(200.0, 0.0, sample_rate_hz);
+        let end = IIR1Coefficients::lowpass(8000.0, 0.0, sample_rate_hz);
+
+        let mut filter = IIR1::from(start);
+        let len = 4800;
+        for i in 0..=len {
+            let t = i as f32 / len as f32;
+            filter.update(start.lerp(&end, t));
+            let output = filter.process(rand(i as f32) * 2.0 - 1.0);
+            assert!(output.is_finite() && output.abs() < 10.0);
+        }
+        let last = start.lerp(&end, 1.0);
+        assert!((last.a1 - end.a1).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_iir1_serde() {
+        let coeffs = IIR1Coefficients::<f64>::highshelf(1000.0, -6.0, 48000.0);
+        let json = serde_json::to_string(&coeffs).unwrap();
+        let restored: IIR1Coefficients<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", coeffs), format!("{:?}", restored));
+
+        let saved = r#"{"a":1.0,"g":1.0,"a1":0.0,"m0":1.0,"m1":0.0}"#;
+        let restored: IIR1Coefficients = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            format!("{:?}", restored),
+            format!("{:?}", IIR1Coefficients::<f32>::bypass())
+        );
+        let restored: IIR1FilterType = serde_json::from_str("\"Tilt\"").unwrap();
+        assert_eq!(restored, IIR1FilterType::Tilt);
+    }
 }
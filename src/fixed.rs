@@ -0,0 +1,214 @@
+//! Second order SVF in fixed point, for processors without an FPU.
+//!
+//! Samples are `i32` in Q1.30: full scale ±1.0 is ±2^30, which leaves one bit of headroom so a
+//! boosting filter can go up to +6 dB over full scale before the output saturates. Use `to_q30`
+//! and `from_q30` to convert.
+//!
+//! The two state variables are kept in Q4.27, with four integer bits over the samples, since
+//! they carry the band pass and low pass of the SVF which rise with Q. That is enough for a full
+//! scale input at a Q of about 8, past that the states saturate instead of wrapping around. The
+//! `a1`..`a3` coefficients are in Q1.30. The `m0`..`m2` mix coefficients can be much larger than
+//! one for bells and shelves, so they share an exponent chosen when quantizing, the smallest one
+//! that fits all three.
+//!
+//! Every multiply is a 32 x 32 bit product into 64 bits, rounded to nearest on the way back.
+//! Very low cutoffs lose precision because `a3` is close to g², with 30 fractional bits a
+//! cutoff of 20 Hz at 48 kHz still leaves it about 11 significant bits.
+
+use num_traits::Float;
+
+use crate::second_order_iir::IIR2Coefficients;
+
+const SAMPLE_BITS: u32 = 30;
+const STATE_BITS: u32 = 27;
+const COEFF_BITS: u32 = 30;
+
+/// Converts a float sample to Q1.30, saturating outside of ±2.0
+pub fn to_q30<T: Float>(sample: T) -> i32 {
+    quantize(sample.to_f64().unwrap_or(0.0), SAMPLE_BITS)
+}
+
+/// Converts a Q1.30 sample back to float
+pub fn from_q30<T: Float>(sample: i32) -> T {
+    T::from(sample as f64 / (1u64 << SAMPLE_BITS) as f64).unwrap_or_else(T::zero)
+}
+
+fn quantize(value: f64, fractional_bits: u32) -> i32 {
+    let scaled = Float::round(value * (1u64 << fractional_bits) as f64);
+    if scaled.is_nan() {
+        0
+    } else {
+        // Float to int casts saturate
+        scaled as i32
+    }
+}
+
+/// Shifts right by bits, rounding to nearest
+#[inline]
+fn round_shift(value: i64, bits: u32) -> i64 {
+    (value + (1 << (bits - 1))) >> bits
+}
+
+#[inline]
+fn saturate(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Full precision product of a coefficient and a state or sample
+#[inline]
+fn mul(coeff: i32, state: i32) -> i64 {
+    coeff as i64 * state as i64
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2Fixed {
+    ic1eq: i32,
+    ic2eq: i32,
+    a1: i32,
+    a2: i32,
+    a3: i32,
+    m0: i32,
+    m1: i32,
+    m2: i32,
+    /// Exponent shared by m0, m1 and m2, they are in Q(30 - m_shift)
+    m_shift: u32,
+}
+
+impl IIR2Fixed {
+    /// Creates the filter with coeffs quantized to fixed point
+    pub fn from_float<T: Float>(coeffs: IIR2Coefficients<T>) -> Self {
+        let mut filter = IIR2Fixed {
+            ic1eq: 0,
+            ic2eq: 0,
+            a1: 0,
+            a2: 0,
+            a3: 0,
+            m0: 0,
+            m1: 0,
+            m2: 0,
+            m_shift: 0,
+        };
+        filter.update(coeffs);
+        filter
+    }
+
+    /// Quantizes and switches to new_coefficients, keeping the state
+    pub fn update<T: Float>(&mut self, new_coefficients: IIR2Coefficients<T>) {
+        let value = |x: T| x.to_f64().unwrap_or(0.0);
+        self.a1 = quantize(value(new_coefficients.a1), COEFF_BITS);
+        self.a2 = quantize(value(new_coefficients.a2), COEFF_BITS);
+        self.a3 = quantize(value(new_coefficients.a3), COEFF_BITS);
+
+        let m = [
+            new_coefficients.m0,
+            new_coefficients.m1,
+            new_coefficients.m2,
+        ]
+        .map(value);
+        let largest = m.iter().fold(0.0f64, |acc, x| acc.max(x.abs()));
+        // Q1.30 holds magnitudes below 2, every shift doubles that
+        let mut m_shift = 0;
+        while m_shift + 1 < STATE_BITS && largest >= (2u64 << m_shift) as f64 {
+            m_shift += 1;
+        }
+        self.m_shift = m_shift;
+        [self.m0, self.m1, self.m2] = m.map(|x| quantize(x, COEFF_BITS - m_shift));
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: i32) -> i32 {
+        let input = input_sample >> (SAMPLE_BITS - STATE_BITS);
+        let v3 = saturate(input as i64 - self.ic2eq as i64);
+        let v1 = saturate(round_shift(
+            mul(self.a1, self.ic1eq) + mul(self.a2, v3),
+            COEFF_BITS,
+        ));
+        let v2 = saturate(
+            self.ic2eq as i64
+                + round_shift(mul(self.a2, self.ic1eq) + mul(self.a3, v3), COEFF_BITS),
+        );
+        self.ic1eq = saturate(2 * v1 as i64 - self.ic1eq as i64);
+        self.ic2eq = saturate(2 * v2 as i64 - self.ic2eq as i64);
+
+        // The mix comes out in Q(57 - m_shift), the input keeps its full precision
+        let output = (mul(self.m0, input_sample) >> (SAMPLE_BITS - STATE_BITS))
+            .saturating_add(mul(self.m1, v1))
+            .saturating_add(mul(self.m2, v2));
+        saturate(round_shift(
+            output,
+            COEFF_BITS + STATE_BITS - SAMPLE_BITS - self.m_shift,
+        ))
+    }
+
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [i32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the internal state, keeping the current coefficients
+    pub fn reset(&mut self) {
+        self.ic1eq = 0;
+        self.ic2eq = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::IIR2;
+    use core::f32::consts::TAU;
+
+    /// RMS of the difference between fixed and float output relative to the float output, in dB
+    fn error_db(coeffs: IIR2Coefficients, frequency_hz: f32, amplitude: f32) -> f32 {
+        let mut fixed = IIR2Fixed::from_float(coeffs);
+        let mut float = IIR2::from(coeffs);
+        let (mut error, mut signal) = (0.0f64, 0.0f64);
+        for i in 0..48000 {
+            let x = amplitude * (TAU * frequency_hz * i as f32 / 48000.0).sin();
+            let expected = float.process(x) as f64;
+            let actual = from_q30::<f64>(fixed.process(to_q30(x)));
+            error += (actual - expected).powi(2);
+            signal += expected.powi(2);
+        }
+        (10.0 * (error / signal).log10()) as f32
+    }
+
+    #[test]
+    fn test_fixed_matches_float() {
+        let minus_12_db = 10.0f32.powf(-12.0 / 20.0);
+        let bell = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+        assert!(error_db(bell, 1000.0, minus_12_db) < -60.0);
+
+        for coeffs in [
+            IIR2Coefficients::lowpass(100.0, 0.0, 0.707, 48000.0),
+            IIR2Coefficients::highshelf(4000.0, -12.0, 0.7, 48000.0),
+            IIR2Coefficients::lowshelf(200.0, 12.0, 0.7, 48000.0),
+            IIR2Coefficients::bypass(),
+        ] {
+            assert!(error_db(coeffs, 300.0, minus_12_db) < -60.0);
+        }
+    }
+
+    #[test]
+    fn test_fixed_saturates() {
+        // A full scale sine through a 12 dB boost clips at the top of Q1.30 rather than wrapping
+        let mut filter = IIR2Fixed::from_float(IIR2Coefficients::bell(1000.0, 12.0, 1.0, 48000.0));
+        let mut float = IIR2::from(IIR2Coefficients::bell(1000.0, 12.0, 1.0, 48000.0));
+        let mut peak = 0;
+        for i in 0..4800 {
+            let x = (TAU * 1000.0 * i as f32 / 48000.0).sin();
+            let y = filter.process(to_q30(x));
+            let expected = float.process(x).clamp(-2.0, 2.0);
+            assert!((from_q30::<f32>(y) - expected).abs() < 1e-3);
+            peak = peak.max(y);
+        }
+        assert_eq!(peak, i32::MAX);
+
+        filter.reset();
+        assert_eq!(filter.process(0), 0);
+        assert_eq!(to_q30(4.0f32), i32::MAX);
+        assert_eq!(from_q30::<f32>(1 << 30), 1.0);
+    }
+}
@@ -0,0 +1,154 @@
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::cascade_iir::IIR2Cascade;
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, flush_denormal};
+
+/// Pole coefficients of Olli Niemitalo's polyphase IIR Hilbert transformer, each giving an all
+/// pass section `(a² - z^-2) / (1 - a² z^-2)`
+const IN_PHASE_POLES: [f64; 4] = [
+    0.402_192_116_242_6,
+    0.856_171_088_242_0,
+    0.972_290_954_565_1,
+    0.995_288_479_127_8,
+];
+const QUADRATURE_POLES: [f64; 4] = [
+    0.692_387_8,
+    0.936_065_432_295_9,
+    0.988_229_522_686_0,
+    0.998_748_845_273_7,
+];
+
+/// The all pass `(a² - z^-2) / (1 - a² z^-2)` as an SVF. It is an inverted second order all pass
+/// at a quarter of the sample rate, with Q set by a. Written out here since the Q of the last
+/// sections is below `MIN_Q`.
+fn section<T: Float>(a: f64) -> IIR2Coefficients<T> {
+    let apow2 = a * a;
+    let k = cast::<T>(2.0 * (1.0 + apow2) / (1.0 - apow2));
+    // g = tan(pi / 4)
+    let a1 = T::one() / (cast::<T>(2.0) + k);
+    IIR2Coefficients {
+        a: T::one(),
+        g: T::one(),
+        gpow2: T::one(),
+        k,
+        a1,
+        a2: a1,
+        a3: a1,
+        m0: -T::one(),
+        m1: cast::<T>(2.0) * k,
+        m2: T::zero(),
+    }
+}
+
+/// Two all pass chains whose outputs are 90 degrees apart, for frequency shifting, single
+/// sideband modulation and envelopes.
+///
+/// Both outputs have unity gain and the same, frequency dependent, phase delay, only their
+/// difference is fixed. It stays within 0.71 degrees of 90 from 0.002 to 0.498 of the sample
+/// rate, 100 Hz to 23.9 kHz at 48 kHz. The design is in normalized frequency, so there is no
+/// sample rate to set.
+#[derive(Copy, Clone, Debug)]
+pub struct HilbertPair<T = f32> {
+    in_phase: IIR2Cascade<4, T>,
+    quadrature: IIR2Cascade<4, T>,
+    delayed: T,
+}
+
+impl<T: Float> HilbertPair<T> {
+    pub fn new() -> Self {
+        HilbertPair {
+            in_phase: IIR2Cascade::from(IN_PHASE_POLES.map(section)),
+            quadrature: IIR2Cascade::from(QUADRATURE_POLES.map(section)),
+            delayed: T::zero(),
+        }
+    }
+
+    /// Processes one sample, returning the in phase output and the quadrature output that lags
+    /// it by 90 degrees
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> (T, T) {
+        let in_phase = self.in_phase.process(input_sample);
+        // The quadrature path has an extra sample of delay
+        let quadrature = self.quadrature.process(self.delayed);
+        self.delayed = flush_denormal(input_sample);
+        (in_phase, quadrature)
+    }
+
+    /// Magnitude of the analytic signal, the envelope of the input
+    #[inline]
+    pub fn process_envelope(&mut self, input_sample: T) -> T {
+        let (in_phase, quadrature) = self.process(input_sample);
+        in_phase.hypot(quadrature)
+    }
+
+    /// Clears the state of both chains
+    pub fn reset(&mut self) {
+        self.in_phase.reset();
+        self.quadrature.reset();
+        self.delayed = T::zero();
+    }
+
+    /// Responses of the in phase and quadrature outputs at frequency_hz
+    pub fn get_bode_samples(&self, frequency_hz: T, sample_rate_hz: T) -> (Complex<T>, Complex<T>) {
+        let w = cast::<T>(core::f64::consts::TAU) * frequency_hz / sample_rate_hz;
+        let delay = Complex::new(w.cos(), -w.sin());
+        (
+            self.in_phase.get_bode_sample(frequency_hz, sample_rate_hz),
+            self.quadrature
+                .get_bode_sample(frequency_hz, sample_rate_hz)
+                * delay,
+        )
+    }
+}
+
+impl<T: Float> Default for HilbertPair<T> {
+    fn default() -> Self {
+        HilbertPair::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    #[test]
+    fn test_hilbert_phase_difference() {
+        let pair = HilbertPair::<f64>::new();
+        // The documented band, 0.002 to 0.498 of the sample rate
+        for i in 0..=2000 {
+            let f = 48000.0 * (0.002 + 0.496 * i as f64 / 2000.0);
+            let (in_phase, quadrature) = pair.get_bode_samples(f, 48000.0);
+            let difference = (quadrature / in_phase).arg().to_degrees();
+            assert!((difference + 90.0).abs() < 0.71);
+            assert!((in_phase.norm() - 1.0).abs() < 1e-9);
+            assert!((quadrature.norm() - 1.0).abs() < 1e-9);
+        }
+
+        // Each section matches the direct form all pass it stands for
+        let a: f64 = IN_PHASE_POLES[0];
+        let coeffs = section::<f64>(a);
+        for f in [100.0, 5000.0, 20000.0] {
+            let z = Complex::new(0.0, -TAU * f / 48000.0).exp();
+            let expected = (a * a - z * z) / (1.0 - a * a * z * z);
+            assert!((coeffs.get_bode_sample(f, 48000.0) - expected).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_hilbert_envelope() {
+        // A sine comes out of both paths at full amplitude, so the envelope is flat
+        let mut pair = HilbertPair::<f64>::new();
+        for i in 0..48000 {
+            let x = 0.5 * (TAU * 1000.0 * i as f64 / 48000.0).sin();
+            let envelope = pair.process_envelope(x);
+            if i > 4800 {
+                assert!((envelope - 0.5).abs() < 0.01);
+            }
+        }
+        pair.reset();
+        assert_eq!(pair.process(0.0), (0.0, 0.0));
+    }
+}
@@ -1,2 +1,523 @@
+//! Filters for real-time audio.
+//!
+//! Every filter and coefficient type is generic over the sample type `T: Float`, defaulting to
+//! `f32`. First and second order sections with the same `T` can be freely chained, use `f64`
+//! throughout for a double precision processing chain. The SIMD types are `f32` only.
+//!
+//! The crate is `no_std` when the default `std` feature is off. The math then comes from `libm`
+//! through the `libm` feature, and `alloc` is needed for the few types that hold a `Vec`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or the `libm` feature is needed for the float math");
+
+extern crate alloc;
+
+pub mod biquad;
+pub mod butterworth;
+pub mod cascade_design;
+pub mod cascade_iir;
+pub mod crossover;
+#[cfg(feature = "dasp")]
+pub mod dasp;
+pub mod envelope;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
 pub mod first_order_iir;
+#[cfg(feature = "fixed")]
+pub mod fixed;
+pub mod hilbert;
+#[cfg(target_has_atomic = "64")]
+pub mod mailbox;
+pub mod multi_iir;
+pub mod nonlinear_iir;
+pub mod octave_bank;
+#[cfg(feature = "wav")]
+pub mod offline;
+pub mod oversampling;
+pub mod parametric_eq;
+pub mod phaser;
+pub mod pinking;
+pub mod rbj;
+pub mod resonant_osc;
+pub mod response;
+pub mod riaa;
 pub mod second_order_iir;
+#[cfg(feature = "simd")]
+pub mod simd_iir;
+pub mod slope_filter;
+pub mod smoothed_iir;
+pub mod smoother;
+pub mod stereo_iir;
+pub mod units;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod weighting;
+pub mod zero_phase;
+
+use alloc::string::String;
+use core::error::Error;
+use core::fmt;
+
+use num_complex::Complex;
+pub use num_traits::Float;
+
+/// Highest cutoff the constructors accept, as a fraction of the sample rate. Nyquist itself
+/// would make the prewarped `tan()` blow up.
+pub const MAX_CUTOFF_RATIO: f32 = 0.4999;
+
+/// Lowest cutoff in Hz the constructors accept.
+pub const MIN_CUTOFF_HZ: f32 = 0.001;
+
+/// Lowest frequency a frequency response sweep starts at
+pub const MIN_RESPONSE_HZ: f32 = 1.0;
+
+/// Lowest Q the constructors accept, smaller or NaN values are raised to it.
+pub const MIN_Q: f32 = 0.001;
+
+/// Largest boost or cut in dB the constructors accept, beyond it the gain is clamped.
+pub const MAX_GAIN_DB: f32 = 120.0;
+
+/// One point of a frequency response
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BodePoint<T = f32> {
+    pub frequency_hz: T,
+    pub magnitude_db: T,
+    pub phase_degrees: T,
+}
+
+impl<T: Float> BodePoint<T> {
+    /// Converts a complex response from `get_bode_sample` into magnitude and phase
+    #[inline]
+    pub fn from_complex(frequency_hz: T, response: Complex<T>) -> Self {
+        BodePoint {
+            frequency_hz,
+            magnitude_db: cast::<T>(20.0) * response.norm().log10(),
+            phase_degrees: response.arg().to_degrees(),
+        }
+    }
+}
+
+/// Logarithmically spaced frequencies from start_hz to end_hz, both included
+pub(crate) fn log_frequencies<T: Float>(
+    start_hz: T,
+    end_hz: T,
+    num_points: usize,
+) -> impl Iterator<Item = T> {
+    let start_hz = start_hz.max(T::from(MIN_RESPONSE_HZ).unwrap());
+    let end_hz = end_hz.max(start_hz);
+    let ratio = end_hz / start_hz;
+    let last = T::from(num_points.saturating_sub(1).max(1)).unwrap();
+    (0..num_points).map(move |i| start_hz * ratio.powf(T::from(i).unwrap() / last))
+}
+
+/// Frequencies of a response sweep, `log_frequencies` with end_hz lowered to at most
+/// `MAX_CUTOFF_RATIO` of the sample rate, as the response above Nyquist only mirrors the one below
+pub(crate) fn response_frequencies<T: Float>(
+    start_hz: T,
+    end_hz: T,
+    num_points: usize,
+    sample_rate_hz: T,
+) -> impl Iterator<Item = T> {
+    let end_hz = end_hz.min(sample_rate_hz * cast(MAX_CUTOFF_RATIO as f64));
+    log_frequencies(start_hz, end_hz, num_points)
+}
+
+/// Group delay in samples of a frequency response, from the phase difference of two nearby
+/// points. Negative frequencies and frequencies above Nyquist are fine, so this is also valid at DC
+/// and at Nyquist.
+pub(crate) fn group_delay<T: Float>(
+    response: impl Fn(T) -> Complex<T>,
+    frequency_hz: T,
+    sample_rate_hz: T,
+) -> T {
+    let delta_hz = sample_rate_hz * cast(1e-4);
+    let phase_difference =
+        (response(frequency_hz + delta_hz) / response(frequency_hz - delta_hz)).arg();
+    let omega_difference =
+        cast::<T>(core::f64::consts::TAU) * (delta_hz + delta_hz) / sample_rate_hz;
+    -phase_difference / omega_difference
+}
+
+/// Magnitudes are compared down to this level by `max_response_deviation_db`, below it two
+/// responses count as equal, so the depth of a notch does not dominate the result
+pub const DEVIATION_FLOOR_DB: f32 = -120.0;
+
+/// Number of frequencies `max_response_deviation_db` compares at
+const DEVIATION_POINTS: usize = 1024;
+
+/// Largest magnitude difference in dB between two responses, over logarithmically spaced
+/// frequencies from `MIN_RESPONSE_HZ` to `MAX_CUTOFF_RATIO` of the sample rate
+pub(crate) fn max_response_deviation_db<T: Float>(
+    response: impl Fn(T) -> Complex<T>,
+    other: impl Fn(T) -> Complex<T>,
+    sample_rate_hz: T,
+) -> T {
+    let floor = cast::<T>(DEVIATION_FLOOR_DB as f64);
+    let db = |response: Complex<T>| (cast::<T>(20.0) * response.norm().log10()).max(floor);
+    let end_hz = sample_rate_hz * cast(MAX_CUTOFF_RATIO as f64);
+    log_frequencies(cast(MIN_RESPONSE_HZ as f64), end_hz, DEVIATION_POINTS)
+        .map(|f| (db(response(f)) - db(other(f))).abs())
+        .fold(T::zero(), T::max)
+}
+
+/// Whether every pair of fields is within tolerance relative to the larger of the two, or within
+/// tolerance absolutely for fields below 1, so terms that should be 0 compare sensibly
+pub(crate) fn fields_approx_eq<T: Float>(fields: &[T], other: &[T], tolerance: T) -> bool {
+    fields.iter().zip(other).all(|(&a, &b)| {
+        let scale = a.abs().max(b.abs()).max(T::one());
+        (a - b).abs() <= tolerance * scale
+    })
+}
+
+/// Error returned when parsing an unknown filter type name
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseFilterTypeError;
+
+impl fmt::Display for ParseFilterTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown filter type")
+    }
+}
+
+impl Error for ParseFilterTypeError {}
+
+/// Error returned by the checked `try_*` constructors when a design parameter is out of range
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FilterDesignError {
+    /// The cutoff is not finite, not positive, or not below Nyquist
+    InvalidCutoff,
+    /// Q is not finite or below `MIN_Q`
+    InvalidQ,
+    /// The gain is not finite or beyond `MAX_GAIN_DB`
+    InvalidGain,
+    /// The sample rate is not finite and positive
+    InvalidSampleRate,
+}
+
+impl fmt::Display for FilterDesignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            FilterDesignError::InvalidCutoff => "cutoff must be positive and below Nyquist",
+            FilterDesignError::InvalidQ => "Q must be finite and at least MIN_Q",
+            FilterDesignError::InvalidGain => "gain must be finite and within MAX_GAIN_DB",
+            FilterDesignError::InvalidSampleRate => "sample rate must be finite and positive",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for FilterDesignError {}
+
+/// Error returned by `IIR2::process_block_with_events` for events it can not place
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventOffsetError {
+    /// An offset is at or past the end of the buffer
+    OutOfRange,
+    /// An offset is smaller than the one before it
+    Unsorted,
+}
+
+impl fmt::Display for EventOffsetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            EventOffsetError::OutOfRange => "event offset is outside the buffer",
+            EventOffsetError::Unsorted => "event offsets are not sorted",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for EventOffsetError {}
+
+/// Checks the parameters shared by every filter design, for the `try_*` constructors
+pub(crate) fn check_design<T: Float>(
+    cutoff_hz: T,
+    gain_db: T,
+    sample_rate_hz: T,
+) -> Result<(), FilterDesignError> {
+    if !(sample_rate_hz.is_finite() && sample_rate_hz > T::zero()) {
+        return Err(FilterDesignError::InvalidSampleRate);
+    }
+    if !(cutoff_hz.is_finite() && cutoff_hz > T::zero() && cutoff_hz < sample_rate_hz / cast(2.0)) {
+        return Err(FilterDesignError::InvalidCutoff);
+    }
+    if !(gain_db.is_finite() && gain_db.abs() <= cast(MAX_GAIN_DB as f64)) {
+        return Err(FilterDesignError::InvalidGain);
+    }
+    Ok(())
+}
+
+/// Checks Q for the `try_*` constructors
+pub(crate) fn check_q<T: Float>(q_value: T) -> Result<(), FilterDesignError> {
+    if q_value.is_finite() && q_value >= cast(MIN_Q as f64) {
+        Ok(())
+    } else {
+        Err(FilterDesignError::InvalidQ)
+    }
+}
+
+/// Clamps the cutoff for the infallible constructors, `None` if the sample rate is unusable
+#[inline]
+pub(crate) fn sanitize_cutoff<T: Float>(cutoff_hz: T, sample_rate_hz: T) -> Option<T> {
+    if sample_rate_hz.is_finite() && sample_rate_hz > T::zero() {
+        Some(clamp_cutoff(cutoff_hz, sample_rate_hz))
+    } else {
+        None
+    }
+}
+
+/// Clamps the gain to `MAX_GAIN_DB`, NaN becomes 0 dB
+#[inline]
+pub(crate) fn sanitize_gain<T: Float>(gain_db: T) -> T {
+    let max_gain_db = cast::<T>(MAX_GAIN_DB as f64);
+    if gain_db.is_nan() {
+        T::zero()
+    } else {
+        gain_db.max(-max_gain_db).min(max_gain_db)
+    }
+}
+
+/// Raises Q to at least `MIN_Q`, NaN included
+#[inline]
+pub(crate) fn sanitize_q<T: Float>(q_value: T) -> T {
+    q_value.max(cast(MIN_Q as f64))
+}
+
+/// Lowercases a filter type name and strips separators, so "Low_Pass" matches "lowpass"
+pub(crate) fn normalize_filter_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Converts a constant into the sample type of a filter
+#[inline(always)]
+pub(crate) fn cast<T: Float>(value: f64) -> T {
+    T::from(value).unwrap()
+}
+
+/// Roots of a z^2 + b z + c. Real roots use the cancellation free form, so a root close to z = 1
+/// next to one close to 0 keeps its precision. With a = 0 the missing root is infinite.
+pub(crate) fn quadratic_roots<T: Float>(a: T, b: T, c: T) -> [Complex<T>; 2] {
+    let two = cast::<T>(2.0);
+    if a == T::zero() {
+        let infinite = Complex::new(T::infinity(), T::zero());
+        return [Complex::new(-c / b, T::zero()), infinite];
+    }
+    let discriminant = b * b - cast::<T>(4.0) * a * c;
+    if discriminant >= T::zero() {
+        let q = -(b + b.signum() * discriminant.sqrt()) / two;
+        if q == T::zero() {
+            // b and c are zero: a double root at the origin
+            return [Complex::new(T::zero(), T::zero()); 2];
+        }
+        [
+            Complex::new(q / a, T::zero()),
+            Complex::new(c / q, T::zero()),
+        ]
+    } else {
+        let re = -b / (two * a);
+        let im = (-discriminant).sqrt() / (two * a);
+        [Complex::new(re, im), Complex::new(re, -im)]
+    }
+}
+
+/// Flushes a state variable that has decayed below the smallest normal float to zero. Denormal
+/// arithmetic is very slow on x86, and a filter fed silence after a loud passage spends ages in
+/// that range. Compiles to nothing without the `denormal-protection` feature.
+#[inline(always)]
+pub(crate) fn flush_denormal<T: Float>(value: T) -> T {
+    if cfg!(feature = "denormal-protection") && value.abs() < T::min_positive_value() {
+        T::zero()
+    } else {
+        value
+    }
+}
+
+/// Limits a cutoff frequency to the range the filter constructors can realize. NaN is treated
+/// as the lowest cutoff.
+#[inline]
+pub(crate) fn clamp_cutoff<T: Float>(cutoff_hz: T, sample_rate_hz: T) -> T {
+    let max_cutoff_hz = sample_rate_hz * T::from(MAX_CUTOFF_RATIO).unwrap();
+    let min_cutoff_hz = T::from(MIN_CUTOFF_HZ).unwrap();
+    if cutoff_hz > max_cutoff_hz {
+        max_cutoff_hz
+    } else if cutoff_hz >= min_cutoff_hz {
+        cutoff_hz
+    } else {
+        min_cutoff_hz
+    }
+}
+
+/// Fast approximation of the SVF prewarp g = tan(PI * cutoff_hz / sample_rate_hz) for audio rate
+/// coefficient updates. The cutoff is clamped like in the constructors, and the cutoff realized by
+/// the approximate g is within 0.02% of cutoff_hz over the whole range. g is always finite and
+/// positive, so the filter stays stable.
+#[inline]
+pub fn fast_prewarp<T: Float>(cutoff_hz: T, sample_rate_hz: T) -> T {
+    let cutoff_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+    let x = cast::<T>(core::f64::consts::PI) * cutoff_hz / sample_rate_hz;
+    // Pade approximant on [0, PI/4], mirrored with tan(x) = 1 / tan(PI/2 - x) above that
+    let tan = |x: T| {
+        let x2 = x * x;
+        x * (cast::<T>(15.0) - x2) / (cast::<T>(15.0) - cast::<T>(6.0) * x2)
+    };
+    let quarter_pi = cast::<T>(core::f64::consts::FRAC_PI_4);
+    if x <= quarter_pi {
+        tan(x)
+    } else {
+        T::one() / tan(quarter_pi + quarter_pi - x)
+    }
+}
+
+/// Level as a fraction of the ceiling below which `soft_clip` passes samples through unchanged
+pub const SOFT_CLIP_KNEE: f32 = 0.5;
+
+/// Smoothly limits a sample to ±ceiling for the `process_block_clipped` output stages. Below
+/// `SOFT_CLIP_KNEE` times the ceiling the sample is returned as is, above the knee a tanh curve
+/// with unity slope at the knee approaches the ceiling without going past it. A ceiling that is
+/// not positive silences the output.
+#[inline]
+pub fn soft_clip<T: Float>(sample: T, ceiling: T) -> T {
+    let knee = cast::<T>(SOFT_CLIP_KNEE as f64) * ceiling;
+    let magnitude = sample.abs();
+    if magnitude <= knee {
+        return sample;
+    }
+    if ceiling.is_nan() || ceiling <= T::zero() {
+        return T::zero();
+    }
+    let headroom = ceiling - knee;
+    let clipped = knee + headroom * ((magnitude - knee) / headroom).tanh();
+    if sample < T::zero() {
+        -clipped
+    } else {
+        clipped
+    }
+}
+
+/// Polynomial sine and cosine of angle shifted by a multiple of PI into [-PI/2, PI/2], so
+/// their ratio is tan(angle) and the cosine is not negative. For batched response plotting,
+/// where the bilinear transform maps the frequency to j tan(PI f / fs). Both come from the
+/// sine series, the cosine as the sine of PI/2 - |x|, which keeps their relative error below
+/// 1e-7 down to the smallest values, close to DC and Nyquist. Branch free apart from the
+/// abs, so loops over it can be vectorized.
+#[inline(always)]
+pub(crate) fn fast_tan_parts<T: Float>(angle: T) -> (T, T) {
+    let pi = cast::<T>(core::f64::consts::PI);
+    let half_pi = cast::<T>(core::f64::consts::FRAC_PI_2);
+    let x = angle - pi * (angle / pi).round();
+    (fast_sin(x), fast_sin(half_pi - x.abs()))
+}
+
+/// Taylor series of the sine to x^11, with a relative error below 6e-8 on [-PI/2, PI/2]
+#[inline(always)]
+fn fast_sin<T: Float>(x: T) -> T {
+    let x2 = x * x;
+    let mut s = cast::<T>(-1.0 / 39_916_800.0);
+    s = s * x2 + cast::<T>(1.0 / 362_880.0);
+    s = s * x2 + cast::<T>(-1.0 / 5040.0);
+    s = s * x2 + cast::<T>(1.0 / 120.0);
+    s = s * x2 + cast::<T>(-1.0 / 6.0);
+    x + x * x2 * s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use first_order_iir::{IIR1Coefficients, IIR1};
+    use second_order_iir::{IIR2Coefficients, IIR2};
+
+    fn chain<T: Float>(sample_rate_hz: T) -> Vec<T> {
+        let mut first = IIR1::from(IIR1Coefficients::lowpass(
+            cast(1000.0),
+            T::zero(),
+            sample_rate_hz,
+        ));
+        let mut second = IIR2::from(IIR2Coefficients::highpass(
+            cast(100.0),
+            T::zero(),
+            T::one(),
+            sample_rate_hz,
+        ));
+        (0..100)
+            .map(|_| second.process(first.process(T::one())))
+            .collect()
+    }
+
+    #[test]
+    fn test_mixed_order_chain() {
+        let output32 = chain(48000.0f32);
+        let output64 = chain(48000.0f64);
+        for (y32, y64) in output32.iter().zip(output64.iter()) {
+            assert!((*y32 as f64 - y64).abs() < 1e-5);
+        }
+
+        let sample_rate_hz = 48000.0f64;
+        let mut first = IIR1::from(IIR1Coefficients::lowpass(1000.0, 0.0, sample_rate_hz));
+        let mut second = IIR2::from(IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, sample_rate_hz));
+        assert!(second.process(first.process(1.0)).is_finite());
+    }
+
+    #[test]
+    fn test_fast_tan_parts() {
+        for i in -10_000..10_000 {
+            let angle = i as f64 * 0.003_7;
+            let (sin, cos) = fast_tan_parts(angle);
+            assert!(cos >= 0.0);
+            assert!((sin.atan2(cos) - angle.tan().atan()).abs() < 1e-7);
+        }
+        // The relative error holds next to 0 and PI/2, where 1 - cos and 1 + cos would cancel
+        let half_pi = core::f64::consts::FRAC_PI_2;
+        for offset in [1e-9, 1e-6, 1e-4, 1e-2, 0.3] {
+            let (sin, cos) = fast_tan_parts(offset);
+            assert!((sin / offset.sin() - 1.0).abs() < 1e-7);
+            assert!((cos / offset.cos() - 1.0).abs() < 1e-7);
+            let (sin, cos) = fast_tan_parts(half_pi - offset);
+            assert!((sin / offset.cos() - 1.0).abs() < 1e-7);
+            assert!((cos / offset.sin() - 1.0).abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_fast_prewarp() {
+        let sample_rate_hz = 48000.0f64;
+        let max_cutoff_hz = sample_rate_hz * MAX_CUTOFF_RATIO as f64;
+        let mut last_g = 0.0;
+        for i in 0..=10000 {
+            let cutoff_hz = max_cutoff_hz * i as f64 / 10000.0;
+            let g = fast_prewarp(cutoff_hz, sample_rate_hz);
+            assert!(g.is_finite() && g > last_g);
+            last_g = g;
+
+            let clamped_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+            let realized_hz = g.atan() * sample_rate_hz / core::f64::consts::PI;
+            assert!((realized_hz - clamped_hz).abs() / clamped_hz < 2e-4);
+        }
+        let g = fast_prewarp(20000.0f32, 44100.0);
+        let exact = (core::f32::consts::PI * 20000.0 / 44100.0).tan();
+        assert!((g - exact).abs() / exact < 1e-3);
+    }
+
+    #[test]
+    fn test_soft_clip() {
+        let mut last = -1.0;
+        for i in -4000..=4000 {
+            let x = i as f64 * 0.001;
+            let y = soft_clip(x, 0.8);
+            if x.abs() <= 0.4 {
+                assert_eq!(y, x);
+            }
+            assert!(y.abs() <= 0.8 && y > last);
+            last = y;
+        }
+        assert_eq!(soft_clip(1.0e9f32, 1.0), 1.0);
+        assert_eq!(soft_clip(-1.0e9f32, 1.0), -1.0);
+        assert_eq!(soft_clip(0.5f32, f32::NAN), 0.0);
+    }
+}
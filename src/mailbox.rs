@@ -0,0 +1,231 @@
+//! Handing coefficients from a parameter thread to the audio thread without locks.
+//!
+//! `mailbox` returns a sender and a receiver sharing a triple buffer: one slot the sender writes
+//! into, one the receiver reads from, and one in between that they swap their slot with. Both
+//! `store` and `load` are a fixed amount of work with a single atomic swap, so neither side ever
+//! waits for the other, and the receiver always sees a complete set of coefficients. Values in
+//! between loads are skipped, the receiver only gets the most recent one.
+//!
+//! The slots are made of atomics, so there is no unsafe code. Values go through `f64`, which is
+//! exact for `f32` and `f64` coefficients.
+//!
+//! ```
+//! use basic_audio_filters::mailbox::mailbox;
+//! use basic_audio_filters::second_order_iir::{IIR2Coefficients, IIR2};
+//!
+//! let (mut sender, mut receiver) = mailbox(IIR2Coefficients::bypass());
+//! let ui_thread = std::thread::spawn(move || {
+//!     sender.store(IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0));
+//! });
+//! ui_thread.join().unwrap();
+//!
+//! // At the start of every block on the audio thread
+//! let mut filter = IIR2::from(IIR2Coefficients::bypass());
+//! if let Some(coeffs) = receiver.try_load() {
+//!     filter.update(coeffs);
+//! }
+//! ```
+
+use alloc::sync::Arc;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use num_traits::Float;
+
+use crate::cast;
+use crate::second_order_iir::IIR2Coefficients;
+
+/// Set in the middle index when it holds a value the receiver has not taken yet
+const FRESH: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+#[derive(Default)]
+struct Slot([AtomicU64; 10]);
+
+impl Slot {
+    fn write<T: Float>(&self, coeffs: &IIR2Coefficients<T>) {
+        let IIR2Coefficients {
+            a,
+            g,
+            gpow2,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        } = *coeffs;
+        for (field, value) in self.0.iter().zip([a, g, gpow2, k, a1, a2, a3, m0, m1, m2]) {
+            let bits = value.to_f64().unwrap_or(f64::NAN).to_bits();
+            field.store(bits, Ordering::Relaxed);
+        }
+    }
+
+    fn read<T: Float>(&self) -> IIR2Coefficients<T> {
+        let [a, g, gpow2, k, a1, a2, a3, m0, m1, m2] =
+            core::array::from_fn(|i| cast::<T>(f64::from_bits(self.0[i].load(Ordering::Relaxed))));
+        IIR2Coefficients {
+            a,
+            g,
+            gpow2,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        }
+    }
+}
+
+struct TripleBuffer {
+    slots: [Slot; 3],
+    /// Index of the slot between sender and receiver, plus `FRESH`
+    middle: AtomicU8,
+}
+
+/// Creates a connected sender and receiver, the receiver starts out with initial
+pub fn mailbox<T: Float>(
+    initial: IIR2Coefficients<T>,
+) -> (CoefficientsSender<T>, CoefficientsReceiver<T>) {
+    let buffer = Arc::new(TripleBuffer {
+        slots: Default::default(),
+        middle: AtomicU8::new(1),
+    });
+    buffer.slots[2].write(&initial);
+    let sender = CoefficientsSender {
+        buffer: buffer.clone(),
+        slot: 0,
+        marker: PhantomData,
+    };
+    let receiver = CoefficientsReceiver {
+        buffer,
+        slot: 2,
+        current: initial,
+    };
+    (sender, receiver)
+}
+
+/// Writing end of a `mailbox`, can be moved to any thread. Wrap it in a mutex to store from
+/// several threads.
+pub struct CoefficientsSender<T = f32> {
+    buffer: Arc<TripleBuffer>,
+    slot: u8,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T: Float> CoefficientsSender<T> {
+    /// Publishes coeffs, replacing any value the receiver has not loaded yet
+    pub fn store(&mut self, coeffs: IIR2Coefficients<T>) {
+        self.buffer.slots[self.slot as usize].write(&coeffs);
+        let previous = self.buffer.middle.swap(self.slot | FRESH, Ordering::AcqRel);
+        self.slot = previous & INDEX;
+    }
+}
+
+/// Reading end of a `mailbox`, for the audio thread
+pub struct CoefficientsReceiver<T = f32> {
+    buffer: Arc<TripleBuffer>,
+    slot: u8,
+    current: IIR2Coefficients<T>,
+}
+
+impl<T: Float> CoefficientsReceiver<T> {
+    /// Takes the most recently stored coefficients if there are new ones since the last load
+    pub fn try_load(&mut self) -> Option<IIR2Coefficients<T>> {
+        if self.buffer.middle.load(Ordering::Relaxed) & FRESH == 0 {
+            return None;
+        }
+        let previous = self.buffer.middle.swap(self.slot, Ordering::AcqRel);
+        self.slot = previous & INDEX;
+        self.current = self.buffer.slots[self.slot as usize].read();
+        Some(self.current)
+    }
+
+    /// The most recently stored coefficients, or the last ones loaded if nothing new arrived
+    pub fn load(&mut self) -> IIR2Coefficients<T> {
+        self.try_load().unwrap_or(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(value: f64) -> IIR2Coefficients<f64> {
+        IIR2Coefficients {
+            a: value,
+            g: value,
+            gpow2: value,
+            k: value,
+            a1: value,
+            a2: value,
+            a3: value,
+            m0: value,
+            m1: value,
+            m2: value,
+        }
+    }
+
+    fn fields(coeffs: &IIR2Coefficients<f64>) -> [f64; 10] {
+        [
+            coeffs.a,
+            coeffs.g,
+            coeffs.gpow2,
+            coeffs.k,
+            coeffs.a1,
+            coeffs.a2,
+            coeffs.a3,
+            coeffs.m0,
+            coeffs.m1,
+            coeffs.m2,
+        ]
+    }
+
+    #[test]
+    fn test_mailbox_latest_value() {
+        let (mut sender, mut receiver) = mailbox(uniform(0.0));
+        assert!(receiver.try_load().is_none());
+        assert_eq!(receiver.load().a, 0.0);
+
+        sender.store(uniform(1.0));
+        sender.store(uniform(2.0));
+        assert_eq!(receiver.try_load().map(|c| c.a), Some(2.0));
+        assert!(receiver.try_load().is_none());
+        assert_eq!(receiver.load().a, 2.0);
+
+        // f32 coefficients come back bit exact
+        let coeffs = IIR2Coefficients::<f32>::bell(1234.5, 3.3, 0.7, 44100.0);
+        let (mut sender, mut receiver) = mailbox(IIR2Coefficients::bypass());
+        sender.store(coeffs);
+        let loaded = receiver.load();
+        assert_eq!(loaded.k.to_bits(), coeffs.k.to_bits());
+        assert_eq!(loaded.m1.to_bits(), coeffs.m1.to_bits());
+    }
+
+    #[test]
+    fn test_mailbox_no_tearing() {
+        let stores = 200_000;
+        let (mut sender, mut receiver) = mailbox(uniform(0.0));
+        let writer = std::thread::spawn(move || {
+            for i in 1..=stores {
+                sender.store(uniform(i as f64));
+            }
+        });
+
+        // Every load has to be one complete set, and they never go back in time
+        let mut last = 0.0;
+        loop {
+            let values = fields(&receiver.load());
+            assert!(values.iter().all(|&x| x == values[0]));
+            assert!(values[0] >= last);
+            last = values[0];
+            if last == stores as f64 {
+                break;
+            }
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use num_traits::Float;
+
+use crate::second_order_iir::IIR2Coefficients;
+
+/// Internal states of N channels sharing one set of SVF coefficients
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2Multi<const N: usize, T = f32> {
+    ic1eq: [T; N],
+    ic2eq: [T; N],
+    pub coeffs: IIR2Coefficients<T>,
+}
+
+impl<const N: usize, T: Float> IIR2Multi<N, T> {
+    /// Creates a multichannel SVF from a set of filter coefficients
+    #[inline]
+    pub fn from(coefficients: IIR2Coefficients<T>) -> Self {
+        IIR2Multi {
+            ic1eq: [T::zero(); N],
+            ic2eq: [T::zero(); N],
+            coeffs: coefficients,
+        }
+    }
+
+    /// Processes one sample for every channel
+    #[inline]
+    pub fn process(&mut self, frame: [T; N]) -> [T; N] {
+        let coeffs = self.coeffs;
+        let mut output = [T::zero(); N];
+
+        for channel in 0..N {
+            let input_sample = frame[channel];
+            let (v1, v2) = coeffs.tick(
+                &mut self.ic1eq[channel],
+                &mut self.ic2eq[channel],
+                input_sample,
+            );
+            output[channel] = coeffs.output(input_sample, v1, v2);
+        }
+
+        output
+    }
+
+    /// Processes an interleaved buffer of N channels in place, one frame at a time
+    ///
+    /// # Panics
+    ///
+    /// If the length is not a multiple of N.
+    #[inline]
+    pub fn process_interleaved(&mut self, buffer: &mut [T]) {
+        assert!(
+            buffer.len().is_multiple_of(N),
+            "interleaved buffer length is not a multiple of the channel count"
+        );
+        for frame in buffer.chunks_exact_mut(N) {
+            let input: [T; N] = core::array::from_fn(|channel| frame[channel]);
+            frame.copy_from_slice(&self.process(input));
+        }
+    }
+
+    #[inline]
+    pub fn update(&mut self, new_coefficients: IIR2Coefficients<T>) {
+        self.coeffs = new_coefficients;
+    }
+
+    /// Clears the internal state of one channel
+    #[inline]
+    pub fn reset_channel(&mut self, channel: usize) {
+        self.ic1eq[channel] = T::zero();
+        self.ic2eq[channel] = T::zero();
+    }
+
+    /// Clears the internal state of every channel, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        self.ic1eq = [T::zero(); N];
+        self.ic2eq = [T::zero(); N];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::IIR2;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    fn compare_against_scalar<const N: usize>() {
+        let coeffs = IIR2Coefficients::highshelf(1000.0, 6.0, 1.0, 48000.0);
+        let mut filter = IIR2Multi::<N>::from(coeffs);
+        let mut scalar = [IIR2::from(coeffs); N];
+
+        for i in 0..1000 {
+            let mut frame = [0.0; N];
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = rand((i * N + channel) as f32);
+            }
+            let output = filter.process(frame);
+            for channel in 0..N {
+                assert_eq!(output[channel], scalar[channel].process(frame[channel]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_multi() {
+        compare_against_scalar::<1>();
+        compare_against_scalar::<8>();
+    }
+
+    #[test]
+    fn test_iir2_multi_interleaved() {
+        let coeffs = IIR2Coefficients::lowpass(3000.0, 0.0, 0.707, 48000.0);
+        let mut interleaved: Vec<f32> = (0..3000).map(|x| rand(x as f32)).collect();
+        let mut scalar = [IIR2::from(coeffs); 3];
+        let expected: Vec<f32> = interleaved
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| scalar[i % 3].process(x))
+            .collect();
+
+        IIR2Multi::<3>::from(coeffs).process_interleaved(&mut interleaved);
+        assert_eq!(interleaved, expected);
+    }
+
+    #[test]
+    fn test_iir2_multi_reset_channel() {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, 48000.0);
+        let mut filter = IIR2Multi::<2>::from(coeffs);
+        filter.process([1.0, 1.0]);
+        filter.reset_channel(0);
+
+        let output = filter.process([0.0, 0.0]);
+        assert_eq!(output[0], 0.0);
+        assert_ne!(output[1], 0.0);
+    }
+}
@@ -0,0 +1,215 @@
+use num_traits::Float;
+
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, flush_denormal};
+
+/// Curve of the saturation inside `NonlinearIIR2`, each with unity slope at 0 and limits of ±1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Saturation {
+    /// tanh(x), the classic transistor ladder and OTA curve
+    Tanh,
+    /// x / sqrt(1 + x²), a softer knee that is cheaper than tanh
+    Algebraic,
+}
+
+impl Saturation {
+    #[inline]
+    fn apply<T: Float>(self, x: T) -> T {
+        match self {
+            Saturation::Tanh => x.tanh(),
+            Saturation::Algebraic => x / (T::one() + x * x).sqrt(),
+        }
+    }
+}
+
+/// SVF with saturation in its loop, for analog style synth filters.
+///
+/// The input of the first integrator, the input minus the low pass feedback, and the state of
+/// the band pass integrator, where it feeds the second one and the damping, go through
+/// `curve(drive * x) / drive`. Small signals see unity gain and the linear filter, loud ones are
+/// compressed and pick up harmonics. As both saturated terms are within ±1 / drive, the band
+/// pass output is too, which keeps the resonance bounded however high Q is. The stored band pass
+/// state 2 v1 - sat(ic1eq) is not saturated itself, it is only bounded by (1 + 2 (a1 + a2)) /
+/// drive, a few times 1 / drive. A drive of 0 skips the saturation, and the output is then bit
+/// identical to `IIR2` with the same coefficients.
+#[derive(Copy, Clone, Debug)]
+pub struct NonlinearIIR2<T = f32> {
+    ic1eq: T,
+    ic2eq: T,
+    drive: T,
+    saturation: Saturation,
+    pub coeffs: IIR2Coefficients<T>,
+}
+
+impl<T: Float> NonlinearIIR2<T> {
+    /// Creates a filter with tanh saturation and the given drive
+    pub fn from(coefficients: IIR2Coefficients<T>, drive: T) -> Self {
+        let mut filter = NonlinearIIR2 {
+            ic1eq: T::zero(),
+            ic2eq: T::zero(),
+            drive: T::zero(),
+            saturation: Saturation::Tanh,
+            coeffs: coefficients,
+        };
+        filter.set_drive(drive);
+        filter
+    }
+
+    /// Sets the drive, where negative values count as positive ones and NaN as 0. Higher drive
+    /// saturates at lower levels, at 1 the saturated band pass state tops out at ±1.
+    pub fn set_drive(&mut self, drive: T) {
+        self.drive = if drive.is_nan() {
+            T::zero()
+        } else {
+            drive.abs()
+        };
+    }
+
+    pub fn drive(&self) -> T {
+        self.drive
+    }
+
+    pub fn set_saturation(&mut self, saturation: Saturation) {
+        self.saturation = saturation;
+    }
+
+    pub fn saturation(&self) -> Saturation {
+        self.saturation
+    }
+
+    #[inline]
+    fn saturate(&self, x: T) -> T {
+        self.saturation.apply(x * self.drive) / self.drive
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        if self.drive == T::zero() {
+            // The same step as IIR2::process
+            let (v1, v2) = self
+                .coeffs
+                .tick(&mut self.ic1eq, &mut self.ic2eq, input_sample);
+            return self.coeffs.output(input_sample, v1, v2);
+        }
+
+        let v3 = self.saturate(input_sample - self.ic2eq);
+        let ic1eq = self.saturate(self.ic1eq);
+        let v1 = self.coeffs.a1 * ic1eq + self.coeffs.a2 * v3;
+        let v2 = self.ic2eq + self.coeffs.a2 * ic1eq + self.coeffs.a3 * v3;
+        self.ic1eq = flush_denormal(cast::<T>(2.0) * v1 - ic1eq);
+        self.ic2eq = flush_denormal(cast::<T>(2.0) * v2 - self.ic2eq);
+
+        self.coeffs.output(input_sample, v1, v2)
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Switches to new coefficients, keeping the state
+    #[inline]
+    pub fn update(&mut self, new_coefficients: IIR2Coefficients<T>) {
+        self.coeffs = new_coefficients;
+    }
+
+    /// Clears the internal state, keeping the coefficients and drive
+    pub fn reset(&mut self) {
+        self.ic1eq = T::zero();
+        self.ic2eq = T::zero();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::IIR2;
+    use core::f64::consts::TAU;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    #[test]
+    fn test_nonlinear_iir2_zero_drive_matches_iir2() {
+        let fs = 48000.0;
+        let designs = [
+            IIR2Coefficients::lowpass(800.0, 0.0, 8.0, fs),
+            IIR2Coefficients::bandpass(3000.0, 0.0, 2.0, fs),
+            IIR2Coefficients::highshelf(6000.0, -9.0, 0.7, fs),
+        ];
+        for coeffs in designs {
+            let mut linear = IIR2::from(coeffs);
+            let mut nonlinear = NonlinearIIR2::from(coeffs, 0.0);
+            for i in 0..2000 {
+                if i == 1000 {
+                    let changed = IIR2Coefficients::bell(500.0, 6.0, 1.0, fs);
+                    linear.update(changed);
+                    nonlinear.update(changed);
+                }
+                let x = rand(i as f32) * 4.0;
+                assert_eq!(nonlinear.process(x).to_bits(), linear.process(x).to_bits());
+            }
+        }
+
+        let mut filter = NonlinearIIR2::<f32>::from(designs[0], f32::NAN);
+        assert_eq!(filter.drive(), 0.0);
+        filter.set_drive(-2.0);
+        assert_eq!(filter.drive(), 2.0);
+    }
+
+    #[test]
+    fn test_nonlinear_iir2_bounded_resonance() {
+        let fs = 48000.0;
+        let coeffs = IIR2Coefficients::<f64>::lowpass(1000.0, 0.0, 40.0, fs);
+        let peak = |filter: &mut dyn FnMut(f64) -> f64| {
+            (0..48000).fold(0.0f64, |peak, i| {
+                let x = (TAU * 1000.0 * i as f64 / fs).sin();
+                peak.max(filter(x).abs())
+            })
+        };
+
+        // A full scale sine at the resonance rings up to about Q in the linear filter
+        let mut linear = IIR2::from(coeffs);
+        assert!(peak(&mut |x| linear.process(x)) > 10.0);
+
+        for saturation in [Saturation::Tanh, Saturation::Algebraic] {
+            let mut driven = NonlinearIIR2::from(coeffs, 2.0);
+            driven.set_saturation(saturation);
+            let driven_peak = peak(&mut |x| driven.process(x));
+            assert!(driven_peak.is_finite() && driven_peak < 10.0);
+
+            // Quiet signals still see the linear filter
+            driven.reset();
+            let mut linear = IIR2::from(coeffs);
+            for i in 0..480 {
+                let x = 1e-4 * rand(i as f32) as f64;
+                assert!((driven.process(x) - linear.process(x)).abs() < 1e-6);
+            }
+        }
+
+        // Pulses at full scale stay bounded too
+        let mut driven = NonlinearIIR2::from(coeffs, 1.0);
+        let mut block: Vec<f64> = (0..48000)
+            .map(|i| if (i / 24) % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        driven.process_block(&mut block);
+        assert!(block.iter().all(|y| y.abs() < 10.0));
+
+        // The saturated band pass state stays within 1 / drive, the stored one within
+        // 1 + 2 (a1 + a2) times that
+        let drive = 2.0;
+        let mut driven = NonlinearIIR2::from(coeffs, drive);
+        let (mut stored, mut saturated) = (0.0f64, 0.0f64);
+        for i in 0..48000 {
+            driven.process((TAU * 1000.0 * i as f64 / fs).sin());
+            stored = stored.max(driven.ic1eq.abs());
+            saturated = saturated.max(driven.saturate(driven.ic1eq).abs());
+        }
+        assert!(saturated <= 1.0 / drive);
+        assert!(stored <= (1.0 + 2.0 * (coeffs.a1 + coeffs.a2)) / drive);
+    }
+}
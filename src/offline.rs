@@ -0,0 +1,341 @@
+//! Filtering WAV files with a chain of second order filters, as used by the `filter_wav` example.
+//!
+//! A chain is written like command line arguments, a flag with the filter type followed by its
+//! parameters: `--bell 1000 6 1.0 --highpass 40 0.707`. Shelves, bells, the constant Q bell
+//! (`constantqbell`) and tilts take cutoff, gain and Q, every other type only cutoff and Q. The
+//! type names are parsed like `IIR2FilterType::from_str`.
+//!
+//! 16 bit integer and 32 bit float WAVs are supported, the output has the same format as the
+//! input. 16 bit output saturates at full scale instead of wrapping around.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2FilterType, IIR2};
+use crate::FilterDesignError;
+
+/// One filter of a chain
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FilterSpec {
+    pub filter_type: IIR2FilterType,
+    pub cutoff_hz: f32,
+    pub gain_db: f32,
+    pub q_value: f32,
+}
+
+impl FilterSpec {
+    pub fn coefficients(&self, sample_rate_hz: f32) -> Result<IIR2Coefficients, FilterDesignError> {
+        IIR2Coefficients::try_new(
+            self.filter_type,
+            self.cutoff_hz,
+            self.gain_db,
+            self.q_value,
+            sample_rate_hz,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum OfflineError {
+    /// The filter chain arguments could not be parsed
+    Arguments(String),
+    /// A filter of the chain is invalid at the sample rate of the file
+    Design(FilterDesignError),
+    /// The file is neither 16 bit integer nor 32 bit float
+    UnsupportedFormat(WavSpec),
+    Wav(hound::Error),
+}
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OfflineError::Arguments(message) => write!(f, "{}", message),
+            OfflineError::Design(error) => write!(f, "invalid filter: {}", error),
+            OfflineError::UnsupportedFormat(spec) => write!(
+                f,
+                "unsupported sample format, {} bit {:?}",
+                spec.bits_per_sample, spec.sample_format
+            ),
+            OfflineError::Wav(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for OfflineError {}
+
+impl From<hound::Error> for OfflineError {
+    fn from(error: hound::Error) -> Self {
+        OfflineError::Wav(error)
+    }
+}
+
+impl From<FilterDesignError> for OfflineError {
+    fn from(error: FilterDesignError) -> Self {
+        OfflineError::Design(error)
+    }
+}
+
+fn takes_gain(filter_type: IIR2FilterType) -> bool {
+    matches!(
+        filter_type,
+        IIR2FilterType::LowShelf
+            | IIR2FilterType::HighShelf
+            | IIR2FilterType::Bell
+            | IIR2FilterType::ConstantQBell
+            | IIR2FilterType::Tilt
+    )
+}
+
+/// Parses a chain like `--bell 1000 6 1.0 --highpass 40 0.707`, see the module documentation
+pub fn parse_filter_chain<S: AsRef<str>>(args: &[S]) -> Result<Vec<FilterSpec>, OfflineError> {
+    let mut chain = Vec::new();
+    let mut args = args.iter().map(AsRef::as_ref);
+    while let Some(flag) = args.next() {
+        let name = flag.strip_prefix("--").ok_or_else(|| {
+            OfflineError::Arguments(format!("expected a filter, got \"{}\"", flag))
+        })?;
+        let filter_type = IIR2FilterType::from_str(name)
+            .map_err(|_| OfflineError::Arguments(format!("unknown filter type \"{}\"", name)))?;
+        let count = if takes_gain(filter_type) { 3 } else { 2 };
+        let mut values = [0.0f32; 3];
+        for value in &mut values[..count] {
+            let arg = args.next().ok_or_else(|| {
+                OfflineError::Arguments(format!("{} takes {} values", flag, count))
+            })?;
+            *value = arg
+                .parse()
+                .map_err(|_| OfflineError::Arguments(format!("\"{}\" is not a number", arg)))?;
+        }
+        let [cutoff_hz, gain_db, q_value] = if count == 3 {
+            values
+        } else {
+            [values[0], 0.0, values[1]]
+        };
+        chain.push(FilterSpec {
+            filter_type,
+            cutoff_hz,
+            gain_db,
+            q_value,
+        });
+    }
+    Ok(chain)
+}
+
+/// Runs every channel of interleaved samples through the chain
+fn process_channels(
+    samples: &mut [f32],
+    channels: usize,
+    chain: &[FilterSpec],
+    sample_rate_hz: f32,
+) -> Result<(), OfflineError> {
+    let mut channel = vec![0.0; samples.len() / channels];
+    for spec in chain {
+        let coeffs = spec.coefficients(sample_rate_hz)?;
+        for c in 0..channels {
+            for (i, sample) in channel.iter_mut().enumerate() {
+                *sample = samples[i * channels + c];
+            }
+            IIR2::from(coeffs).process_block(&mut channel);
+            for (i, sample) in channel.iter().enumerate() {
+                samples[i * channels + c] = *sample;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a WAV from reader, filters it with chain and writes it to writer in the same format
+pub fn filter_wav<R: Read, W: Write + Seek>(
+    reader: R,
+    writer: W,
+    chain: &[FilterSpec],
+) -> Result<(), OfflineError> {
+    let reader = WavReader::new(reader)?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let sample_rate_hz = spec.sample_rate as f32;
+    let mut writer = WavWriter::new(writer, spec)?;
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, 16) => {
+            let scale = -(i16::MIN as f32);
+            let mut samples = reader
+                .into_samples::<i16>()
+                .map(|x| x.map(|x| x as f32 / scale))
+                .collect::<Result<Vec<f32>, _>>()?;
+            process_channels(&mut samples, channels, chain, sample_rate_hz)?;
+            for sample in samples {
+                let value = (sample * scale).round();
+                writer.write_sample(value.clamp(i16::MIN as f32, i16::MAX as f32) as i16)?;
+            }
+        }
+        (SampleFormat::Float, 32) => {
+            let mut samples = reader
+                .into_samples::<f32>()
+                .collect::<Result<Vec<f32>, _>>()?;
+            process_channels(&mut samples, channels, chain, sample_rate_hz)?;
+            for sample in samples {
+                writer.write_sample(sample)?;
+            }
+        }
+        _ => return Err(OfflineError::UnsupportedFormat(spec)),
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Filters the WAV file at input into a new file at output, see `filter_wav`
+pub fn filter_wav_file(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    chain: &[FilterSpec],
+) -> Result<(), OfflineError> {
+    let reader = BufReader::new(File::open(input).map_err(hound::Error::IoError)?);
+    let writer = BufWriter::new(File::create(output).map_err(hound::Error::IoError)?);
+    filter_wav(reader, writer, chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+    use std::io::Cursor;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// Two tones at 100 Hz and 5 kHz with amplitude each, one second long
+    fn tones(amplitude: f32) -> Vec<f32> {
+        (0..SAMPLE_RATE)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                amplitude * ((TAU * 100.0 * t).sin() + (TAU * 5000.0 * t).sin())
+            })
+            .collect()
+    }
+
+    fn wav<S: hound::Sample + Copy>(samples: &[S], bits: u16, format: SampleFormat) -> Vec<u8> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: bits,
+            sample_format: format,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    fn filtered(input: Vec<u8>, args: &[&str]) -> WavReader<Cursor<Vec<u8>>> {
+        let chain = parse_filter_chain(args).unwrap();
+        let mut output = Cursor::new(Vec::new());
+        filter_wav(Cursor::new(input), &mut output, &chain).unwrap();
+        WavReader::new(Cursor::new(output.into_inner())).unwrap()
+    }
+
+    /// RMS of the component at frequency_hz, over the second half to skip the transient
+    fn band_rms(samples: &[f32], frequency_hz: f32) -> f32 {
+        let tail = &samples[samples.len() / 2..];
+        let (re, im) = tail
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (i, &x)| {
+                let phase = TAU * frequency_hz * i as f32 / SAMPLE_RATE as f32;
+                (re + x * phase.cos(), im + x * phase.sin())
+            });
+        (re * re + im * im).sqrt() * 2.0 / tail.len() as f32 / 2.0f32.sqrt()
+    }
+
+    #[test]
+    fn test_parse_filter_chain() {
+        let chain =
+            parse_filter_chain(&["--bell", "1000", "6", "1.0", "--highpass", "40", "0.707"])
+                .unwrap();
+        assert_eq!(
+            chain,
+            [
+                FilterSpec {
+                    filter_type: IIR2FilterType::Bell,
+                    cutoff_hz: 1000.0,
+                    gain_db: 6.0,
+                    q_value: 1.0,
+                },
+                FilterSpec {
+                    filter_type: IIR2FilterType::HighPass,
+                    cutoff_hz: 40.0,
+                    gain_db: 0.0,
+                    q_value: 0.707,
+                },
+            ]
+        );
+        assert!(parse_filter_chain(&["--bell", "1000", "6"]).is_err());
+        assert_eq!(
+            parse_filter_chain(&["--constantqbell", "1000", "-3", "2.0"]).unwrap()[0].gain_db,
+            -3.0
+        );
+        assert!(parse_filter_chain(&["--ladder", "1000", "0.7"]).is_err());
+        assert!(parse_filter_chain(&["1000"]).is_err());
+        assert!(parse_filter_chain(&["--lowpass", "1k", "0.7"]).is_err());
+    }
+
+    #[test]
+    fn test_filter_wav_float() {
+        let input = wav(&tones(0.25), 32, SampleFormat::Float);
+        let mut reader = filtered(input, &["--lowpass", "1000", "0.707"]);
+        assert_eq!(reader.spec().sample_format, SampleFormat::Float);
+        let output: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        assert_eq!(output.len(), SAMPLE_RATE as usize);
+
+        let rms = 0.25 / 2.0f32.sqrt();
+        assert!((band_rms(&output, 100.0) / rms - 1.0).abs() < 0.01);
+        // At five times the cutoff a second order low pass is down about 28 dB
+        assert!(20.0 * (band_rms(&output, 5000.0) / rms).log10() < -26.0);
+    }
+
+    #[test]
+    fn test_filter_wav_16_bit() {
+        let samples: Vec<i16> = tones(0.25).iter().map(|x| (x * 32768.0) as i16).collect();
+        let mut reader = filtered(
+            wav(&samples, 16, SampleFormat::Int),
+            &["--highshelf", "2000", "-12", "0.7"],
+        );
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        let output: Vec<f32> = reader
+            .samples::<i16>()
+            .map(|x| x.unwrap() as f32 / 32768.0)
+            .collect();
+        let rms = 0.25 / 2.0f32.sqrt();
+        assert!(20.0 * (band_rms(&output, 100.0) / rms).log10() > -0.1);
+        assert!((20.0 * (band_rms(&output, 5000.0) / rms).log10() + 12.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_filter_wav_saturates() {
+        // Both tones at half scale boosted by 12 dB go well past full scale
+        let samples: Vec<i16> = tones(0.5).iter().map(|x| (x * 32767.0) as i16).collect();
+        let mut reader = filtered(
+            wav(&samples, 16, SampleFormat::Int),
+            &["--lowshelf", "1000", "12", "0.7"],
+        );
+        let output: Vec<i16> = reader.samples::<i16>().map(Result::unwrap).collect();
+        assert!(output.contains(&i16::MAX));
+        assert!(output.contains(&i16::MIN));
+        // Wrapping would flip the sign at the peaks of the 100 Hz tone
+        let quarter_period = SAMPLE_RATE as usize / 400;
+        for period in 10..90 {
+            let peak = period * SAMPLE_RATE as usize / 100 + quarter_period;
+            assert!(output[peak - 5..peak + 5].iter().all(|&x| x > 0));
+        }
+
+        let input = wav(&[0i32; 16], 24, SampleFormat::Int);
+        let result = filter_wav(Cursor::new(input), Cursor::new(Vec::new()), &[]);
+        assert!(matches!(result, Err(OfflineError::UnsupportedFormat(_))));
+    }
+}
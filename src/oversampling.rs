@@ -0,0 +1,411 @@
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::second_order_iir::{IIR2Design, IIR2};
+use crate::{cast, flush_denormal};
+
+/// Smallest attenuation in dB of `Oversampler2x` from `OVERSAMPLER_STOPBAND_RATIO` of the base
+/// sample rate up to the oversampled Nyquist
+pub const OVERSAMPLER_STOPBAND_DB: f32 = 84.9;
+
+/// Passband edge of `Oversampler2x` as a ratio of the base sample rate, 20 kHz at 44.1 kHz. The
+/// passband ripple is below 1e-7 dB.
+pub const OVERSAMPLER_PASSBAND_RATIO: f32 = 0.4536;
+
+/// Stopband edge of `Oversampler2x` as a ratio of the base sample rate, the mirror image of the
+/// passband edge
+pub const OVERSAMPLER_STOPBAND_RATIO: f32 = 0.5464;
+
+/// Coefficients of the half band, from the elliptic design of Laurent de Soras' HIIR library with
+/// a transition band of 0.0232 of the oversampled rate. The even ones make up the first path and
+/// the odd ones the second.
+const HALFBAND_COEFFICIENTS: [f64; 8] = [
+    0.053_662_356_131_265_08,
+    0.193_588_001_097_550_1,
+    0.372_559_234_555_883_2,
+    0.546_969_637_228_489_5,
+    0.693_291_544_753_319_3,
+    0.806_908_825_733_021_9,
+    0.894_143_716_584_353_2,
+    0.965_920_222_665_099_7,
+];
+
+const PATH_STAGES: usize = HALFBAND_COEFFICIENTS.len() / 2;
+
+/// Chain of first order all passes (a + z^-1) / (1 + a z^-1) running at the base rate, one
+/// polyphase branch of the half band
+#[derive(Copy, Clone, Debug)]
+struct AllpassPath<T> {
+    coefficients: [T; PATH_STAGES],
+    x1: [T; PATH_STAGES],
+    y1: [T; PATH_STAGES],
+}
+
+impl<T: Float> AllpassPath<T> {
+    fn new(first: usize) -> Self {
+        let mut coefficients = [T::zero(); PATH_STAGES];
+        for (stage, coefficient) in coefficients.iter_mut().enumerate() {
+            *coefficient = cast(HALFBAND_COEFFICIENTS[first + 2 * stage]);
+        }
+        AllpassPath {
+            coefficients,
+            x1: [T::zero(); PATH_STAGES],
+            y1: [T::zero(); PATH_STAGES],
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        let mut x = input_sample;
+        for stage in 0..PATH_STAGES {
+            let y = (x - self.y1[stage]) * self.coefficients[stage] + self.x1[stage];
+            self.x1[stage] = x;
+            self.y1[stage] = flush_denormal(y);
+            x = y;
+        }
+        x
+    }
+
+    /// Delay at DC in base rate samples
+    fn dc_delay(&self) -> T {
+        self.coefficients.iter().fold(T::zero(), |delay, &a| {
+            delay + (T::one() - a) / (T::one() + a)
+        })
+    }
+
+    fn reset(&mut self) {
+        self.x1 = [T::zero(); PATH_STAGES];
+        self.y1 = [T::zero(); PATH_STAGES];
+    }
+}
+
+/// 2x oversampling with a polyphase IIR half band, for running a nonlinear stage at twice the
+/// sample rate.
+///
+/// The half band is two all pass chains at the base rate whose outputs, interleaved, are the
+/// upsampled signal, and whose average, fed with the two halves of a frame, is the downsampled
+/// one. That is much cheaper than a FIR of the same steepness but, unlike a FIR, not linear
+/// phase. Up to `OVERSAMPLER_PASSBAND_RATIO` of the sample rate the response is flat, images and
+/// aliases from `OVERSAMPLER_STOPBAND_RATIO` on are at least `OVERSAMPLER_STOPBAND_DB` down, and
+/// in between is the transition band. Upsampling and downsampling each have their own state.
+#[derive(Copy, Clone, Debug)]
+pub struct Oversampler2x<T = f32> {
+    up: [AllpassPath<T>; 2],
+    down: [AllpassPath<T>; 2],
+}
+
+impl<T: Float> Oversampler2x<T> {
+    pub fn new() -> Self {
+        let paths = [AllpassPath::new(0), AllpassPath::new(1)];
+        Oversampler2x {
+            up: paths,
+            down: paths,
+        }
+    }
+
+    /// Two samples at twice the rate for one input sample
+    #[inline]
+    pub fn upsample(&mut self, input_sample: T) -> [T; 2] {
+        [
+            self.up[0].process(input_sample),
+            self.up[1].process(input_sample),
+        ]
+    }
+
+    /// One sample at the base rate for two samples at twice the rate, in the order `upsample`
+    /// returns them
+    #[inline]
+    pub fn downsample(&mut self, input: [T; 2]) -> T {
+        let first = self.down[0].process(input[1]);
+        let second = self.down[1].process(input[0]);
+        (first + second) * cast(0.5)
+    }
+
+    /// Delay of low frequencies through `upsample` and then `downsample`, in base rate samples.
+    /// It rises toward the passband edge, like for any minimum phase filter.
+    pub fn latency(&self) -> T {
+        // Each half band delays by its first path, twice the chain delay in oversampled samples.
+        // The downsampled output lines up with the second half of the frame, half a base rate
+        // sample later than the first.
+        self.up[0].dc_delay() + self.down[0].dc_delay() - cast(0.5)
+    }
+
+    /// Clears the state of both half bands
+    pub fn reset(&mut self) {
+        for path in self.up.iter_mut().chain(self.down.iter_mut()) {
+            path.reset();
+        }
+    }
+}
+
+impl<T: Float> Default for Oversampler2x<T> {
+    fn default() -> Self {
+        Oversampler2x::new()
+    }
+}
+
+/// How many times the host rate `OversampledIIR2` runs its filter at
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OversamplingFactor {
+    X2,
+    X4,
+}
+
+impl OversamplingFactor {
+    pub fn ratio(self) -> usize {
+        match self {
+            OversamplingFactor::X2 => 2,
+            OversamplingFactor::X4 => 4,
+        }
+    }
+}
+
+/// Second order filter running at 2x or 4x the host rate through `Oversampler2x`, so responses
+/// close to Nyquist keep the shape of the analog prototype that the bilinear transform cramps
+/// at the host rate. It also leaves room for nonlinear extensions of the filter.
+///
+/// Designs are given at the host rate and redesigned at the internal rate. 4x is two 2x stages
+/// nested, and everything above `OVERSAMPLER_PASSBAND_RATIO` of the host rate is removed by the
+/// half bands.
+#[derive(Copy, Clone, Debug)]
+pub struct OversampledIIR2<T = f32> {
+    filter: IIR2<T>,
+    design: IIR2Design<T>,
+    factor: OversamplingFactor,
+    outer: Oversampler2x<T>,
+    inner: Oversampler2x<T>,
+}
+
+impl<T: Float> OversampledIIR2<T> {
+    pub fn new(design: IIR2Design<T>, factor: OversamplingFactor) -> Self {
+        OversampledIIR2 {
+            filter: IIR2::from(Self::internal_design(design, factor).coefficients()),
+            design,
+            factor,
+            outer: Oversampler2x::new(),
+            inner: Oversampler2x::new(),
+        }
+    }
+
+    fn internal_design(design: IIR2Design<T>, factor: OversamplingFactor) -> IIR2Design<T> {
+        IIR2Design {
+            sample_rate_hz: design.sample_rate_hz * cast(factor.ratio() as f64),
+            ..design
+        }
+    }
+
+    /// The design at the host rate
+    pub fn design(&self) -> IIR2Design<T> {
+        self.design
+    }
+
+    pub fn factor(&self) -> OversamplingFactor {
+        self.factor
+    }
+
+    /// Switches to a new design at the host rate, keeping the state
+    pub fn update(&mut self, design: IIR2Design<T>) {
+        self.design = design;
+        self.filter
+            .update(Self::internal_design(design, self.factor).coefficients());
+    }
+
+    /// Delay of the resampling in host rate samples, on top of the delay of the filter itself
+    pub fn latency_samples(&self) -> T {
+        match self.factor {
+            OversamplingFactor::X2 => self.outer.latency(),
+            OversamplingFactor::X4 => self.outer.latency() + self.inner.latency() * cast(0.5),
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let mut frame = self.outer.upsample(input_sample);
+        match self.factor {
+            OversamplingFactor::X2 => {
+                for sample in frame.iter_mut() {
+                    *sample = self.filter.process(*sample);
+                }
+            }
+            OversamplingFactor::X4 => {
+                for sample in frame.iter_mut() {
+                    let inner = self.inner.upsample(*sample).map(|x| self.filter.process(x));
+                    *sample = self.inner.downsample(inner);
+                }
+            }
+        }
+        self.outer.downsample(frame)
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the state of the filter and the resampling
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.outer.reset();
+        self.inner.reset();
+    }
+
+    /// Response of the filter for frequency_hz, with sample_rate_hz the host rate the filter
+    /// runs at, so the coefficients are evaluated at the internal rate. Leaves out the half
+    /// bands, which are flat up to `OVERSAMPLER_PASSBAND_RATIO`.
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        let internal_rate_hz = sample_rate_hz * cast(self.factor.ratio() as f64);
+        self.filter
+            .coeffs
+            .get_bode_sample(frequency_hz, internal_rate_hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    /// Amplitude of the frequency_hz component, for signals with a whole number of periods
+    fn amplitude(signal: &[f64], frequency_hz: f64, sample_rate_hz: f64) -> f64 {
+        let (re, im) = signal
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, &x)| {
+                let phase = TAU * frequency_hz * n as f64 / sample_rate_hz;
+                (re + x * phase.cos(), im + x * phase.sin())
+            });
+        2.0 * (re * re + im * im).sqrt() / signal.len() as f64
+    }
+
+    #[test]
+    fn test_oversampler_round_trip() {
+        let sample_rate_hz = 44100.0;
+        let mut oversampler = Oversampler2x::<f64>::new();
+        let input: Vec<f64> = (0..44100)
+            .map(|i| (TAU * 1000.0 * i as f64 / sample_rate_hz).sin())
+            .collect();
+        let output: Vec<f64> = input
+            .iter()
+            .map(|&x| {
+                let frame = oversampler.upsample(x);
+                oversampler.downsample(frame)
+            })
+            .collect();
+        let level = amplitude(&output[4410..], 1000.0, sample_rate_hz);
+        assert!((20.0 * level.log10()).abs() < 0.1);
+
+        // The output trails the input by the latency, about 2.7 samples
+        let latency = oversampler.latency();
+        assert!(latency > 2.5 && latency < 3.0);
+        for (n, &y) in output.iter().enumerate().skip(4410).take(100) {
+            let delayed = (TAU * 1000.0 * (n as f64 - latency) / sample_rate_hz).sin();
+            assert!((y - delayed).abs() < 1e-3);
+        }
+
+        oversampler.reset();
+        assert_eq!(oversampler.upsample(0.0), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_oversampler_images_and_aliases() {
+        // 20 kHz at 44.1 kHz images to 24.1 kHz at the oversampled rate, and that folds back
+        // onto 20 kHz when downsampling. Bins are 10 Hz wide over 0.1 s.
+        let base_rate = 44100.0;
+        let high_rate = 88200.0;
+        let stopband = 10.0f64.powf(-OVERSAMPLER_STOPBAND_DB as f64 / 20.0);
+
+        let mut oversampler = Oversampler2x::<f64>::new();
+        let mut upsampled = Vec::new();
+        for i in 0..44100 {
+            let frame = oversampler.upsample((TAU * 20000.0 * i as f64 / base_rate).sin());
+            upsampled.extend_from_slice(&frame);
+        }
+        let tail = &upsampled[upsampled.len() - 8820..];
+        assert!((amplitude(tail, 20000.0, high_rate) - 1.0).abs() < 0.01);
+        assert!(amplitude(tail, 24100.0, high_rate) < stopband);
+
+        let mut downsampled = Vec::new();
+        for i in 0..44100 {
+            let frame = [2 * i, 2 * i + 1].map(|n| (TAU * 24100.0 * n as f64 / high_rate).sin());
+            downsampled.push(oversampler.downsample(frame));
+        }
+        let tail = &downsampled[downsampled.len() - 4410..];
+        assert!(amplitude(tail, 20000.0, base_rate) < stopband);
+    }
+
+    #[test]
+    fn test_oversampled_iir2_high_bell() {
+        use crate::second_order_iir::IIR2FilterType;
+
+        // A 16 kHz bell at 44.1 kHz, measured at 20 kHz against the analog prototype
+        let sample_rate_hz = 44100.0;
+        let design = IIR2Design {
+            filter_type: IIR2FilterType::Bell,
+            cutoff_hz: 16000.0,
+            gain_db: 12.0,
+            q_value: 1.0,
+            sample_rate_hz,
+        };
+        let a = 10.0f64.powf(12.0 / 40.0);
+        let k = 1.0 / a;
+        let s = Complex::new(0.0, 20000.0 / 16000.0);
+        let analog_db = 20.0
+            * ((s * s + s * k * a * a + 1.0) / (s * s + s * k + 1.0))
+                .norm()
+                .log10();
+
+        let measured_db = |process: &mut dyn FnMut(f64) -> f64| {
+            let output: Vec<f64> = (0..13230)
+                .map(|i| process((TAU * 20000.0 * i as f64 / sample_rate_hz).sin()))
+                .collect();
+            20.0 * amplitude(&output[8820..], 20000.0, sample_rate_hz).log10()
+        };
+        let mut plain = IIR2::from(design.coefficients());
+        let plain_error = (measured_db(&mut |x| plain.process(x)) - analog_db).abs();
+        let mut oversampled = OversampledIIR2::new(design, OversamplingFactor::X4);
+        let oversampled_error = (measured_db(&mut |x| oversampled.process(x)) - analog_db).abs();
+        let mut doubled = OversampledIIR2::new(design, OversamplingFactor::X2);
+        let doubled_error = (measured_db(&mut |x| doubled.process(x)) - analog_db).abs();
+
+        assert!(plain_error > 3.0);
+        assert!(oversampled_error < 0.5);
+        assert!(doubled_error < plain_error / 2.0);
+        assert!(oversampled_error < doubled_error);
+    }
+
+    #[test]
+    fn test_oversampled_iir2_latency() {
+        use crate::second_order_iir::IIR2FilterType;
+
+        // A 0 dB bell passes the signal unchanged, leaving only the resampling delay
+        let sample_rate_hz = 48000.0;
+        let design = IIR2Design {
+            filter_type: IIR2FilterType::Bell,
+            cutoff_hz: 1000.0,
+            gain_db: 0.0,
+            q_value: 1.0,
+            sample_rate_hz,
+        };
+        for factor in [OversamplingFactor::X2, OversamplingFactor::X4] {
+            let mut filter = OversampledIIR2::<f64>::new(design, factor);
+            let latency = filter.latency_samples();
+            let signal = |n: f64| (TAU * 500.0 * n / sample_rate_hz).sin();
+            let mut block: Vec<f64> = (0..4800).map(|i| signal(i as f64)).collect();
+            filter.process_block(&mut block);
+            for (n, &y) in block.iter().enumerate().skip(2400) {
+                assert!((y - signal(n as f64 - latency)).abs() < 1e-3);
+            }
+
+            filter.update(IIR2Design {
+                gain_db: -6.0,
+                ..design
+            });
+            let response = filter.get_bode_sample(1000.0, design.sample_rate_hz);
+            assert!((response.norm().log10() * 20.0 + 6.0).abs() < 1e-3);
+            filter.reset();
+            assert_eq!(filter.process(0.0), 0.0);
+        }
+    }
+}
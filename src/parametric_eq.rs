@@ -0,0 +1,323 @@
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2FilterType, IIR2};
+use crate::{cast, response_frequencies, BodePoint};
+
+/// Settings of one band of a `ParametricEq`
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EqBand<T = f32> {
+    pub filter_type: IIR2FilterType,
+    pub frequency_hz: T,
+    pub gain_db: T,
+    pub q_value: T,
+    pub enabled: bool,
+}
+
+impl<T: Float> EqBand<T> {
+    /// A disabled flat bell at 1 kHz
+    pub fn disabled() -> Self {
+        EqBand {
+            filter_type: IIR2FilterType::Bell,
+            frequency_hz: cast(1000.0),
+            gain_db: T::zero(),
+            q_value: cast(0.5),
+            enabled: false,
+        }
+    }
+
+    fn coefficients(&self, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        IIR2Coefficients::new(
+            self.filter_type,
+            self.frequency_hz,
+            self.gain_db,
+            self.q_value,
+            sample_rate_hz,
+        )
+    }
+}
+
+/// Length of the fade when a band is switched on or off, in seconds
+pub const TOGGLE_FADE_SECONDS: f64 = 0.01;
+
+/// Up to N SVF bands processed in series. Switching a band on or off fades it in or out
+/// between the dry and the filtered signal, and fully disabled bands are skipped.
+#[derive(Copy, Clone, Debug)]
+pub struct ParametricEq<const N: usize, T = f32> {
+    bands: [EqBand<T>; N],
+    filters: [IIR2<T>; N],
+    /// How far each band is faded in, from 0 for bypassed to toggle_samples for fully on
+    fade_positions: [usize; N],
+    toggle_samples: usize,
+    sample_rate_hz: T,
+}
+
+impl<const N: usize, T: Float> ParametricEq<N, T> {
+    /// Creates an EQ with every band disabled, fading bands over `TOGGLE_FADE_SECONDS`
+    pub fn new(sample_rate_hz: T) -> Self {
+        let band = EqBand::disabled();
+        ParametricEq {
+            bands: [band; N],
+            filters: [IIR2::from(band.coefficients(sample_rate_hz)); N],
+            fade_positions: [0; N],
+            toggle_samples: toggle_samples(sample_rate_hz),
+            sample_rate_hz,
+        }
+    }
+
+    pub fn band(&self, index: usize) -> EqBand<T> {
+        self.bands[index]
+    }
+
+    /// Changes one band. The states of the other bands are kept. A band that gets enabled
+    /// fades in from a cleared state rather than whatever it held when it was disabled, and a
+    /// band that gets disabled keeps running until it has faded out. Toggling again during a
+    /// fade turns it around from where it is.
+    pub fn set_band(&mut self, index: usize, band: EqBand<T>) {
+        if band.enabled && self.fade_positions[index] == 0 {
+            self.filters[index].reset();
+        }
+        self.bands[index] = band;
+        self.filters[index].update(band.coefficients(self.sample_rate_hz));
+        if self.toggle_samples == 0 {
+            self.fade_positions[index] = self.fade_target(index);
+        }
+    }
+
+    /// Sets the length of the fade when a band is switched on or off, 0 switches immediately.
+    /// Fades in progress are finished.
+    pub fn set_toggle_samples(&mut self, toggle_samples: usize) {
+        self.toggle_samples = toggle_samples;
+        self.finish_fades();
+    }
+
+    pub fn toggle_samples(&self) -> usize {
+        self.toggle_samples
+    }
+
+    /// Redesigns every band for the new rate and resets the fade length to
+    /// `TOGGLE_FADE_SECONDS`
+    pub fn set_sample_rate(&mut self, sample_rate_hz: T) {
+        self.sample_rate_hz = sample_rate_hz;
+        for (band, filter) in self.bands.iter().zip(self.filters.iter_mut()) {
+            filter.update(band.coefficients(sample_rate_hz));
+        }
+        self.set_toggle_samples(toggle_samples(sample_rate_hz));
+    }
+
+    /// Whether a band is fading in or out
+    #[inline]
+    pub fn is_fading(&self, index: usize) -> bool {
+        self.fade_positions[index] != self.fade_target(index)
+    }
+
+    #[inline]
+    fn fade_target(&self, index: usize) -> usize {
+        if self.bands[index].enabled {
+            self.toggle_samples
+        } else {
+            0
+        }
+    }
+
+    fn finish_fades(&mut self) {
+        for index in 0..N {
+            self.fade_positions[index] = self.fade_target(index);
+        }
+    }
+
+    /// Runs one sample through a fading band, moving the fade one step along
+    #[inline]
+    fn process_fading(&mut self, index: usize, input_sample: T) -> T {
+        let filtered = self.filters[index].process(input_sample);
+        if self.bands[index].enabled {
+            self.fade_positions[index] += 1;
+        } else {
+            self.fade_positions[index] -= 1;
+        }
+        let mix = cast::<T>(self.fade_positions[index] as f64 / self.toggle_samples as f64);
+        input_sample + (filtered - input_sample) * mix
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let mut sample = input_sample;
+        for index in 0..N {
+            if self.is_fading(index) {
+                sample = self.process_fading(index, sample);
+            } else if self.bands[index].enabled {
+                sample = self.filters[index].process(sample);
+            }
+        }
+        sample
+    }
+
+    /// Processes the buffer in place, one band at a time. Output is identical to calling
+    /// `process` on each sample.
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for index in 0..N {
+            let fading = self.fade_positions[index].abs_diff(self.fade_target(index));
+            let (fade, rest) = buffer.split_at_mut(fading.min(buffer.len()));
+            for sample in fade.iter_mut() {
+                *sample = self.process_fading(index, *sample);
+            }
+            if self.bands[index].enabled {
+                self.filters[index].process_block(rest);
+            }
+        }
+    }
+
+    /// Clears the internal state of every band and finishes fades, keeping the settings
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.reset();
+        }
+        self.finish_fades();
+    }
+
+    /// Combined response of the enabled bands
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.bands
+            .iter()
+            .zip(self.filters.iter())
+            .filter(|(band, _)| band.enabled)
+            .fold(Complex::new(T::one(), T::zero()), |acc, (_, filter)| {
+                acc * filter.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            })
+    }
+
+    /// Samples the combined response at num_points logarithmically spaced frequencies from
+    /// start_hz to end_hz, for drawing the EQ curve
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Vec<BodePoint<T>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| BodePoint::from_complex(f, self.get_bode_sample(f, sample_rate_hz)))
+            .collect()
+    }
+}
+
+fn toggle_samples<T: Float>(sample_rate_hz: T) -> usize {
+    (sample_rate_hz * cast(TOGGLE_FADE_SECONDS))
+        .round()
+        .to_usize()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    fn band(filter_type: IIR2FilterType, frequency_hz: f32, gain_db: f32) -> EqBand {
+        EqBand {
+            filter_type,
+            frequency_hz,
+            gain_db,
+            q_value: 1.0,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_parametric_eq_response() {
+        let sample_rate_hz = 48000.0;
+        let mut eq = ParametricEq::<4>::new(sample_rate_hz);
+        assert_eq!(eq.process(0.25), 0.25);
+
+        let bands = [
+            band(IIR2FilterType::HighPass, 40.0, 0.0),
+            band(IIR2FilterType::Bell, 1000.0, -6.0),
+            band(IIR2FilterType::HighShelf, 8000.0, 3.0),
+        ];
+        for (i, b) in bands.iter().enumerate() {
+            eq.set_band(i, *b);
+        }
+        for point in eq.frequency_response(20.0, 20000.0, 50, sample_rate_hz) {
+            let product = bands.iter().fold(Complex::new(1.0, 0.0), |acc, b| {
+                acc * b
+                    .coefficients(sample_rate_hz)
+                    .get_bode_sample(point.frequency_hz, sample_rate_hz)
+            });
+            assert!((point.magnitude_db - 20.0 * product.norm().log10()).abs() < 1e-3);
+        }
+    }
+
+    /// Largest step between samples of a 500 Hz sine through the EQ, with band 1 toggled at the
+    /// given sample indices
+    fn max_step_toggling(eq: &mut ParametricEq<3>, toggles: &[usize]) -> f32 {
+        let mut last = 0.0;
+        let mut max_step = 0.0f32;
+        for i in 0..48000 {
+            if toggles.contains(&i) {
+                let mut b = eq.band(1);
+                b.enabled = !b.enabled;
+                eq.set_band(1, b);
+            }
+            let x = (i as f32 * core::f32::consts::TAU * 500.0 / 48000.0).sin() * 0.5
+                + (rand(i as f32) - 0.5) * 0.001;
+            let output = eq.process(x);
+            assert!(output.is_finite());
+            if i > 0 {
+                max_step = max_step.max((output - last).abs());
+            }
+            last = output;
+        }
+        max_step
+    }
+
+    #[test]
+    fn test_parametric_eq_toggle_bands() {
+        let mut eq = ParametricEq::<3>::new(48000.0);
+        eq.set_band(0, band(IIR2FilterType::LowPass, 5000.0, 0.0));
+        eq.set_band(1, band(IIR2FilterType::Bell, 500.0, 6.0));
+        assert_eq!(eq.toggle_samples(), 480);
+
+        // Toggles at the band's center frequency, some of them halfway through a fade. The
+        // boosted sine itself moves by up to 0.5 * 2 * 2 pi 500 / 48000 = 0.065 per sample.
+        let toggles = [
+            4812, 9600, 14400, 14600, 19200, 19250, 19300, 28800, 38400, 38500,
+        ];
+        let mut toggled = eq;
+        let max_step = max_step_toggling(&mut toggled, &toggles);
+        assert!(max_step < 0.068);
+        assert!(!toggled.is_fading(1));
+
+        // Switching instantly jumps by about the difference between the dry and boosted sine
+        let mut hard = eq;
+        hard.set_toggle_samples(0);
+        assert!(max_step_toggling(&mut hard, &toggles) > 0.2);
+
+        // Block processing matches sample by sample processing through a fade
+        let mut a = eq;
+        let mut b = eq;
+        let mut disabled = eq.band(1);
+        disabled.enabled = false;
+        a.set_band(1, disabled);
+        b.set_band(1, disabled);
+        let mut buffer: Vec<f32> = (0..1000).map(|i| rand(i as f32) - 0.5).collect();
+        let expected: Vec<f32> = buffer.iter().map(|&x| a.process(x)).collect();
+        for chunk in buffer.chunks_mut(300) {
+            b.process_block(chunk);
+        }
+        assert_eq!(buffer, expected);
+
+        // Changing one band keeps the state of the others
+        let mut eq = toggled;
+        let mut before = eq;
+        eq.set_band(1, band(IIR2FilterType::Bell, 2000.0, -3.0));
+        assert_eq!(eq.filters[0].process(0.0), before.filters[0].process(0.0));
+        assert!(eq.filters[0].process(0.0) != 0.0);
+    }
+}
@@ -0,0 +1,222 @@
+use num_traits::Float;
+
+use crate::cast;
+use crate::first_order_iir::{IIR1Coefficients, IIR1};
+
+pub const MIN_PHASER_STAGES: usize = 4;
+pub const MAX_PHASER_STAGES: usize = 12;
+
+/// Largest feedback magnitude `set_feedback` accepts. The all passes have unity gain, so the loop
+/// is stable for any feedback below 1, but close to it the notches turn into long ringing peaks.
+pub const MAX_PHASER_FEEDBACK: f32 = 0.95;
+
+/// Chain of first order all passes mixed with the dry signal, the core of a phaser.
+///
+/// Each stage shifts the phase by up to -180 degrees, and wherever the chain is at an odd
+/// multiple of -180 the wet signal cancels the dry one, so n stages give n / 2 notches. The
+/// stages sit at `allpass_phase90` frequencies spread evenly in octaves around the center, and
+/// the lfo value moves the center across the sweep range. The sections are in the SVF form,
+/// whose state stays valid for new coefficients, so they can be updated every sample.
+#[derive(Copy, Clone, Debug)]
+pub struct PhaserAllpassChain<T = f32> {
+    stages: [IIR1<T>; MAX_PHASER_STAGES],
+    num_stages: usize,
+    center_hz: T,
+    spread_octaves: T,
+    sweep_octaves: T,
+    feedback: T,
+    sample_rate_hz: T,
+    last_wet: T,
+    last_lfo: Option<T>,
+}
+
+impl<T: Float> PhaserAllpassChain<T> {
+    /// Creates a phaser with num_stages all passes, limited to `MIN_PHASER_STAGES` to
+    /// `MAX_PHASER_STAGES`. It starts centered at 1 kHz with the stages spread over two octaves,
+    /// a sweep range of four octaves and no feedback.
+    pub fn new(num_stages: usize, sample_rate_hz: T) -> Self {
+        let bypass = IIR1::from(IIR1Coefficients::bypass());
+        PhaserAllpassChain {
+            stages: [bypass; MAX_PHASER_STAGES],
+            num_stages: num_stages.clamp(MIN_PHASER_STAGES, MAX_PHASER_STAGES),
+            center_hz: cast(1000.0),
+            spread_octaves: cast(2.0),
+            sweep_octaves: cast(4.0),
+            feedback: T::zero(),
+            sample_rate_hz,
+            last_wet: T::zero(),
+            last_lfo: None,
+        }
+    }
+
+    pub fn num_stages(&self) -> usize {
+        self.num_stages
+    }
+
+    /// Center of the sweep, reached at an lfo value of 0.5
+    pub fn set_center_frequency(&mut self, center_hz: T) {
+        self.center_hz = center_hz;
+        self.last_lfo = None;
+    }
+
+    /// Octaves between the lowest and highest stage
+    pub fn set_spread(&mut self, spread_octaves: T) {
+        self.spread_octaves = spread_octaves.max(T::zero());
+        self.last_lfo = None;
+    }
+
+    /// Octaves the center moves between lfo values of 0 and 1
+    pub fn set_sweep_range(&mut self, sweep_octaves: T) {
+        self.sweep_octaves = sweep_octaves.max(T::zero());
+        self.last_lfo = None;
+    }
+
+    /// Amount of the wet signal fed back into the chain, clamped to ±`MAX_PHASER_FEEDBACK`.
+    /// Positive values deepen and sharpen the notches, negative values move peaks between them.
+    pub fn set_feedback(&mut self, feedback: T) {
+        let limit = cast::<T>(MAX_PHASER_FEEDBACK as f64);
+        self.feedback = if feedback.is_nan() {
+            T::zero()
+        } else {
+            feedback.max(-limit).min(limit)
+        };
+    }
+
+    pub fn feedback(&self) -> T {
+        self.feedback
+    }
+
+    /// Frequency of the -90 degree point of a stage for an lfo value
+    pub fn stage_frequency(&self, stage: usize, lfo_value: T) -> T {
+        let half = cast::<T>(0.5);
+        let lfo_value = lfo_value.max(T::zero()).min(T::one());
+        let position = if self.num_stages > 1 {
+            cast::<T>(stage as f64 / (self.num_stages - 1) as f64) - half
+        } else {
+            T::zero()
+        };
+        let octaves = self.sweep_octaves * (lfo_value - half) + self.spread_octaves * position;
+        self.center_hz * octaves.exp2()
+    }
+
+    fn update_stages(&mut self, lfo_value: T) {
+        if self.last_lfo == Some(lfo_value) {
+            return;
+        }
+        for stage in 0..self.num_stages {
+            let frequency_hz = self.stage_frequency(stage, lfo_value);
+            self.stages[stage].update(IIR1Coefficients::allpass_phase90(
+                frequency_hz,
+                self.sample_rate_hz,
+            ));
+        }
+        self.last_lfo = Some(lfo_value);
+    }
+
+    /// Processes one sample with the center swept by lfo_value in 0..1. The stages are only
+    /// redesigned when the lfo value changes.
+    #[inline]
+    pub fn process(&mut self, input_sample: T, lfo_value: T) -> T {
+        self.update_stages(lfo_value);
+        let mut wet = input_sample + self.feedback * self.last_wet;
+        for stage in self.stages[..self.num_stages].iter_mut() {
+            wet = stage.process(wet);
+        }
+        self.last_wet = wet;
+        (input_sample + wet) * cast(0.5)
+    }
+
+    /// Clears the state of every stage and the feedback path
+    pub fn reset(&mut self) {
+        for stage in self.stages.iter_mut() {
+            stage.reset();
+        }
+        self.last_wet = T::zero();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn impulse_response(phaser: &mut PhaserAllpassChain<f64>, lfo_value: f64) -> Vec<f64> {
+        phaser.reset();
+        (0..16384)
+            .map(|i| phaser.process(if i == 0 { 1.0 } else { 0.0 }, lfo_value))
+            .collect()
+    }
+
+    fn dft_db(impulse_response: &[f64], frequency_hz: f64) -> f64 {
+        let (re, im) = impulse_response
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (n, &h)| {
+                let phase = TAU * frequency_hz * n as f64 / SAMPLE_RATE;
+                (re + h * phase.cos(), im - h * phase.sin())
+            });
+        10.0 * (re * re + im * im).log10()
+    }
+
+    /// Frequencies of the local minima of the measured magnitude on a log grid
+    fn notches(impulse_response: &[f64]) -> Vec<(f64, f64)> {
+        let grid: Vec<f64> = (0..300)
+            .map(|i| 20.0 * 1000.0f64.powf(i as f64 / 299.0))
+            .collect();
+        let levels: Vec<f64> = grid.iter().map(|&f| dft_db(impulse_response, f)).collect();
+        (1..grid.len() - 1)
+            .filter(|&i| levels[i] < levels[i - 1] && levels[i] < levels[i + 1])
+            .map(|i| (grid[i], levels[i]))
+            .collect()
+    }
+
+    #[test]
+    fn test_phaser_notches_move() {
+        let mut phaser = PhaserAllpassChain::<f64>::new(6, SAMPLE_RATE);
+        let low = notches(&impulse_response(&mut phaser, 0.0));
+        let high = notches(&impulse_response(&mut phaser, 1.0));
+
+        // Six stages make three notches, all deep, and sweeping up moves every one of them up
+        assert_eq!(low.len(), 3);
+        assert_eq!(high.len(), 3);
+        for ((f_low, db_low), (f_high, db_high)) in low.iter().zip(&high) {
+            assert!(*db_low < -20.0 && *db_high < -20.0);
+            assert!(f_high / f_low > 8.0);
+        }
+        // Away from the notches the dry and wet signals add up
+        assert!(dft_db(&impulse_response(&mut phaser, 0.5), 20.0) > -0.1);
+    }
+
+    #[test]
+    fn test_phaser_stages_and_feedback() {
+        assert_eq!(PhaserAllpassChain::<f32>::new(2, 48000.0).num_stages(), 4);
+        assert_eq!(PhaserAllpassChain::<f32>::new(16, 48000.0).num_stages(), 12);
+
+        let mut phaser = PhaserAllpassChain::<f64>::new(12, SAMPLE_RATE);
+        phaser.set_feedback(5.0);
+        assert_eq!(phaser.feedback(), MAX_PHASER_FEEDBACK as f64);
+        phaser.set_feedback(f64::NAN);
+        assert_eq!(phaser.feedback(), 0.0);
+
+        // Even at the feedback limit the impulse response dies out
+        phaser.set_feedback(-1.0);
+        let response = impulse_response(&mut phaser, 0.3);
+        let peak_after =
+            |start: usize| response[start..].iter().fold(0.0f64, |m, x| m.max(x.abs()));
+        assert!(peak_after(12000) < peak_after(0) / 100.0);
+
+        // Sweeping every sample stays bounded
+        phaser.set_feedback(0.9);
+        phaser.reset();
+        let mut peak = 0.0f64;
+        for i in 0..48000 {
+            let lfo = 0.5 + 0.5 * (TAU * 5.0 * i as f64 / SAMPLE_RATE).sin();
+            let x = (TAU * 440.0 * i as f64 / SAMPLE_RATE).sin();
+            peak = peak.max(phaser.process(x, lfo).abs());
+        }
+        assert!(peak.is_finite() && peak < 20.0);
+    }
+}
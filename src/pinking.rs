@@ -0,0 +1,104 @@
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::first_order_iir::{IIR1Coefficients, IIR1};
+use crate::{cast, sanitize_cutoff};
+
+/// Lowest tilt center, low enough for the slope to hold from 20 Hz
+const FIRST_CENTER_HZ: f64 = 5.0;
+/// Spacing of the tilt centers, each tilt covers log2(3) octaves of the slope
+const CENTER_RATIO: f64 = 3.0;
+
+/// Approximates a -3 dB/octave slope within 0.3 dB per octave from 20 Hz to 20 kHz with first
+/// order tilts spaced every log2(3) octaves, normalized to 0 dB at 1 kHz. Used to turn white noise
+/// into pink noise or as a tilt reference for analyzers.
+#[derive(Clone, Debug)]
+pub struct PinkingFilter<T = f32> {
+    sections: Vec<IIR1<T>>,
+}
+
+impl<T: Float> PinkingFilter<T> {
+    /// Designs the tilts for sample_rate_hz, `None` if the rate is not usable or too low for even
+    /// the first tilt at 5 Hz to fit
+    pub fn new(sample_rate_hz: T) -> Option<Self> {
+        sanitize_cutoff(T::zero(), sample_rate_hz)?;
+        let gain_db = cast::<T>(-3.0 * CENTER_RATIO.log2());
+        // Tilts too close to Nyquist get squashed by the prewarping, the ones below already
+        // carry the slope up to 20 kHz there
+        let max_center_hz = sample_rate_hz.to_f64().unwrap() * 0.45;
+        let sections: Vec<_> = (0..)
+            .map(|i| FIRST_CENTER_HZ * CENTER_RATIO.powi(i))
+            .take_while(|&center_hz| center_hz < max_center_hz)
+            .map(|center_hz| {
+                IIR1::from(IIR1Coefficients::tilt(
+                    cast(center_hz),
+                    gain_db,
+                    sample_rate_hz,
+                ))
+            })
+            .collect();
+        if sections.is_empty() {
+            return None;
+        }
+        let mut filter = PinkingFilter { sections };
+        let gain = T::one() / filter.get_bode_sample(cast(1000.0), sample_rate_hz).norm();
+        let coeffs = &mut filter.sections[0].coeffs;
+        coeffs.m0 = coeffs.m0 * gain;
+        coeffs.m1 = coeffs.m1 * gain;
+        Some(filter)
+    }
+
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.sections
+            .iter()
+            .fold(Complex::new(T::one(), T::zero()), |acc, section| {
+                acc * section.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            })
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        self.sections
+            .iter_mut()
+            .fold(input_sample, |x, section| section.process(x))
+    }
+
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinking_filter() {
+        for &sample_rate_hz in &[44100.0, 48000.0, 96000.0] {
+            let filter = PinkingFilter::<f64>::new(sample_rate_hz).unwrap();
+            let db = |f: f64| 20.0 * filter.get_bode_sample(f, sample_rate_hz).norm().log10();
+            assert!(db(1000.0).abs() < 1e-9);
+            for i in 0..10 {
+                let f = 20.0 * 2.0f64.powi(i);
+                assert!((db(f) - db(f * 2.0) - 3.0).abs() < 0.3);
+            }
+        }
+
+        let mut filter = PinkingFilter::<f32>::new(48000.0).unwrap();
+        for i in 0..48000 {
+            let x = ((i as f32 * 12.9898).sin() * 43_758.547).fract();
+            assert!(filter.process(x).is_finite());
+        }
+        filter.reset();
+        assert_eq!(filter.process(0.0), 0.0);
+
+        for &sample_rate_hz in &[0.0, -48000.0, 10.0, f64::NAN, f64::INFINITY] {
+            assert!(PinkingFilter::new(sample_rate_hz).is_none());
+        }
+        assert!(PinkingFilter::new(12.0).is_some());
+    }
+}
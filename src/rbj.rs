@@ -0,0 +1,302 @@
+//! Constructors following the conventions of Robert Bristow-Johnson's Audio EQ Cookbook.
+//!
+//! The cookbook biquads are mapped onto the SVF form, so the results run on `IIR2` like any other
+//! coefficient set. Designs that have no stable realization return `None`, e.g. for a
+//! non-positive Q or slope. The cookbook math runs in the sample type, and in f32 the poles of
+//! cutoffs within a few Hz of DC at high sample rates round onto or past the unit circle, which
+//! also returns `None`.
+
+use core::f64::consts::TAU;
+
+use num_traits::Float;
+
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, clamp_cutoff};
+
+struct Prototype<T> {
+    cos_w0: T,
+    sin_w0: T,
+}
+
+impl<T: Float> Prototype<T> {
+    fn new(f0: T, fs: T) -> Self {
+        let w0 = cast::<T>(TAU) * clamp_cutoff(f0, fs) / fs;
+        Prototype {
+            cos_w0: w0.cos(),
+            sin_w0: w0.sin(),
+        }
+    }
+
+    fn alpha(&self, q: T) -> T {
+        self.sin_w0 / (cast::<T>(2.0) * q)
+    }
+}
+
+fn from_cookbook<T: Float>(
+    b0: T,
+    b1: T,
+    b2: T,
+    a0: T,
+    a1: T,
+    a2: T,
+) -> Option<IIR2Coefficients<T>> {
+    IIR2Coefficients::from_biquad(b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0)
+}
+
+pub fn lowpass<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    let b1 = T::one() - p.cos_w0;
+    let b0 = b1 / cast(2.0);
+    from_cookbook(
+        b0,
+        b1,
+        b0,
+        T::one() + alpha,
+        -cast::<T>(2.0) * p.cos_w0,
+        T::one() - alpha,
+    )
+}
+
+pub fn highpass<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    let b0 = (T::one() + p.cos_w0) / cast(2.0);
+    from_cookbook(
+        b0,
+        -(T::one() + p.cos_w0),
+        b0,
+        T::one() + alpha,
+        -cast::<T>(2.0) * p.cos_w0,
+        T::one() - alpha,
+    )
+}
+
+/// Band pass with a constant 0 dB peak gain
+pub fn bandpass<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    from_cookbook(
+        alpha,
+        T::zero(),
+        -alpha,
+        T::one() + alpha,
+        -cast::<T>(2.0) * p.cos_w0,
+        T::one() - alpha,
+    )
+}
+
+/// Band pass with a constant skirt gain, the peak gain is Q
+pub fn bandpass_constant_skirt<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    from_cookbook(
+        q * alpha,
+        T::zero(),
+        -q * alpha,
+        T::one() + alpha,
+        -cast::<T>(2.0) * p.cos_w0,
+        T::one() - alpha,
+    )
+}
+
+pub fn notch<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    let b1 = -cast::<T>(2.0) * p.cos_w0;
+    from_cookbook(
+        T::one(),
+        b1,
+        T::one(),
+        T::one() + alpha,
+        b1,
+        T::one() - alpha,
+    )
+}
+
+pub fn allpass<T: Float>(f0: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    let b1 = -cast::<T>(2.0) * p.cos_w0;
+    from_cookbook(
+        T::one() - alpha,
+        b1,
+        T::one() + alpha,
+        T::one() + alpha,
+        b1,
+        T::one() - alpha,
+    )
+}
+
+pub fn peaking_eq<T: Float>(f0: T, gain_db: T, q: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let alpha = p.alpha(q);
+    let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+    let b1 = -cast::<T>(2.0) * p.cos_w0;
+    from_cookbook(
+        T::one() + alpha * a,
+        b1,
+        T::one() - alpha * a,
+        T::one() + alpha / a,
+        b1,
+        T::one() - alpha / a,
+    )
+}
+
+/// Converts a bandwidth in octaves (between the -3 dB points for band pass and notch, or the
+/// midpoint gain points for a peaking EQ) into the equivalent cookbook Q
+pub fn q_from_bandwidth<T: Float>(f0: T, bw_octaves: T, fs: T) -> T {
+    let w0 = cast::<T>(TAU) * clamp_cutoff(f0, fs) / fs;
+    let x = cast::<T>(core::f64::consts::LN_2 / 2.0) * bw_octaves * w0 / w0.sin();
+    T::one() / (cast::<T>(2.0) * x.sinh())
+}
+
+/// alpha of the shelves for a slope S, where S = 1 is the steepest slope without overshoot
+fn shelf_alpha<T: Float>(p: &Prototype<T>, a: T, slope: T) -> T {
+    let two = cast::<T>(2.0);
+    p.sin_w0 / two * ((a + T::one() / a) * (T::one() / slope - T::one()) + two).sqrt()
+}
+
+pub fn low_shelf<T: Float>(f0: T, gain_db: T, slope: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+    let two = cast::<T>(2.0);
+    let two_sqrt_a_alpha = two * a.sqrt() * shelf_alpha(&p, a, slope);
+    let (ap1, am1) = (a + T::one(), a - T::one());
+    from_cookbook(
+        a * (ap1 - am1 * p.cos_w0 + two_sqrt_a_alpha),
+        two * a * (am1 - ap1 * p.cos_w0),
+        a * (ap1 - am1 * p.cos_w0 - two_sqrt_a_alpha),
+        ap1 + am1 * p.cos_w0 + two_sqrt_a_alpha,
+        -two * (am1 + ap1 * p.cos_w0),
+        ap1 + am1 * p.cos_w0 - two_sqrt_a_alpha,
+    )
+}
+
+pub fn high_shelf<T: Float>(f0: T, gain_db: T, slope: T, fs: T) -> Option<IIR2Coefficients<T>> {
+    let p = Prototype::new(f0, fs);
+    let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+    let two = cast::<T>(2.0);
+    let two_sqrt_a_alpha = two * a.sqrt() * shelf_alpha(&p, a, slope);
+    let (ap1, am1) = (a + T::one(), a - T::one());
+    from_cookbook(
+        a * (ap1 + am1 * p.cos_w0 + two_sqrt_a_alpha),
+        -two * a * (am1 + ap1 * p.cos_w0),
+        a * (ap1 + am1 * p.cos_w0 - two_sqrt_a_alpha),
+        ap1 - am1 * p.cos_w0 + two_sqrt_a_alpha,
+        two * (am1 - ap1 * p.cos_w0),
+        ap1 - am1 * p.cos_w0 - two_sqrt_a_alpha,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+
+    const FS: f64 = 48000.0;
+    const F0: f64 = 1000.0;
+
+    /// Evaluates an analog cookbook prototype through the prewarped bilinear transform
+    fn analog(frequency_hz: f64, prototype: impl Fn(Complex<f64>) -> Complex<f64>) -> Complex<f64> {
+        let warp = |f: f64| (core::f64::consts::PI * f / FS).tan();
+        prototype(Complex::new(0.0, warp(frequency_hz) / warp(F0)))
+    }
+
+    fn check(
+        coeffs: Option<IIR2Coefficients<f64>>,
+        prototype: impl Fn(Complex<f64>) -> Complex<f64>,
+    ) {
+        let coeffs = coeffs.unwrap();
+        for i in 0..12 {
+            let f = 20.0 * 1.7f64.powi(i);
+            let expected = analog(f, &prototype);
+            let actual = coeffs.get_bode_sample(f, FS);
+            assert!((expected - actual).norm() < 1e-6 * expected.norm().max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_rbj_pass_filters() {
+        let q = 2.0;
+        check(lowpass(F0, q, FS), |s| 1.0 / (s * s + s / q + 1.0));
+        check(highpass(F0, q, FS), |s| s * s / (s * s + s / q + 1.0));
+        check(bandpass(F0, q, FS), |s| (s / q) / (s * s + s / q + 1.0));
+        check(bandpass_constant_skirt(F0, q, FS), |s| {
+            s / (s * s + s / q + 1.0)
+        });
+        check(notch(F0, q, FS), |s| (s * s + 1.0) / (s * s + s / q + 1.0));
+        check(allpass(F0, q, FS), |s| {
+            (s * s - s / q + 1.0) / (s * s + s / q + 1.0)
+        });
+    }
+
+    #[test]
+    fn test_rbj_q_from_bandwidth() {
+        // The -3 dB points of a band pass designed from a bandwidth sit that many octaves apart, the
+        // cookbook prewarping of the bandwidth is close but not exact
+        let bw = 1.5;
+        let coeffs = bandpass(F0, q_from_bandwidth(F0, bw, FS), FS).unwrap();
+        let mut edges = [0.0f64; 2];
+        for (edge, sign) in edges.iter_mut().zip([-1.0, 1.0]) {
+            let (mut lo, mut hi) = if sign < 0.0 {
+                (1.0, F0)
+            } else {
+                (F0, FS / 2.0 - 1.0)
+            };
+            for _ in 0..100 {
+                let mid = (lo + hi) / 2.0;
+                let above = coeffs.get_bode_sample(mid, FS).norm() > 0.5f64.sqrt();
+                if above == (sign < 0.0) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            *edge = lo;
+        }
+        assert!(((edges[1] / edges[0]).log2() - bw).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rbj_eq_filters() {
+        for &gain_db in &[-12.0, 6.0] {
+            let a = 10.0f64.powf(gain_db / 40.0);
+            let q = 0.7;
+            check(peaking_eq(F0, gain_db, q, FS), |s| {
+                (s * s + s * (a / q) + 1.0) / (s * s + s / (a * q) + 1.0)
+            });
+
+            let slope = 0.8;
+            let inv_q = ((a + 1.0 / a) * (1.0 / slope - 1.0) + 2.0).sqrt();
+            check(low_shelf(F0, gain_db, slope, FS), |s| {
+                (s * s + s * (a.sqrt() * inv_q) + a) * a
+                    / (s * s * a + s * (a.sqrt() * inv_q) + 1.0)
+            });
+            check(high_shelf(F0, gain_db, slope, FS), |s| {
+                (s * s * a + s * (a.sqrt() * inv_q) + 1.0) * a
+                    / (s * s + s * (a.sqrt() * inv_q) + a)
+            });
+        }
+    }
+
+    #[test]
+    fn test_rbj_f32() {
+        // Away from DC f32 follows the f64 design
+        for f0 in [100.0, 1000.0, 10000.0] {
+            let single = peaking_eq(f0, 6.0, 2.0, 48000.0f32).unwrap();
+            let double = peaking_eq(f0 as f64, 6.0, 2.0, 48000.0).unwrap();
+            for f in [50.0, 500.0, 5000.0, 15000.0] {
+                let difference = single.get_bode_sample(f, 48000.0f32).norm() as f64
+                    / double.get_bode_sample(f as f64, 48000.0).norm();
+                assert!((20.0 * difference.log10()).abs() < 0.01);
+            }
+        }
+
+        // A 5 Hz low pass at 192 kHz has no stable f32 cookbook biquad, rather than a flat one
+        assert!(lowpass(5.0f32, 0.7, 192000.0).is_none());
+        assert!(lowpass(5.0f64, 0.7, 192000.0).is_some());
+        assert!(lowpass(F0, -1.0, FS).is_none());
+        assert!(low_shelf(F0, 6.0, 0.0, FS).is_none());
+    }
+}
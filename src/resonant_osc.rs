@@ -0,0 +1,210 @@
+use core::f64::consts::PI;
+
+use num_traits::Float;
+
+use crate::{cast, clamp_cutoff, flush_denormal};
+
+/// Highest resonance `ResonantOscFilter` accepts, a damping of -1. Beyond -2 the SVF equations
+/// have no solution at some cutoffs.
+pub const MAX_RESONANCE: f32 = 1.5;
+
+/// State the filter starts from, standing in for the noise that starts an analog oscillator.
+/// Below self-oscillation it dies away about 100 dB down.
+const SEED: f64 = 1e-5;
+
+/// Resonant low pass that turns into a sine oscillator at the cutoff frequency once resonance
+/// passes 1, like an analog VCF with the resonance turned all the way up.
+///
+/// The resonance sets the damping of a SVF, k = 2 (1 - resonance), so 0 is a Q of 0.5, 1 is the
+/// lossless filter and higher values are negative damping that makes the filter ring up on its
+/// own. A soft limiter in the feedback adds `|k| amplitude / level` of damping, with the
+/// amplitude taken from the band pass and low pass outputs, which form a quadrature pair of equal
+/// amplitude at the cutoff. The oscillation settles where the two cancel, at the set level with
+/// a damping of exactly 0, and a lossless trapezoidal SVF oscillates exactly at its prewarped
+/// cutoff, so the pitch tracks the cutoff to within a fraction of a cent. Below self-oscillation
+/// the same limiter tames the resonance for loud inputs.
+#[derive(Copy, Clone, Debug)]
+pub struct ResonantOscFilter<T = f32> {
+    ic1eq: T,
+    ic2eq: T,
+    energy: T,
+    g: T,
+    k: T,
+    cutoff_hz: T,
+    resonance: T,
+    level: T,
+    sample_rate_hz: T,
+}
+
+impl<T: Float> ResonantOscFilter<T> {
+    /// Creates the filter with an oscillation level of 0.5, see `set_resonance` for the range
+    pub fn new(cutoff_hz: T, resonance: T, sample_rate_hz: T) -> Self {
+        let mut filter = ResonantOscFilter {
+            ic1eq: cast(SEED),
+            ic2eq: T::zero(),
+            energy: T::zero(),
+            g: T::zero(),
+            k: T::zero(),
+            cutoff_hz,
+            resonance: T::zero(),
+            level: cast(0.5),
+            sample_rate_hz,
+        };
+        filter.set_cutoff(cutoff_hz);
+        filter.set_resonance(resonance);
+        filter
+    }
+
+    /// Moves the cutoff, and with it the pitch of the oscillation, keeping the state
+    pub fn set_cutoff(&mut self, cutoff_hz: T) {
+        self.cutoff_hz = clamp_cutoff(cutoff_hz, self.sample_rate_hz);
+        self.g = (cast::<T>(PI) * self.cutoff_hz / self.sample_rate_hz).tan();
+    }
+
+    pub fn cutoff_hz(&self) -> T {
+        self.cutoff_hz
+    }
+
+    /// Resonance from 0 to `MAX_RESONANCE`, self-oscillating above 1. NaN counts as 0.
+    pub fn set_resonance(&mut self, resonance: T) {
+        let resonance = if resonance.is_nan() {
+            T::zero()
+        } else {
+            resonance
+        };
+        self.resonance = resonance.max(T::zero()).min(cast(MAX_RESONANCE as f64));
+        self.k = cast::<T>(2.0) * (T::one() - self.resonance);
+    }
+
+    pub fn resonance(&self) -> T {
+        self.resonance
+    }
+
+    /// Amplitude the oscillation settles at, and where the limiter starts to act below
+    /// self-oscillation. Values that are not positive are ignored.
+    pub fn set_level(&mut self, level: T) {
+        if level > T::zero() {
+            self.level = level;
+        }
+    }
+
+    pub fn level(&self) -> T {
+        self.level
+    }
+
+    /// Processes one sample, returning the low pass output
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let excess = self.energy.sqrt() / self.level;
+        let k = self.k + self.k.abs() * excess;
+        let a1 = T::one() / (T::one() + self.g * (self.g + k));
+        let a2 = self.g * a1;
+        let a3 = self.g * a2;
+
+        let v3 = input_sample - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = flush_denormal(cast::<T>(2.0) * v1 - self.ic1eq);
+        self.ic2eq = flush_denormal(cast::<T>(2.0) * v2 - self.ic2eq);
+        self.energy = v1 * v1 + v2 * v2;
+        v2
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the state back to the small seed that starts the oscillation
+    pub fn reset(&mut self) {
+        self.ic1eq = cast(SEED);
+        self.ic2eq = T::zero();
+        self.energy = T::zero();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+    /// Frequency from the first and last rising zero crossings, interpolated between samples
+    fn zero_crossing_frequency(signal: &[f64], sample_rate_hz: f64) -> f64 {
+        let crossings: Vec<f64> = signal
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+            .map(|(n, pair)| n as f64 + pair[0] / (pair[0] - pair[1]))
+            .collect();
+        let periods = (crossings.len() - 1) as f64;
+        periods * sample_rate_hz / (crossings[crossings.len() - 1] - crossings[0])
+    }
+
+    fn rms(signal: &[f64]) -> f64 {
+        (signal.iter().map(|x| x * x).sum::<f64>() / signal.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_resonant_osc_pitch() {
+        let sample_rate_hz = 48000.0;
+        for cutoff_hz in [50.0, 110.0, 440.0, 1000.0, 3000.0, 6000.0, 10000.0] {
+            for resonance in [1.1, 1.5] {
+                let mut filter =
+                    ResonantOscFilter::<f64>::new(cutoff_hz, resonance, sample_rate_hz);
+                let mut output = vec![0.0; 96000];
+                filter.process_block(&mut output);
+                let tail = &output[48000..];
+
+                let frequency = zero_crossing_frequency(tail, sample_rate_hz);
+                let cents = 1200.0 * (frequency / cutoff_hz).log2();
+                let amplitude = rms(tail) * 2.0f64.sqrt();
+                assert!(cents.abs() < 1.0);
+                assert!((amplitude - 0.5).abs() < 0.005);
+            }
+        }
+
+        // Following a cutoff change
+        let mut filter = ResonantOscFilter::<f32>::new(200.0, 1.2, sample_rate_hz as f32);
+        let mut output = vec![0.0; 48000];
+        filter.process_block(&mut output);
+        filter.set_cutoff(2000.0);
+        filter.set_level(0.25);
+        for _ in 0..2 {
+            output.fill(0.0);
+            filter.process_block(&mut output);
+        }
+        let output: Vec<f64> = output.iter().map(|&x| x as f64).collect();
+        let cents = 1200.0 * (zero_crossing_frequency(&output, sample_rate_hz) / 2000.0).log2();
+        assert!(cents.abs() < 3.0);
+        assert!((rms(&output) * 2.0f64.sqrt() - 0.25).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_resonant_osc_below_oscillation() {
+        // Quiet signals see the plain SVF low pass with Q = 1 / k
+        let sample_rate_hz = 48000.0;
+        let mut filter = ResonantOscFilter::<f64>::new(1000.0, 0.5, sample_rate_hz);
+        let mut linear = IIR2::from(IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, sample_rate_hz));
+        filter.process(0.0);
+        filter.reset();
+        let mut max_difference = 0.0f64;
+        for i in 0..4800 {
+            let x = 1e-3 * ((i * 7919) % 17) as f64 / 17.0;
+            max_difference = max_difference.max((filter.process(x) - linear.process(x)).abs());
+        }
+        // Up to the seed ringing out
+        assert!(max_difference < 1e-4);
+
+        // Without self-oscillation the output dies away
+        let mut silent = [0.0; 48000];
+        filter.process_block(&mut silent);
+        assert!(silent[47999].abs() < 1e-12);
+
+        filter.set_resonance(f64::NAN);
+        assert_eq!(filter.resonance(), 0.0);
+        filter.set_resonance(9.0);
+        assert_eq!(filter.resonance(), MAX_RESONANCE as f64);
+    }
+}
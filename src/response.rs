@@ -0,0 +1,241 @@
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::first_order_iir::IIR1Coefficients;
+use crate::second_order_iir::IIR2Coefficients;
+use crate::{cast, response_frequencies, BodePoint, MAX_CUTOFF_RATIO, MIN_CUTOFF_HZ};
+
+/// Anything with a complex frequency response, so chains of different section types can be
+/// evaluated together
+pub trait BodeResponse<T: Float = f32> {
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T>;
+}
+
+impl<T: Float> BodeResponse<T> for IIR1Coefficients<T> {
+    #[inline]
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.get_bode_sample(frequency_hz, sample_rate_hz)
+    }
+}
+
+impl<T: Float> BodeResponse<T> for IIR2Coefficients<T> {
+    #[inline]
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        self.get_bode_sample(frequency_hz, sample_rate_hz)
+    }
+}
+
+/// Coefficients of either section order, for chains that mix first and second order filters
+#[derive(Copy, Clone, Debug)]
+pub enum SectionCoefficients<T = f32> {
+    FirstOrder(IIR1Coefficients<T>),
+    SecondOrder(IIR2Coefficients<T>),
+}
+
+impl<T: Float> BodeResponse<T> for SectionCoefficients<T> {
+    #[inline]
+    fn bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        match self {
+            SectionCoefficients::FirstOrder(coeffs) => {
+                coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            }
+            SectionCoefficients::SecondOrder(coeffs) => {
+                coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            }
+        }
+    }
+}
+
+impl<T> From<IIR1Coefficients<T>> for SectionCoefficients<T> {
+    fn from(coeffs: IIR1Coefficients<T>) -> Self {
+        SectionCoefficients::FirstOrder(coeffs)
+    }
+}
+
+impl<T> From<IIR2Coefficients<T>> for SectionCoefficients<T> {
+    fn from(coeffs: IIR2Coefficients<T>) -> Self {
+        SectionCoefficients::SecondOrder(coeffs)
+    }
+}
+
+/// Response of the sections processed in series, the product of their responses
+pub fn combined_bode_sample<T: Float, C: BodeResponse<T>>(
+    sections: &[C],
+    frequency_hz: T,
+    sample_rate_hz: T,
+) -> Complex<T> {
+    sections
+        .iter()
+        .fold(Complex::new(T::one(), T::zero()), |acc, section| {
+            acc * section.bode_sample(frequency_hz, sample_rate_hz)
+        })
+}
+
+/// Samples the combined response at num_points logarithmically spaced frequencies from start_hz
+/// to end_hz. The phase is unwrapped along the sweep, so it keeps accumulating past -180 degrees
+/// instead of jumping. This assumes the phase changes by less than 180 degrees between
+/// neighbouring points, use enough points around sharp resonances.
+pub fn combined_frequency_response<T: Float, C: BodeResponse<T>>(
+    sections: &[C],
+    start_hz: T,
+    end_hz: T,
+    num_points: usize,
+    sample_rate_hz: T,
+) -> Vec<BodePoint<T>> {
+    let full_turn = cast::<T>(360.0);
+    let mut previous: Option<T> = None;
+    response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+        .map(|f| {
+            let mut point =
+                BodePoint::from_complex(f, combined_bode_sample(sections, f, sample_rate_hz));
+            if let Some(previous) = previous {
+                let step = point.phase_degrees - previous;
+                point.phase_degrees = previous + step - full_turn * (step / full_turn).round();
+            }
+            previous = Some(point.phase_degrees);
+            point
+        })
+        .collect()
+}
+
+/// Points of the grid `effective_cutoff_hz` and `peak_frequency_hz` start their search on, log
+/// spaced from `MIN_CUTOFF_HZ` to `MAX_CUTOFF_RATIO` of the sample rate
+const SEARCH_POINTS: usize = 1024;
+
+fn search_grid<T: Float>(sample_rate_hz: T) -> Vec<T> {
+    let start_hz = cast::<T>(MIN_CUTOFF_HZ as f64);
+    let ratio = sample_rate_hz * cast(MAX_CUTOFF_RATIO as f64) / start_hz;
+    let last = cast::<T>((SEARCH_POINTS - 1) as f64);
+    (0..SEARCH_POINTS)
+        .map(|i| start_hz * ratio.powf(cast::<T>(i as f64) / last))
+        .collect()
+}
+
+/// Frequency where the magnitude falls 3 dB below the passband, found by bisection on a log
+/// grid. The passband is whichever of DC and Nyquist is louder, and the search starts from that
+/// end, so a resonant low pass reports the edge past its peak. `None` when the response never
+/// crosses, like for all passes and gentle shelves, or when DC and Nyquist are both silent as
+/// for band passes.
+pub(crate) fn effective_cutoff_hz<T: Float>(
+    magnitude: impl Fn(T) -> T,
+    sample_rate_hz: T,
+) -> Option<T> {
+    let dc = magnitude(T::zero());
+    let nyquist = magnitude(sample_rate_hz / cast(2.0));
+    let passband = dc.max(nyquist);
+    if !(passband.is_finite() && passband > T::zero()) {
+        return None;
+    }
+    let threshold = passband * cast(core::f64::consts::FRAC_1_SQRT_2);
+    let below = |f: T| magnitude(f) < threshold;
+
+    let mut grid = search_grid(sample_rate_hz);
+    if nyquist > dc {
+        grid.reverse();
+    }
+    let crossing = grid.iter().position(|&f| below(f))?;
+    if crossing == 0 {
+        return None;
+    }
+    // Bisect in log frequency between the last point above and the first below
+    let (mut inside, mut outside) = (grid[crossing - 1].ln(), grid[crossing].ln());
+    for _ in 0..48 {
+        let middle = (inside + outside) / cast(2.0);
+        if below(middle.exp()) {
+            outside = middle;
+        } else {
+            inside = middle;
+        }
+    }
+    Some(((inside + outside) / cast(2.0)).exp())
+}
+
+/// Frequency of the magnitude maximum, refined with a golden section search around the loudest
+/// point of a log grid. `None` when the maximum is at DC or Nyquist, so only responses with an
+/// actual peak, such as boosting bells and resonant low and high passes, report one.
+pub(crate) fn peak_frequency_hz<T: Float>(
+    magnitude: impl Fn(T) -> T,
+    sample_rate_hz: T,
+) -> Option<T> {
+    let grid = search_grid(sample_rate_hz);
+    let levels: Vec<T> = grid.iter().map(|&f| magnitude(f)).collect();
+    let loudest =
+        (0..grid.len()).fold(0, |best, i| if levels[i] > levels[best] { i } else { best });
+    if loudest == 0
+        || loudest == grid.len() - 1
+        || levels[loudest] <= magnitude(T::zero())
+        || levels[loudest] <= magnitude(sample_rate_hz / cast(2.0))
+    {
+        return None;
+    }
+
+    let inverse_phi = cast::<T>(0.618_033_988_749_894_8);
+    let (mut low, mut high) = (grid[loudest - 1].ln(), grid[loudest + 1].ln());
+    for _ in 0..64 {
+        let a = high - (high - low) * inverse_phi;
+        let b = low + (high - low) * inverse_phi;
+        if magnitude(a.exp()) < magnitude(b.exp()) {
+            low = a;
+        } else {
+            high = b;
+        }
+    }
+    Some(((low + high) / cast(2.0)).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(x: Complex<f32>) -> f32 {
+        20.0 * x.norm().log10()
+    }
+
+    #[test]
+    fn test_combined_bode_sample() {
+        let sample_rate_hz = 48000.0;
+        let chain = [
+            IIR2Coefficients::lowshelf(100.0, 6.0, 0.7, sample_rate_hz),
+            IIR2Coefficients::bell(1000.0, -4.0, 2.0, sample_rate_hz),
+            IIR2Coefficients::highshelf(8000.0, 3.0, 0.7, sample_rate_hz),
+        ];
+        let response = combined_frequency_response(&chain, 10.0, 20000.0, 200, sample_rate_hz);
+        for point in response {
+            let f = point.frequency_hz;
+            let sum: f32 = chain
+                .iter()
+                .map(|c| db(c.get_bode_sample(f, sample_rate_hz)))
+                .sum();
+            assert!((point.magnitude_db - sum).abs() < 1e-3);
+            assert!((db(combined_bode_sample(&chain, f, sample_rate_hz)) - sum).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_combined_mixed_orders() {
+        let sample_rate_hz = 48000.0;
+        let first = IIR1Coefficients::lowpass(2000.0, 0.0, sample_rate_hz);
+        let second = IIR2Coefficients::highpass(50.0, 0.0, 0.707, sample_rate_hz);
+        let chain: [SectionCoefficients; 2] = [first.into(), second.into()];
+        for f in [20.0, 500.0, 10000.0] {
+            let expected = first.get_bode_sample(f, sample_rate_hz)
+                * second.get_bode_sample(f, sample_rate_hz);
+            assert!((combined_bode_sample(&chain, f, sample_rate_hz) - expected).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_combined_phase_unwrapped() {
+        let sample_rate_hz = 48000.0;
+        // Four all passes turn the phase through four full circles from DC to Nyquist
+        let chain = [IIR2Coefficients::allpass(1000.0, 0.0, 0.707, sample_rate_hz); 4];
+        let response = combined_frequency_response(&chain, 1.0, 23900.0, 2000, sample_rate_hz);
+        for pair in response.windows(2) {
+            assert!(pair[1].phase_degrees <= pair[0].phase_degrees);
+        }
+        assert!(response[0].phase_degrees.abs() < 1.0);
+        assert!((response.last().unwrap().phase_degrees + 1440.0).abs() < 10.0);
+    }
+}
@@ -0,0 +1,163 @@
+//! RIAA phono equalization
+
+use core::f64::consts::TAU;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::cast;
+use crate::second_order_iir::IIR2Coefficients;
+
+const T1: f64 = 3180e-6;
+const T2: f64 = 318e-6;
+const T3: f64 = 75e-6;
+
+/// Analog playback response (1 + s T2) / ((1 + s T1) (1 + s T3))
+fn analog_playback(frequency_hz: f64) -> Complex<f64> {
+    let s = Complex::new(0.0, TAU * frequency_hz);
+    (s * T2 + 1.0) / ((s * T1 + 1.0) * (s * T3 + 1.0))
+}
+
+/// Playback biquad (b0, b1, b2, a1, a2) fitted to the analog curve normalized to 0 dB at 1 kHz.
+///
+/// The magnitude squared of a biquad at w is (B0 phi0 + B1 phi1 + B2 phi2) / (A0 phi0 + A1 phi1 +
+/// A2 phi2) with phi1 = sin^2(w / 2), phi0 = 1 - phi1, phi2 = 4 phi0 phi1, A0 = (1 + a1 + a2)^2,
+/// A1 = (1 - a1 + a2)^2, A2 = -4 a2 and likewise for B (Vicanek, "Matched Second Order Digital
+/// Filters"). Matching the analog curve at DC and four more frequencies is linear in the ratios
+/// to A0, after which both polynomials are factored back into coefficients. Unlike the bilinear
+/// transform this doesn't pin a zero to Nyquist, which would make the curve sag well before
+/// 20 kHz at 44.1 kHz. The remaining error up to 20 kHz, or 0.45 times the sample rate below
+/// that, is below 0.55 dB at 32 kHz, 0.4 dB at 44.1 kHz, 0.25 dB at 48 kHz, 0.015 dB at 88.2 kHz,
+/// 0.01 dB at 96 kHz and 0.001 dB at 192 kHz. At 16 kHz and below the match points lie beyond
+/// Nyquist and there is no solution.
+fn playback_biquad(sample_rate_hz: f64) -> [f64; 5] {
+    let reference = analog_playback(1000.0).norm_sqr();
+    let target = |frequency_hz: f64| analog_playback(frequency_hz).norm_sqr() / reference;
+    let phi = |frequency_hz: f64| {
+        let phi1 = (core::f64::consts::PI * frequency_hz / sample_rate_hz)
+            .sin()
+            .powi(2);
+        let phi0 = 1.0 - phi1;
+        [phi0, phi1, 4.0 * phi0 * phi1]
+    };
+
+    // Unknowns A1 / A0, A2 / A0, B1 / A0, B2 / A0
+    let dc = target(0.0);
+    let mut system = [[0.0; 5]; 4];
+    let match_hz = [100.0, 2000.0, 10000.0, (sample_rate_hz * 0.45).min(20000.0)];
+    for (row, &f) in system.iter_mut().zip(match_hz.iter()) {
+        let [phi0, phi1, phi2] = phi(f);
+        let h = target(f);
+        *row = [-h * phi1, -h * phi2, phi1, phi2, (h - dc) * phi0];
+    }
+    let [alpha, beta, gamma, delta] = solve(system);
+
+    // (1 - a1 + a2) = sqrt(alpha) (1 + a1 + a2) and -4 a2 = beta (1 + a1 + a2)^2
+    let sqrt_alpha = alpha.sqrt();
+    let u = (-(1.0 + sqrt_alpha) + ((1.0 + sqrt_alpha).powi(2) + 4.0 * beta).sqrt()) / beta;
+    let a2 = -beta * u * u / 4.0;
+    let a1 = u - 1.0 - a2;
+
+    // b0 + b1 + b2 = u sqrt(dc), b0 - b1 + b2 = u sqrt(gamma) and b0 b2 = -delta u^2 / 4
+    let at_dc = u * dc.sqrt();
+    let at_nyquist = u * gamma.sqrt();
+    let sum = (at_dc + at_nyquist) / 2.0;
+    let b1 = (at_dc - at_nyquist) / 2.0;
+    let root = (sum * sum + delta * u * u).max(0.0).sqrt();
+    [(sum + root) / 2.0, b1, (sum - root) / 2.0, a1, a2]
+}
+
+/// Gaussian elimination with partial pivoting of an augmented 4x4 system
+fn solve(mut m: [[f64; 5]; 4]) -> [f64; 4] {
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+            .unwrap();
+        m.swap(col, pivot);
+        let pivot_row = m[col];
+        for row in m.iter_mut().skip(col + 1) {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+    let mut x = [0.0; 4];
+    for row in (0..4).rev() {
+        let known: f64 = (row + 1..4).map(|k| m[row][k] * x[k]).sum();
+        x[row] = (m[row][4] - known) / m[row][row];
+    }
+    x
+}
+
+/// RIAA playback de-emphasis, 0 dB at 1 kHz. Returns `None` when the fitted biquad is not
+/// stable, which is the case at sample rates of 16 kHz and below.
+pub fn riaa_playback<T: Float>(sample_rate_hz: T) -> Option<IIR2Coefficients<T>> {
+    let [b0, b1, b2, a1, a2] = playback_biquad(sample_rate_hz.to_f64().unwrap());
+    IIR2Coefficients::from_biquad(cast(b0), cast(b1), cast(b2), cast(a1), cast(a2))
+}
+
+/// RIAA record pre-emphasis, the exact inverse of `riaa_playback`. Returns `None` at the same
+/// sample rates.
+pub fn riaa_record<T: Float>(sample_rate_hz: T) -> Option<IIR2Coefficients<T>> {
+    let [b0, b1, b2, a1, a2] = playback_biquad(sample_rate_hz.to_f64().unwrap());
+    IIR2Coefficients::from_biquad(
+        cast(1.0 / b0),
+        cast(a1 / b0),
+        cast(a2 / b0),
+        cast(b1 / b0),
+        cast(b2 / b0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_frequencies;
+
+    fn db(x: Complex<f64>) -> f64 {
+        20.0 * x.norm().log10()
+    }
+
+    #[test]
+    fn test_riaa() {
+        // Worst error of the playback curve up to 20 kHz, or 0.45 times the rate below that
+        let rates = [
+            (32000.0, 0.55),
+            (44100.0, 0.4),
+            (48000.0, 0.25),
+            (88200.0, 0.015),
+            (96000.0, 0.01),
+            (192000.0, 0.001),
+        ];
+        for &(sample_rate_hz, max_error_db) in &rates {
+            let playback = riaa_playback::<f64>(sample_rate_hz).unwrap();
+            let record = riaa_record::<f64>(sample_rate_hz).unwrap();
+            let reference = analog_playback(1000.0);
+            let end_hz = (sample_rate_hz * 0.45).min(20000.0);
+            for f in log_frequencies(20.0, end_hz, 100) {
+                let p = playback.get_bode_sample(f, sample_rate_hz);
+                let r = record.get_bode_sample(f, sample_rate_hz);
+                assert!(db(p * r).abs() < 0.1);
+                assert!((db(p) - db(analog_playback(f) / reference)).abs() < max_error_db);
+            }
+            assert!((db(playback.get_bode_sample(20.0, sample_rate_hz)) - 19.3).abs() < 0.1);
+            if sample_rate_hz > 40000.0 {
+                assert!((db(playback.get_bode_sample(20000.0, sample_rate_hz)) + 19.6).abs() < 0.1);
+            }
+
+            // f32 holds up to the rounding of the coefficients
+            let playback = riaa_playback::<f32>(sample_rate_hz as f32).unwrap();
+            for f in log_frequencies(20.0, end_hz as f32, 100) {
+                let p = playback.get_bode_sample(f, sample_rate_hz as f32);
+                let expected = db(analog_playback(f as f64) / reference);
+                assert!((20.0 * (p.norm() as f64).log10() - expected).abs() < max_error_db + 0.01);
+            }
+        }
+
+        for &sample_rate_hz in &[8000.0, 16000.0, f64::NAN] {
+            assert!(riaa_playback::<f64>(sample_rate_hz).is_none());
+            assert!(riaa_record::<f64>(sample_rate_hz).is_none());
+        }
+    }
+}
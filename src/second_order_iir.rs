@@ -1,58 +1,448 @@
-use std::f32::consts::{PI, TAU};
+use alloc::vec::Vec;
+use core::f64::consts::{FRAC_1_SQRT_2, PI, TAU};
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
 
 use num_complex::Complex;
+use num_traits::Float;
 
-#[derive(Copy, Clone, Debug)]
-pub struct IIR2Coefficients {
-    pub a: f32,
-    pub g: f32,
-    pub gpow2: f32,
-    pub k: f32,
-    pub a1: f32,
-    pub a2: f32,
-    pub a3: f32,
-    pub m0: f32,
-    pub m1: f32,
-    pub m2: f32,
+use crate::biquad::BiquadCoefficients;
+use crate::response;
+use crate::units::{note_to_hz, note_to_hz_tuned};
+use crate::{
+    cast, check_design, check_q, clamp_cutoff, fast_prewarp, fast_tan_parts, fields_approx_eq,
+    flush_denormal, group_delay, max_response_deviation_db, normalize_filter_name, quadratic_roots,
+    response_frequencies, sanitize_cutoff, sanitize_gain, sanitize_q, soft_clip, BodePoint,
+    EventOffsetError, FilterDesignError, ParseFilterTypeError, MAX_CUTOFF_RATIO,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IIR2FilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    AllPass,
+    LowShelf,
+    HighShelf,
+    Bell,
+    ConstantQBell,
+    Tilt,
+}
+
+impl IIR2FilterType {
+    pub const ALL: [IIR2FilterType; 10] = [
+        IIR2FilterType::LowPass,
+        IIR2FilterType::HighPass,
+        IIR2FilterType::BandPass,
+        IIR2FilterType::Notch,
+        IIR2FilterType::AllPass,
+        IIR2FilterType::LowShelf,
+        IIR2FilterType::HighShelf,
+        IIR2FilterType::Bell,
+        IIR2FilterType::ConstantQBell,
+        IIR2FilterType::Tilt,
+    ];
+}
+
+/// Parses names like "LowPass", "low_pass" or "lowpass", ignoring case
+impl FromStr for IIR2FilterType {
+    type Err = ParseFilterTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_filter_name(s).as_str() {
+            "lowpass" => Ok(IIR2FilterType::LowPass),
+            "highpass" => Ok(IIR2FilterType::HighPass),
+            "bandpass" => Ok(IIR2FilterType::BandPass),
+            "notch" => Ok(IIR2FilterType::Notch),
+            "allpass" => Ok(IIR2FilterType::AllPass),
+            "lowshelf" => Ok(IIR2FilterType::LowShelf),
+            "highshelf" => Ok(IIR2FilterType::HighShelf),
+            "bell" => Ok(IIR2FilterType::Bell),
+            "constantqbell" => Ok(IIR2FilterType::ConstantQBell),
+            "tilt" => Ok(IIR2FilterType::Tilt),
+            _ => Err(ParseFilterTypeError),
+        }
+    }
+}
+
+/// Writes names like "Low Pass", which `from_str` parses back
+impl fmt::Display for IIR2FilterType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            IIR2FilterType::LowPass => "Low Pass",
+            IIR2FilterType::HighPass => "High Pass",
+            IIR2FilterType::BandPass => "Band Pass",
+            IIR2FilterType::Notch => "Notch",
+            IIR2FilterType::AllPass => "All Pass",
+            IIR2FilterType::LowShelf => "Low Shelf",
+            IIR2FilterType::HighShelf => "High Shelf",
+            IIR2FilterType::Bell => "Bell",
+            IIR2FilterType::ConstantQBell => "Constant Q Bell",
+            IIR2FilterType::Tilt => "Tilt",
+        };
+        write!(f, "{}", name)
+    }
 }
 
-impl IIR2Coefficients {
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR2Coefficients<T = f32> {
+    pub a: T,
+    pub g: T,
+    pub gpow2: T,
+    pub k: T,
+    pub a1: T,
+    pub a2: T,
+    pub a3: T,
+    pub m0: T,
+    pub m1: T,
+    pub m2: T,
+}
+
+impl<T: Float> IIR2Coefficients<T> {
+    /// Creates coefficients for any filter type, dispatching to the dedicated constructor
+    #[inline]
+    pub fn new(
+        filter_type: IIR2FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let constructor = match filter_type {
+            IIR2FilterType::LowPass => IIR2Coefficients::lowpass,
+            IIR2FilterType::HighPass => IIR2Coefficients::highpass,
+            IIR2FilterType::BandPass => IIR2Coefficients::bandpass,
+            IIR2FilterType::Notch => IIR2Coefficients::notch,
+            IIR2FilterType::AllPass => IIR2Coefficients::allpass,
+            IIR2FilterType::LowShelf => IIR2Coefficients::lowshelf,
+            IIR2FilterType::HighShelf => IIR2Coefficients::highshelf,
+            IIR2FilterType::Bell => IIR2Coefficients::bell,
+            IIR2FilterType::ConstantQBell => IIR2Coefficients::constant_q_bell,
+            IIR2FilterType::Tilt => IIR2Coefficients::tilt,
+        };
+        constructor(cutoff_hz, gain_db, q_value, sample_rate_hz)
+    }
+
     #[inline]
-    pub fn get_bode_sample(self, frequency_hz: f32, sample_rate_hz: f32) -> Complex<f32> {
+    pub fn get_bode_sample(self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
         //Use y.norm() for amplitude and y.arg().to_degrees() for phase. Add to combine phase.
 
-        let z = -TAU * frequency_hz / sample_rate_hz;
-        let z = z.cos() + z.sin() * Complex::<f32>::new(0.0, 1.0);
+        let z = -cast::<T>(TAU) * frequency_hz / sample_rate_hz;
+        let z = Complex::new(z.cos(), z.sin());
         let zpow2 = z * z;
 
-        let denominator = (self.gpow2 + self.g * self.k + 1.0)
-            + 2.0 * (self.gpow2 - 1.0) * z
-            + (self.gpow2 - self.g * self.k + 1.0) * zpow2;
+        let denominator = zpow2 * (self.gpow2 - self.g * self.k + T::one())
+            + z * (cast::<T>(2.0) * (self.gpow2 - T::one()))
+            + (self.gpow2 + self.g * self.k + T::one());
+
+        let numerator = (-zpow2 + T::one()) * (self.m1 * self.g)
+            + (z * cast::<T>(2.0) + zpow2 + T::one()) * (self.m2 * self.gpow2);
+
+        numerator / denominator + self.m0
+    }
+
+    /// Evaluates `get_bode_sample` at every frequency in frequencies, writing the results to out.
+    /// Meant for redrawing response curves. The response is taken as a ratio of polynomials in
+    /// j tan(PI f / fs) with the mix folded into the numerator, using coefficients computed once
+    /// and polynomial trig, see `fast_tan_parts`. The result is within 0.01 dB and 0.01 degrees
+    /// of the scalar method in f64, and unlike it keeps that precision in f32 for steep filters
+    /// close to DC and Nyquist, where z is nearly ±1 and the mix of a high pass nearly cancels.
+    pub fn get_bode_samples(&self, frequencies: &[T], sample_rate_hz: T, out: &mut [Complex<T>]) {
+        assert_eq!(frequencies.len(), out.len());
+        let gk = self.g * self.k;
+        let n1 = (self.m0 * self.k + self.m1) * self.g;
+        let n0 = (self.m0 + self.m2) * self.gpow2;
+        let angle_per_hz = cast::<T>(PI) / sample_rate_hz;
+
+        for (frequency_hz, out) in frequencies.iter().zip(out.iter_mut()) {
+            // Both polynomials times cos^2, so tan = sin / cos never divides
+            let (sin, cos) = fast_tan_parts(angle_per_hz * *frequency_hz);
+            let (sin2, cos2, sin_cos) = (sin * sin, cos * cos, sin * cos);
+            let numerator = Complex::new(n0 * cos2 - self.m0 * sin2, n1 * sin_cos);
+            let denominator = Complex::new(self.gpow2 * cos2 - sin2, gk * sin_cos);
+            *out = numerator / denominator;
+        }
+    }
+
+    /// Moves coefficients designed at sample_rate_hz to new_sample_rate_hz. The frequency g was
+    /// prewarped from is recovered with atan and clamped against the new Nyquist, while k and the
+    /// mix do not depend on the sample rate. So the response at the cutoff is unchanged for every
+    /// type apart from the shelves and tilt, whose g is also scaled by the gain. Up to a 1 kHz
+    /// cutoff they stay within 0.1 dB for gains up to 18 dB and Q up to 4, closer to Nyquist they
+    /// drift by several dB. Further from the cutoff the bilinear warping differs between the
+    /// rates, as it would for a fresh design. Use `DesignedIIR2::with_sample_rate`
+    /// when the design parameters are known.
+    pub fn at_sample_rate(&self, sample_rate_hz: T, new_sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let pi = cast::<T>(PI);
+        let warped_hz = self.g.atan() * sample_rate_hz / pi;
+        let warped_hz = clamp_cutoff(warped_hz, new_sample_rate_hz);
+        let g = (pi * warped_hz / new_sample_rate_hz).tan();
+        let a1 = T::one() / (T::one() + g * (g + self.k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        IIR2Coefficients {
+            g,
+            gpow2: g * g,
+            a1,
+            a2,
+            a3,
+            ..*self
+        }
+    }
+
+    /// Frequency where the response falls 3 dB below the passband, see
+    /// `response::effective_cutoff_hz` for how the passband is chosen
+    pub fn effective_cutoff_hz(&self, sample_rate_hz: T) -> Option<T> {
+        response::effective_cutoff_hz(
+            |f| self.get_bode_sample(f, sample_rate_hz).norm(),
+            sample_rate_hz,
+        )
+    }
+
+    /// Frequency of the magnitude peak, `None` if the loudest point is at DC or Nyquist
+    pub fn peak_frequency_hz(&self, sample_rate_hz: T) -> Option<T> {
+        response::peak_frequency_hz(
+            |f| self.get_bode_sample(f, sample_rate_hz).norm(),
+            sample_rate_hz,
+        )
+    }
+
+    /// Poles and zeros of the transfer function in the z plane, as `(poles, zeros)`. Complex
+    /// pairs are returned with the positive imaginary part first. A zero that moves to infinity, when
+    /// b0 of the equivalent biquad is zero, is returned as infinite.
+    pub fn poles_zeros(&self) -> ([Complex<T>; 2], [Complex<T>; 2]) {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.to_biquad();
+        (
+            quadratic_roots(T::one(), a1, a2),
+            quadratic_roots(b0, b1, b2),
+        )
+    }
+
+    /// True if both poles are strictly inside the unit circle. The SVF is stable exactly when g
+    /// and k are positive (see `from_biquad`), so this checks those instead of the pole radius.
+    /// The radius from `poles_zeros` needs a tolerance to be useful: at Q = 100 and a 10 Hz
+    /// cutoff at 48 kHz the poles are within 1e-5 of the circle, and far above that Q the f32 radius rounds
+    /// to 1.0 while the filter still runs fine.
+    pub fn is_stable(&self) -> bool {
+        self.is_finite() && self.g > T::zero() && self.k > T::zero()
+    }
+
+    /// Converts to normalized direct form biquad coefficients with the same transfer function.
+    /// Direct form is much more sensitive to rounding at low cutoffs than the SVF, use f64 when the
+    /// result is exported to other processors.
+    pub fn to_biquad(&self) -> BiquadCoefficients<T> {
+        let two = cast::<T>(2.0);
+        let gk = self.g * self.k;
+        let a0 = self.gpow2 + gk + T::one();
+        let a1 = two * (self.gpow2 - T::one());
+        let a2 = self.gpow2 - gk + T::one();
+        let b0 = self.m0 * a0 + self.m1 * self.g + self.m2 * self.gpow2;
+        let b1 = self.m0 * a1 + two * self.m2 * self.gpow2;
+        let b2 = self.m0 * a2 - self.m1 * self.g + self.m2 * self.gpow2;
+        BiquadCoefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// Maps normalized direct form biquad coefficients onto the SVF. Returns `None` when the
+    /// biquad is not stable, which is also exactly when no SVF with positive g and k exists.
+    pub fn from_biquad(b0: T, b1: T, b2: T, a1: T, a2: T) -> Option<IIR2Coefficients<T>> {
+        let two = cast::<T>(2.0);
+        let four = cast::<T>(4.0);
+        let gpow2 = (T::one() + a1 + a2) / (T::one() - a1 + a2);
+        if !(gpow2.is_finite() && gpow2 > T::zero()) {
+            return None;
+        }
+        let g = gpow2.sqrt();
+        let norm = four / (T::one() - a1 + a2);
+        let k = (T::one() - a2) * norm / (two * g);
+        if !(k.is_finite() && k > T::zero()) {
+            return None;
+        }
+
+        let (b0, b1, b2) = (b0 * norm, b1 * norm, b2 * norm);
+        let m0 = (b0 - b1 + b2) / four;
+        let m1 = (b0 - b2) / (two * g) - m0 * k;
+        let m2 = (b0 + b1 + b2) / (four * gpow2) - m0;
+
+        let a1 = T::one() / (T::one() + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        Some(IIR2Coefficients {
+            a: T::one(),
+            g,
+            gpow2,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        })
+    }
+
+    /// Interpolates between two coefficient sets, t = 0 returns self and t = 1 returns other.
+    /// g, k and the mix are interpolated and the rest is re-derived, so every step in between is a
+    /// stable filter.
+    pub fn lerp(&self, other: &IIR2Coefficients<T>, t: T) -> IIR2Coefficients<T> {
+        let mix = |x: T, y: T| x + (y - x) * t;
+        let g = mix(self.g, other.g);
+        let k = mix(self.k, other.k);
+        let a1 = T::one() / (T::one() + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        IIR2Coefficients {
+            a: mix(self.a, other.a),
+            g,
+            gpow2: g * g,
+            k,
+            a1,
+            a2,
+            a3,
+            m0: mix(self.m0, other.m0),
+            m1: mix(self.m1, other.m1),
+            m2: mix(self.m2, other.m2),
+        }
+    }
+
+    /// Returns false if any coefficient is NaN or infinite, e.g. when built from a NaN cutoff.
+    /// Check before handing coefficients computed from untrusted parameters to a filter.
+    pub fn is_finite(&self) -> bool {
+        [
+            self.a, self.g, self.gpow2, self.k, self.a1, self.a2, self.a3, self.m0, self.m1,
+            self.m2,
+        ]
+        .iter()
+        .all(|x| x.is_finite())
+    }
+
+    /// Phase in degrees at frequency_hz, wrapped to -180..180
+    pub fn phase_degrees_at(&self, frequency_hz: T, sample_rate_hz: T) -> T {
+        self.get_bode_sample(frequency_hz, sample_rate_hz)
+            .arg()
+            .to_degrees()
+    }
+
+    /// Group delay in samples at frequency_hz, divide by the sample rate for seconds
+    pub fn group_delay_at(&self, frequency_hz: T, sample_rate_hz: T) -> T {
+        group_delay(
+            |f| self.get_bode_sample(f, sample_rate_hz),
+            frequency_hz,
+            sample_rate_hz,
+        )
+    }
+
+    /// Whether every coefficient is within tolerance of the one in other, relative to the larger
+    /// of the two, or absolutely for coefficients below 1
+    pub fn approx_eq(&self, other: &IIR2Coefficients<T>, tolerance: T) -> bool {
+        fields_approx_eq(
+            &[
+                self.a, self.g, self.gpow2, self.k, self.a1, self.a2, self.a3, self.m0, self.m1,
+                self.m2,
+            ],
+            &[
+                other.a,
+                other.g,
+                other.gpow2,
+                other.k,
+                other.a1,
+                other.a2,
+                other.a3,
+                other.m0,
+                other.m1,
+                other.m2,
+            ],
+            tolerance,
+        )
+    }
 
-        let y = self.m0
-            + (self.m1 * self.g * (1.0 - zpow2) + self.m2 * self.gpow2 * (1.0 + 2.0 * z + zpow2))
-                / denominator;
+    /// Worst magnitude difference in dB between the responses of self and other from
+    /// `MIN_RESPONSE_HZ` to `MAX_CUTOFF_RATIO` of the sample rate, with both floored at
+    /// `DEVIATION_FLOOR_DB`. Tells if two sets of coefficients sound the same, even when they
+    /// differ in ways `approx_eq` would flag.
+    pub fn max_response_deviation_db(&self, other: &IIR2Coefficients<T>, sample_rate_hz: T) -> T {
+        max_response_deviation_db(
+            |f| self.get_bode_sample(f, sample_rate_hz),
+            |f| other.get_bode_sample(f, sample_rate_hz),
+            sample_rate_hz,
+        )
+    }
 
-        y
+    /// Samples the response at num_points logarithmically spaced frequencies from start_hz to
+    /// end_hz. start_hz is raised to at least `MIN_RESPONSE_HZ` and end_hz lowered to at most
+    /// `MAX_CUTOFF_RATIO` of the sample rate.
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Vec<BodePoint<T>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| BodePoint::from_complex(f, self.get_bode_sample(f, sample_rate_hz)))
+            .collect()
     }
 
     #[inline]
-    pub fn lowpass(
-        cutoff_hz: f32,
-        _gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+    pub fn empty() -> IIR2Coefficients<T> {
+        IIR2Coefficients {
+            a: T::zero(),
+            g: T::zero(),
+            gpow2: T::zero(),
+            k: T::zero(),
+            a1: T::zero(),
+            a2: T::zero(),
+            a3: T::zero(),
+            m0: T::zero(),
+            m1: T::zero(),
+            m2: T::zero(),
+        }
+    }
+
+    /// Passes the input through unchanged. The states never move away from zero and g/k are
+    /// chosen so `get_bode_sample` stays well defined.
+    #[inline]
+    pub fn bypass() -> IIR2Coefficients<T> {
+        IIR2Coefficients {
+            a: T::one(),
+            g: T::one(),
+            gpow2: T::one(),
+            k: cast(2.0),
+            a1: T::zero(),
+            a2: T::zero(),
+            a3: T::zero(),
+            m0: T::one(),
+            m1: T::zero(),
+            m2: T::zero(),
+        }
+    }
+
+    #[inline]
+    pub fn lowpass(cutoff_hz: T, gain_db: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 0.0;
-        let m1 = 0.0;
-        let m2 = 1.0;
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = T::zero();
+        let m1 = T::zero();
+        let m2 = gain;
         IIR2Coefficients {
             a,
             g,
@@ -69,21 +459,26 @@ impl IIR2Coefficients {
 
     #[inline]
     pub fn highpass(
-        cutoff_hz: f32,
-        _gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 1.0;
-        let m1 = -k;
-        let m2 = -1.0;
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = gain;
+        let m1 = -k * gain;
+        let m2 = -gain;
         IIR2Coefficients {
             a,
             g,
@@ -100,21 +495,26 @@ impl IIR2Coefficients {
 
     #[inline]
     pub fn bandpass(
-        cutoff_hz: f32,
-        _gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 0.0;
-        let m1 = 1.0;
-        let m2 = 0.0;
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = T::zero();
+        let m1 = gain;
+        let m2 = T::zero();
         IIR2Coefficients {
             a,
             g,
@@ -129,23 +529,80 @@ impl IIR2Coefficients {
         }
     }
 
+    /// Band pass with a peak gain of exactly 0 dB at the cutoff for any Q. `bandpass` peaks at Q
+    /// times its gain instead.
     #[inline]
-    pub fn notch(
-        cutoff_hz: f32,
-        _gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+    pub fn bandpass_peak_normalized(
+        cutoff_hz: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let mut coeffs = IIR2Coefficients::bandpass(cutoff_hz, T::zero(), q_value, sample_rate_hz);
+        coeffs.m1 = coeffs.k;
+        coeffs
+    }
+
+    /// The cookbook "constant skirt gain" band pass, whose peak gain is Q and whose skirts do not
+    /// move with Q. This is `bandpass` at 0 dB, kept under the cookbook name for clarity.
+    #[inline]
+    pub fn bandpass_constant_skirt(
+        cutoff_hz: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        IIR2Coefficients::bandpass(cutoff_hz, T::zero(), q_value, sample_rate_hz)
+    }
+
+    /// Two pole resonator ringing at center_hz, whose impulse response decays by 60 dB in
+    /// decay_seconds, with a gain of exactly 1 at the center. The pole radius r is set from the
+    /// decay, r^(decay_seconds * sample_rate_hz) = 10^-3, and k is solved from the SVF pole
+    /// radius r^2 = (1 + g^2 - g k) / (1 + g^2 + g k). Out of range decays are clamped by Q.
+    #[inline]
+    pub fn resonator(center_hz: T, decay_seconds: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let mut coeffs = IIR2Coefficients::bandpass(center_hz, T::zero(), T::one(), sample_rate_hz);
+        let g = coeffs.g;
+        let rpow2 = cast::<T>(10.0).powf(cast::<T>(-6.0) / (decay_seconds * sample_rate_hz));
+        let k = (T::one() + coeffs.gpow2) * (T::one() - rpow2) / (g * (T::one() + rpow2));
+        let k = T::one() / sanitize_q(T::one() / k);
+        coeffs.k = k;
+        coeffs.a1 = T::one() / (T::one() + g * (g + k));
+        coeffs.a2 = g * coeffs.a1;
+        coeffs.a3 = g * coeffs.a2;
+        coeffs.m1 = k;
+        coeffs
+    }
+
+    /// Linkwitz transform, moves the pole pair of a driver at f0_hz and q0 to f1_hz and q1, so a
+    /// sealed box rolling off at f0_hz afterwards rolls off like a high pass at f1_hz. This is
+    /// the analog (s^2 + s w0 / q0 + w0^2) / (s^2 + s w1 / q1 + w1^2), with both frequencies
+    /// prewarped so the zeros land exactly on the poles of `highpass(f0_hz, 0.0, q0, ..)`. The
+    /// gain at DC is (f0_hz / f1_hz)^2 before warping, so lowering the roll off boosts the lows.
+    #[inline]
+    pub fn linkwitz_transform(
+        f0_hz: T,
+        q0: T,
+        f1_hz: T,
+        q1: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let (Some(f0_hz), Some(f1_hz)) = (
+            sanitize_cutoff(f0_hz, sample_rate_hz),
+            sanitize_cutoff(f1_hz, sample_rate_hz),
+        ) else {
+            return IIR2Coefficients::bypass();
+        };
+        let q0 = sanitize_q(q0);
+        let q1 = sanitize_q(q1);
+        let a = T::one();
+        let g = (cast::<T>(PI) * f1_hz / sample_rate_hz).tan();
+        let ratio = (cast::<T>(PI) * f0_hz / sample_rate_hz).tan() / g;
+        let k = T::one() / q1;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 1.0;
-        let m1 = -k;
-        let m2 = 0.0;
+        let m0 = T::one();
+        let m1 = ratio / q0 - k;
+        let m2 = ratio * ratio - T::one();
         IIR2Coefficients {
             a,
             g,
@@ -160,23 +617,84 @@ impl IIR2Coefficients {
         }
     }
 
+    /// Partial notch that attenuates the cutoff by depth_db instead of removing it, and returns
+    /// to unity at DC and Nyquist. It has the poles of `notch` with the same Q, so it is as wide
+    /// as a full notch would be, only shallower. Positive depths are treated as 0 dB.
     #[inline]
-    pub fn allpass(
-        cutoff_hz: f32,
-        _gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 1.0;
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+    pub fn notch_depth(
+        cutoff_hz: T,
+        depth_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let mut coeffs = IIR2Coefficients::notch(cutoff_hz, T::zero(), q_value, sample_rate_hz);
+        let depth = cast::<T>(10.0).powf(sanitize_gain(depth_db).min(T::zero()) / cast(20.0));
+        coeffs.m0 = T::one();
+        coeffs.m1 = coeffs.k * (depth - T::one());
+        coeffs.m2 = T::zero();
+        coeffs
+    }
+
+    /// Notch whose -3 dB points are bandwidth_octaves apart, see `q_from_bandwidth`
+    #[inline]
+    pub fn notch_bw(
+        cutoff_hz: T,
+        gain_db: T,
+        bandwidth_octaves: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let q_value = q_from_bandwidth(cutoff_hz, bandwidth_octaves, sample_rate_hz);
+        IIR2Coefficients::notch(cutoff_hz, gain_db, q_value, sample_rate_hz)
+    }
+
+    #[inline]
+    pub fn notch(cutoff_hz: T, gain_db: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = gain;
+        let m1 = -k * gain;
+        let m2 = T::zero();
+        IIR2Coefficients {
+            a,
+            g,
+            gpow2: g * g,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        }
+    }
+
+    #[inline]
+    pub fn allpass(cutoff_hz: T, gain_db: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = T::one();
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 1.0;
-        let m1 = -2.0 * k;
-        let m2 = 0.0;
+        let gain = cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let m0 = gain;
+        let m1 = -cast::<T>(2.0) * k * gain;
+        let m2 = T::zero();
         IIR2Coefficients {
             a,
             g,
@@ -191,23 +709,39 @@ impl IIR2Coefficients {
         }
     }
 
+    /// Low shelf with an RBJ style slope instead of Q, see `q_from_slope`
+    #[inline]
+    pub fn lowshelf_slope(
+        cutoff_hz: T,
+        gain_db: T,
+        slope: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let q_value = q_from_slope(gain_db, slope);
+        IIR2Coefficients::lowshelf(cutoff_hz, gain_db, q_value, sample_rate_hz)
+    }
+
     #[inline]
     pub fn lowshelf(
-        cutoff_hz: f32,
-        gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 10.0f32.powf(gain_db / 40.0);
-        let g = (PI * cutoff_hz / sample_rate_hz).tan() / a.sqrt();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan() / a.sqrt();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 1.0;
-        let m1 = k * (a - 1.0);
-        let m2 = a * a - 1.0;
+        let m0 = T::one();
+        let m1 = k * (a - T::one());
+        let m2 = a * a - T::one();
         IIR2Coefficients {
             a,
             g,
@@ -222,23 +756,39 @@ impl IIR2Coefficients {
         }
     }
 
+    /// High shelf with an RBJ style slope instead of Q, see `q_from_slope`
+    #[inline]
+    pub fn highshelf_slope(
+        cutoff_hz: T,
+        gain_db: T,
+        slope: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let q_value = q_from_slope(gain_db, slope);
+        IIR2Coefficients::highshelf(cutoff_hz, gain_db, q_value, sample_rate_hz)
+    }
+
     #[inline]
     pub fn highshelf(
-        cutoff_hz: f32,
-        gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 10.0f32.powf(gain_db / 40.0);
-        let g = (PI * cutoff_hz / sample_rate_hz).tan() * a.sqrt();
-        let k = 1.0 / q_value;
-        let a1 = 1.0 / (1.0 + g * (g + k));
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan() * a.sqrt();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
         let m0 = a * a;
-        let m1 = k * (1.0 - a) * a;
-        let m2 = 1.0 - a * a;
+        let m1 = k * (T::one() - a) * a;
+        let m2 = T::one() - a * a;
         IIR2Coefficients {
             a,
             g,
@@ -253,23 +803,34 @@ impl IIR2Coefficients {
         }
     }
 
+    /// Bell whose half gain points are bandwidth_octaves apart, see `q_from_bandwidth`
+    #[inline]
+    pub fn bell_bw(
+        cutoff_hz: T,
+        gain_db: T,
+        bandwidth_octaves: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let q_value = q_from_bandwidth(cutoff_hz, bandwidth_octaves, sample_rate_hz);
+        IIR2Coefficients::bell(cutoff_hz, gain_db, q_value, sample_rate_hz)
+    }
+
     #[inline]
-    pub fn bell(
-        cutoff_hz: f32,
-        gain_db: f32,
-        q_value: f32,
-        sample_rate_hz: f32,
-    ) -> IIR2Coefficients {
-        let cutoff_hz = cutoff_hz.min(sample_rate_hz * 0.5);
-        let a = 10.0f32.powf(gain_db / 40.0);
-        let g = (PI * cutoff_hz / sample_rate_hz).tan();
-        let k = 1.0 / (q_value * a);
-        let a1 = 1.0 / (1.0 + g * (g + k));
+    pub fn bell(cutoff_hz: T, gain_db: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / (q_value * a);
+        let a1 = T::one() / (T::one() + g * (g + k));
         let a2 = g * a1;
         let a3 = g * a2;
-        let m0 = 1.0;
-        let m1 = k * (a * a - 1.0);
-        let m2 = 0.0;
+        let m0 = T::one();
+        let m1 = k * (a * a - T::one());
+        let m2 = T::zero();
         IIR2Coefficients {
             a,
             g,
@@ -283,69 +844,2521 @@ impl IIR2Coefficients {
             m2,
         }
     }
-}
 
-/// Internal states and coefficients of the SVF form
-#[derive(Copy, Clone, Debug)]
-pub struct IIR2 {
-    ic1eq: f32,
-    ic2eq: f32,
-    pub coeffs: IIR2Coefficients,
-}
+    /// Bell with the same analog prototype as `bell`, but without the cramping of the bilinear
+    /// transform, after S. J. Orfanidis, "Digital Parametric Equalizer Design With Prescribed
+    /// Nyquist-Frequency Gain" (1997). `bell` forces the gain at Nyquist to 0 dB, so bells close
+    /// to Nyquist get narrower and lopsided. This one takes the gain the analog bell has at
+    /// Nyquist instead, while keeping gain_db at the cutoff and the half gain bandwidth of
+    /// q_value, so high boosts keep their shape. Well below Nyquist both are the same.
+    ///
+    /// Like the matched designs it is computed in f64 whatever T is, and returns `None` when the
+    /// sample rate is unusable or the result is not a stable biquad.
+    pub fn bell_decramped(
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Option<IIR2Coefficients<T>> {
+        let cutoff_hz = sanitize_cutoff(cutoff_hz, sample_rate_hz)?.to_f64()?;
+        let sample_rate_hz = sample_rate_hz.to_f64()?;
+        let gain_db = sanitize_gain(gain_db).to_f64()?;
+        let q_value = sanitize_q(q_value).to_f64()?;
+        let a = 10.0f64.powf(gain_db / 40.0);
+        // The design divides by the distance between the peak and band edge gains
+        if (a - 1.0).abs() < 1e-6 {
+            return Some(IIR2Coefficients::bypass());
+        }
 
-impl IIR2 {
-    /// Creates a SVF from a set of filter coefficients
+        // Reference gain G0 = 1, peak gain G = A^2, and the band edges are at the half gain
+        // GB = A, where the prototype of `bell` is w0 / Q wide
+        let gain = a * a;
+        let (gpow2, gbpow2) = (gain * gain, gain);
+        let w0 = TAU * cutoff_hz / sample_rate_hz;
+        let bandwidth = w0 / q_value;
+
+        let f = (gpow2 - gbpow2).abs();
+        let g00 = (gpow2 - 1.0).abs();
+        let f00 = (gbpow2 - 1.0).abs();
+        let detune = (w0 * w0 - PI * PI).powi(2);
+        let spread = f00 * (PI * bandwidth).powi(2) / f;
+        let g1 = ((detune + gpow2 * spread) / (detune + spread)).sqrt();
+
+        let g01 = (gpow2 - g1).abs();
+        let g11 = (gpow2 - g1 * g1).abs();
+        let f01 = (gbpow2 - g1).abs();
+        let f11 = (gbpow2 - g1 * g1).abs();
+        let w2 = (g11 / g00).sqrt() * (w0 / 2.0).tan().powi(2);
+        let dw = (1.0 + (f00 / f11).sqrt() * w2) * (bandwidth / 2.0).tan();
+        let c = f11 * dw * dw - 2.0 * w2 * (f01 - (f00 * f11).sqrt());
+        let d = 2.0 * w2 * (g01 - (g00 * g11).sqrt());
+        let big_a = ((c + d) / f).sqrt();
+        let big_b = ((gpow2 * c + gbpow2 * d) / f).sqrt();
+
+        let norm = 1.0 + w2 + big_a;
+        IIR2Coefficients::from_biquad(
+            (g1 + w2 + big_b) / norm,
+            -2.0 * (g1 - w2) / norm,
+            (g1 - big_b + w2) / norm,
+            -2.0 * (1.0 - w2) / norm,
+            (1.0 + w2 - big_a) / norm,
+        )
+        .map(IIR2Coefficients::from_f64)
+    }
+
+    /// Band shelf with a flat plateau of gain_db between low_hz and high_hz and unity gain
+    /// outside, as a high shelf up at low_hz followed by the mirrored high shelf back down at
+    /// high_hz. Both corners sit at the half gain point of their shelf, and with the corners a
+    /// few octaves apart the plateau is flat to 0.1 dB from two octaves inside them. A single SVF
+    /// can only peak at one frequency, so this takes two sections, to run through `IIR2Cascade`.
+    /// The corners may come in either order.
     #[inline]
-    pub fn from(coefficients: IIR2Coefficients) -> Self {
-        IIR2 {
-            ic1eq: 0.0,
-            ic2eq: 0.0,
-            coeffs: coefficients,
+    pub fn bandshelf(
+        low_hz: T,
+        high_hz: T,
+        gain_db: T,
+        sample_rate_hz: T,
+    ) -> [IIR2Coefficients<T>; 2] {
+        let q_value = cast(FRAC_1_SQRT_2);
+        [
+            IIR2Coefficients::highshelf(low_hz.min(high_hz), gain_db, q_value, sample_rate_hz),
+            IIR2Coefficients::highshelf(low_hz.max(high_hz), -gain_db, q_value, sample_rate_hz),
+        ]
+    }
+
+    /// Boosts or cuts the band around the cutoff by gain_db like a bell, but the bandwidth is set
+    /// by q_value alone and does not change with the gain. There is no plateau, the response
+    /// peaks at the cutoff, see `bandshelf` for a flat band.
+    #[inline]
+    pub fn constant_q_bell(
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let m0 = T::one();
+        let m1 = k * (a * a - T::one());
+        let m2 = T::zero();
+        IIR2Coefficients {
+            a,
+            g,
+            gpow2: g * g,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
         }
     }
 
+    /// Tilts the spectrum around pivot_hz, with -gain_db/2 at DC and +gain_db/2 at Nyquist.
     #[inline]
-    pub fn process(&mut self, input_sample: f32) -> f32 {
-        let v3 = input_sample - self.ic2eq;
-        let v1 = self.coeffs.a1 * self.ic1eq + self.coeffs.a2 * v3;
-        let v2 = self.ic2eq + self.coeffs.a2 * self.ic1eq + self.coeffs.a3 * v3;
-        self.ic1eq = 2.0 * v1 - self.ic1eq;
-        self.ic2eq = 2.0 * v2 - self.ic2eq;
+    pub fn tilt(pivot_hz: T, gain_db: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        let Some(pivot_hz) = sanitize_cutoff(pivot_hz, sample_rate_hz) else {
+            return IIR2Coefficients::bypass();
+        };
+        let gain_db = sanitize_gain(gain_db);
+        let q_value = sanitize_q(q_value);
+        let a = cast::<T>(10.0).powf(gain_db / cast(40.0));
+        let g = (cast::<T>(PI) * pivot_hz / sample_rate_hz).tan() * a.sqrt();
+        let k = T::one() / q_value;
+        let a1 = T::one() / (T::one() + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let m0 = a;
+        let m1 = k * (T::one() - a);
+        let m2 = (T::one() - a * a) / a;
+        IIR2Coefficients {
+            a,
+            g,
+            gpow2: g * g,
+            k,
+            a1,
+            a2,
+            a3,
+            m0,
+            m1,
+            m2,
+        }
+    }
+}
 
-        self.coeffs.m0 * input_sample + self.coeffs.m1 * v1 + self.coeffs.m2 * v2
+/// Q of a bell, notch or band pass whose band edges are bandwidth_octaves apart in the digital
+/// response. The edges are the -3 dB points of the notch and band pass, and the half gain points
+/// of the bell. The analog relation Q = 1 / (2 sinh(ln(2) / 2 * BW)) is only right well below
+/// Nyquist, as the bilinear transform squeezes the upper edge. Here the edges are solved for on
+/// the warped frequency axis instead, so the bandwidth is exact at any cutoff. If the upper edge
+/// would have to be above Nyquist, the widest band that fits is used. Takes its arguments in
+/// the order of `rbj::q_from_bandwidth`.
+pub fn q_from_bandwidth<T: Float>(cutoff_hz: T, bandwidth_octaves: T, sample_rate_hz: T) -> T {
+    let cutoff_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+    let half_turn = cast::<T>(PI);
+    let w0 = cast::<T>(TAU) * cutoff_hz / sample_rate_hz;
+    let t0 = (w0 / cast(2.0)).tan();
+    let ratio = cast::<T>(2.0).powf(bandwidth_octaves);
+
+    // The edges w1 and ratio * w1 are where the analog prototype has its edges, which satisfy
+    // tan(w1 / 2) * tan(w2 / 2) = tan(w0 / 2)^2. The product grows with w1, so bisect it.
+    let edges = |w1: T| ((w1 / cast(2.0)).tan(), (ratio * w1 / cast(2.0)).tan());
+    let highest_edge = half_turn * cast(2.0 * MAX_CUTOFF_RATIO as f64);
+    let (mut low, mut high) = (T::zero(), w0.min(highest_edge / ratio));
+    for _ in 0..64 {
+        let middle = (low + high) / cast(2.0);
+        let (t1, t2) = edges(middle);
+        if t1 * t2 < t0 * t0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
     }
+    let (t1, t2) = edges((low + high) / cast(2.0));
+    t0 / (t2 - t1)
+}
 
-    #[inline]
-    pub fn update(&mut self, new_coefficients: IIR2Coefficients) {
-        self.coeffs = new_coefficients;
+/// Q of a shelf with slope S, using the cookbook relation 1/Q = sqrt((A + 1/A)(1/S - 1) + 2).
+/// S = 1 is the steepest slope without overshoot, Q = 1/sqrt(2) for any gain, and smaller S make
+/// the transition more gradual. S is clamped to [0.01, 1], so the response is always monotonic.
+pub fn q_from_slope<T: Float>(gain_db: T, slope: T) -> T {
+    let a = cast::<T>(10.0).powf(sanitize_gain(gain_db) / cast(40.0));
+    let slope = slope.max(cast(0.01)).min(T::one());
+    let inverse_q = ((a + T::one() / a) * (T::one() / slope - T::one()) + cast(2.0)).sqrt();
+    T::one() / inverse_q
+}
+
+/// Bandwidth in octaves between the band edges of a bell, notch or band pass with q_value, the
+/// inverse of `q_from_bandwidth`
+pub fn bandwidth_from_q<T: Float>(cutoff_hz: T, q_value: T, sample_rate_hz: T) -> T {
+    let cutoff_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+    let t0 = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+    let half_width = t0 / (cast::<T>(2.0) * q_value);
+    let t2 = half_width + (half_width * half_width + t0 * t0).sqrt();
+    let t1 = t0 * t0 / t2;
+    (t2.atan() / t1.atan()).log2()
+}
+
+/// Defines `try_*` versions of the constructors that validate their parameters
+macro_rules! checked_constructors {
+    ($($checked:ident => $constructor:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Like `", stringify!($constructor), "`, but returns an error for invalid parameters instead of sanitizing them")]
+            pub fn $checked(
+                cutoff_hz: T,
+                gain_db: T,
+                q_value: T,
+                sample_rate_hz: T,
+            ) -> Result<IIR2Coefficients<T>, FilterDesignError> {
+                check_design(cutoff_hz, gain_db, sample_rate_hz)?;
+                check_q(q_value)?;
+                Ok(IIR2Coefficients::$constructor(cutoff_hz, gain_db, q_value, sample_rate_hz))
+            }
+        )*
+    };
+}
+
+/// Checked constructors. The plain constructors accept anything and never produce non-finite
+/// coefficients: the cutoff is clamped like `clamp_cutoff` with NaN becoming `MIN_CUTOFF_HZ`, Q
+/// is raised to `MIN_Q`, the gain is limited to `MAX_GAIN_DB` with NaN becoming 0 dB, and an
+/// unusable sample rate gives `bypass`. Use these to reject bad user input instead.
+impl<T: Float> IIR2Coefficients<T> {
+    /// Like `new`, but returns an error for invalid parameters instead of sanitizing them
+    pub fn try_new(
+        filter_type: IIR2FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Result<IIR2Coefficients<T>, FilterDesignError> {
+        check_design(cutoff_hz, gain_db, sample_rate_hz)?;
+        check_q(q_value)?;
+        Ok(IIR2Coefficients::new(
+            filter_type,
+            cutoff_hz,
+            gain_db,
+            q_value,
+            sample_rate_hz,
+        ))
     }
+
+    checked_constructors!(
+        try_lowpass => lowpass,
+        try_highpass => highpass,
+        try_bandpass => bandpass,
+        try_notch => notch,
+        try_allpass => allpass,
+        try_lowshelf => lowshelf,
+        try_highshelf => highshelf,
+        try_bell => bell,
+        try_constant_q_bell => constant_q_bell,
+        try_tilt => tilt,
+    );
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Points between DC and Nyquist the matched designs minimize their worst error on
+const MATCH_POINTS: usize = 64;
 
-    fn rand(x: f32) -> f32 {
-        ((x * 12.9898).sin() * 43758.5453).fract()
+/// Poles of a matched design, placed by the impulse invariant transform. The squared magnitude
+/// of a biquad numerator or denominator is B0 phi0 + B1 phi1 + B2 phi2 with phi1 = sin^2(w / 2),
+/// phi0 = 1 - phi1 and phi2 = 4 phi0 phi1, which is linear in the B, so the zeros are picked in
+/// that form and factored at the end.
+struct MatchedPoles<T> {
+    a1: T,
+    a2: T,
+    big_a: [T; 3],
+    w0: T,
+}
+
+impl<T: Float> MatchedPoles<T> {
+    fn new(cutoff_hz: T, q_value: T, sample_rate_hz: T) -> Self {
+        let w0 = cast::<T>(TAU) * cutoff_hz / sample_rate_hz;
+        let zeta = T::one() / (cast::<T>(2.0) * q_value);
+        let decay = (-zeta * w0).exp();
+        let a1 = if zeta <= T::one() {
+            -cast::<T>(2.0) * decay * ((T::one() - zeta * zeta).sqrt() * w0).cos()
+        } else {
+            -cast::<T>(2.0) * decay * ((zeta * zeta - T::one()).sqrt() * w0).cosh()
+        };
+        let a2 = decay * decay;
+        MatchedPoles {
+            a1,
+            a2,
+            big_a: [
+                (T::one() + a1 + a2).powi(2),
+                (T::one() - a1 + a2).powi(2),
+                -cast::<T>(4.0) * a2,
+            ],
+            w0,
+        }
     }
 
-    #[test]
-    fn test_iir2() {
-        let mut audio: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
+    fn squared(big: &[T; 3], w: T) -> T {
+        let phi1 = (w / cast(2.0)).sin().powi(2);
+        let phi0 = T::one() - phi1;
+        big[0] * phi0 + big[1] * phi1 + big[2] * cast(4.0) * phi0 * phi1
+    }
 
-        let sample_rate_hz = 48000.0;
-        let cutoff_hz = 1000.0;
-        let gain_db = 6.0;
-        let q_value = 1.0;
+    /// Whether B0 phi0 + B1 phi1 + B2 phi2 is non-negative everywhere, as it must be to be the
+    /// squared magnitude of an actual numerator. As a quadratic in phi1 it can only dip below
+    /// zero between DC and Nyquist when B2 is negative.
+    fn is_realizable(big_b: &[T; 3]) -> bool {
+        let [big_b0, big_b1, big_b2] = *big_b;
+        if !(big_b0 >= T::zero() && big_b1 >= T::zero()) {
+            return false;
+        }
+        if big_b2 >= T::zero() {
+            return true;
+        }
+        let four = cast::<T>(4.0);
+        let vertex = (big_b1 - big_b0 + four * big_b2) / (cast::<T>(2.0) * four * big_b2);
+        if !(vertex > T::zero() && vertex < T::one()) {
+            return true;
+        }
+        big_b0 + (big_b1 - big_b0 + four * big_b2) * vertex - four * big_b2 * vertex * vertex
+            >= T::zero()
+    }
 
-        let coeffs = IIR2Coefficients::highshelf(cutoff_hz, gain_db, q_value, sample_rate_hz);
+    /// Numerator with squared magnitude dc at DC, cutoff at the cutoff and nyquist at Nyquist
+    fn zeros(&self, dc: T, cutoff: T, nyquist: T) -> [T; 3] {
+        let big_b0 = self.big_a[0] * dc;
+        let big_b1 = self.big_a[1] * nyquist;
+        let partial = MatchedPoles::squared(&[big_b0, big_b1, T::zero()], self.w0);
+        let phi2 = self.w0.sin().powi(2);
+        let big_b2 = (MatchedPoles::squared(&self.big_a, self.w0) * cutoff - partial) / phi2;
+        [big_b0, big_b1, big_b2]
+    }
 
-        let mut filter = IIR2::from(coeffs);
+    /// Matches the analog squared magnitude, a function of the frequency relative to the cutoff,
+    /// exactly at DC and the cutoff. The gain at Nyquist is left free and chosen by a golden
+    /// section search to minimize the worst error in dB, as matching it exactly too leaves up to
+    /// a dB of error just below Nyquist.
+    fn fit(&self, analog: impl Fn(T) -> T) -> Option<IIR2Coefficients<T>> {
+        let half_turn = cast::<T>(PI);
+        let (dc, cutoff) = (analog(T::zero()), analog(T::one()));
+        let nyquist = analog(half_turn / self.w0);
+        let worst_error = |log_scale: T| {
+            let big_b = self.zeros(dc, cutoff, nyquist * log_scale.exp());
+            if !MatchedPoles::is_realizable(&big_b) {
+                return T::infinity();
+            }
+            (1..=MATCH_POINTS).fold(T::zero(), |worst, i| {
+                let w = half_turn * cast(i as f64 / MATCH_POINTS as f64);
+                let digital =
+                    MatchedPoles::squared(&big_b, w) / MatchedPoles::squared(&self.big_a, w);
+                let error = (digital / analog(w / self.w0)).ln().abs();
+                if error.is_nan() {
+                    T::infinity()
+                } else {
+                    worst.max(error)
+                }
+            })
+        };
 
-        for i in 0..1000 {
-            audio[i] = filter.process(audio[i]);
+        let inverse_phi = cast::<T>(0.618_033_988_749_894_8);
+        let (mut low, mut high) = (-cast::<T>(2.0), cast::<T>(2.0));
+        for _ in 0..40 {
+            let a = high - (high - low) * inverse_phi;
+            let b = low + (high - low) * inverse_phi;
+            if worst_error(a) < worst_error(b) {
+                high = b;
+            } else {
+                low = a;
+            }
         }
 
-        assert_eq!(audio[500], -0.5090322)
+        self.factor(self.zeros(dc, cutoff, nyquist * ((low + high) / cast(2.0)).exp()))
+    }
+
+    /// Peaking numerator matched to an analog response with its maximum or minimum of gainpow2
+    /// at the cutoff. Besides the level at DC and the cutoff, the slope of the squared magnitude
+    /// is matched to zero at the cutoff, which fixes the bandwidth.
+    fn peak_fit(&self, gainpow2: T) -> Option<IIR2Coefficients<T>> {
+        let four = cast::<T>(4.0);
+        let [big_a0, big_a1, big_a2] = self.big_a;
+        let phi1 = (self.w0 / cast(2.0)).sin().powi(2);
+        let phi0 = T::one() - phi1;
+        let r1 = MatchedPoles::squared(&self.big_a, self.w0) * gainpow2;
+        let r2 = (big_a1 - big_a0 + four * (phi0 - phi1) * big_a2) * gainpow2;
+        let big_b0 = big_a0;
+        let big_b2 = (r1 - r2 * phi1 - big_b0) / (four * phi1 * phi1);
+        let big_b1 = r2 + big_b0 + four * (phi1 - phi0) * big_b2;
+        self.factor([big_b0, big_b1, big_b2])
+    }
+
+    /// Factors B back into b0 + b1 + b2 = sqrt(B0), b0 - b1 + b2 = sqrt(B1) and -4 b0 b2 = B2
+    fn factor(&self, big_b: [T; 3]) -> Option<IIR2Coefficients<T>> {
+        let [big_b0, big_b1, big_b2] = big_b;
+        let (root_b0, root_b1) = (big_b0.max(T::zero()).sqrt(), big_b1.max(T::zero()).sqrt());
+        let even = (root_b0 + root_b1) / cast(2.0);
+        let b0 = (even + (even * even + big_b2).max(T::zero()).sqrt()) / cast(2.0);
+        let b1 = (root_b0 - root_b1) / cast(2.0);
+        let b2 = if b0 > T::zero() {
+            -big_b2 / (cast::<T>(4.0) * b0)
+        } else {
+            T::zero()
+        };
+        self.coefficients(b0, b1, b2)
+    }
+
+    fn coefficients(&self, b0: T, b1: T, b2: T) -> Option<IIR2Coefficients<T>> {
+        IIR2Coefficients::from_biquad(b0, b1, b2, self.a1, self.a2)
+    }
+}
+
+/// Matched designs after M. Vicanek, "Matched Second Order Digital Filters" (2016). The
+/// bilinear transform squeezes the whole analog response below Nyquist, so high cutoffs come out
+/// narrower than the analog prototype and low passes are forced to zero at Nyquist. These place
+/// the poles with the impulse invariant transform and pick the zeros so the magnitude tracks the
+/// analog one all the way up to Nyquist. The phase is not matched. Designing one takes a short
+/// search, so they are more expensive to construct than the bilinear versions. The biquads are
+/// mapped back onto the SVF with `from_biquad`, so they run on `IIR2` like any other coefficients.
+///
+/// The design always runs in f64 and only the resulting SVF coefficients are rounded to T, as
+/// the poles of low cutoffs sit so close to z = 1 that an f32 fit is off by tens of dB. They
+/// return `None` when the sample rate is unusable or the fitted biquad is not stable.
+impl<T: Float> IIR2Coefficients<T> {
+    /// Low pass matched to the analog 1 / (s^2 + s / Q + 1)
+    pub fn lowpass_matched(
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Option<IIR2Coefficients<T>> {
+        let sample_rate_hz = sample_rate_hz.to_f64()?;
+        let cutoff_hz = sanitize_cutoff(cutoff_hz.to_f64()?, sample_rate_hz)?;
+        let gainpow2 = 10.0f64.powf(sanitize_gain(gain_db).to_f64()? / 10.0);
+        let q_value = sanitize_q(q_value).to_f64()?;
+        let k = 1.0 / q_value;
+        MatchedPoles::new(cutoff_hz, q_value, sample_rate_hz)
+            .fit(|omega| gainpow2 / ((1.0 - omega * omega).powi(2) + (omega * k).powi(2)))
+            .map(IIR2Coefficients::from_f64)
+    }
+
+    /// High pass matched to the analog s^2 / (s^2 + s / Q + 1). The double zero at DC leaves only
+    /// the overall gain free, which is set to match at the cutoff.
+    pub fn highpass_matched(
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Option<IIR2Coefficients<T>> {
+        let sample_rate_hz = sample_rate_hz.to_f64()?;
+        let cutoff_hz = sanitize_cutoff(cutoff_hz.to_f64()?, sample_rate_hz)?;
+        let gain = 10.0f64.powf(sanitize_gain(gain_db).to_f64()? / 20.0);
+        let q_value = sanitize_q(q_value).to_f64()?;
+        let poles = MatchedPoles::new(cutoff_hz, q_value, sample_rate_hz);
+        let phi1 = (poles.w0 / 2.0).sin().powi(2);
+        let b0 = MatchedPoles::squared(&poles.big_a, poles.w0).sqrt() * q_value / (4.0 * phi1);
+        poles
+            .coefficients(gain * b0, -2.0 * gain * b0, gain * b0)
+            .map(IIR2Coefficients::from_f64)
+    }
+
+    /// Bell matched to the same analog prototype as `bell`, with the same gain and Q
+    pub fn bell_matched(
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Option<IIR2Coefficients<T>> {
+        let sample_rate_hz = sample_rate_hz.to_f64()?;
+        let cutoff_hz = sanitize_cutoff(cutoff_hz.to_f64()?, sample_rate_hz)?;
+        let gain_db = sanitize_gain(gain_db).to_f64()?;
+        let q_value = sanitize_q(q_value).to_f64()?;
+        // `bell` puts its poles at Q * A and its zeros at Q / A, for a gain of A^2 at the cutoff
+        let a = 10.0f64.powf(gain_db / 40.0);
+        MatchedPoles::new(cutoff_hz, q_value * a, sample_rate_hz)
+            .peak_fit(a.powi(4))
+            .map(IIR2Coefficients::from_f64)
+    }
+
+    /// Rounds coefficients designed in f64 to T
+    pub(crate) fn from_f64(coeffs: IIR2Coefficients<f64>) -> IIR2Coefficients<T> {
+        IIR2Coefficients {
+            a: cast(coeffs.a),
+            g: cast(coeffs.g),
+            gpow2: cast(coeffs.gpow2),
+            k: cast(coeffs.k),
+            a1: cast(coeffs.a1),
+            a2: cast(coeffs.a2),
+            a3: cast(coeffs.a3),
+            m0: cast(coeffs.m0),
+            m1: cast(coeffs.m1),
+            m2: cast(coeffs.m2),
+        }
+    }
+}
+
+/// Constructors with the cutoff given as a MIDI note, see `units`, so a keyboard tracking filter
+/// is one call per note. The plain versions tune A4 to 440 Hz, the `_tuned` ones to a4_hz.
+impl<T: Float> IIR2Coefficients<T> {
+    #[inline]
+    pub fn lowpass_note(note: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        IIR2Coefficients::lowpass(note_to_hz(note), T::zero(), q_value, sample_rate_hz)
+    }
+
+    #[inline]
+    pub fn lowpass_note_tuned(
+        note: T,
+        a4_hz: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let cutoff_hz = note_to_hz_tuned(note, a4_hz);
+        IIR2Coefficients::lowpass(cutoff_hz, T::zero(), q_value, sample_rate_hz)
+    }
+
+    #[inline]
+    pub fn highpass_note(note: T, q_value: T, sample_rate_hz: T) -> IIR2Coefficients<T> {
+        IIR2Coefficients::highpass(note_to_hz(note), T::zero(), q_value, sample_rate_hz)
+    }
+
+    #[inline]
+    pub fn highpass_note_tuned(
+        note: T,
+        a4_hz: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> IIR2Coefficients<T> {
+        let cutoff_hz = note_to_hz_tuned(note, a4_hz);
+        IIR2Coefficients::highpass(cutoff_hz, T::zero(), q_value, sample_rate_hz)
+    }
+}
+
+/// Sample rate the responses for `Display` of `IIR2Coefficients` are measured at. The peak is
+/// reported relative to the sample rate, so the value only sets how low the search goes.
+const DISPLAY_SAMPLE_RATE_HZ: f64 = 1_000_000.0;
+
+/// Without the design parameters, describes the response by its DC gain, peak and Nyquist gain,
+/// like `DC +0.0 dB, peak +6.0 dB at 0.02083 fs, Nyquist +0.0 dB`. The peak frequency is a
+/// fraction of the sample rate, which the coefficients do not know.
+impl<T: Float> fmt::Display for IIR2Coefficients<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sample_rate_hz = cast::<T>(DISPLAY_SAMPLE_RATE_HZ);
+        let db = |frequency_hz: T| {
+            let magnitude = self.get_bode_sample(frequency_hz, sample_rate_hz).norm();
+            (cast::<T>(20.0) * magnitude.log10())
+                .to_f64()
+                .unwrap_or(f64::NAN)
+        };
+        write!(f, "DC {:+.1} dB, ", db(T::zero()))?;
+        match self.peak_frequency_hz(sample_rate_hz) {
+            Some(peak_hz) => {
+                let ratio = (peak_hz / sample_rate_hz).to_f64().unwrap_or(f64::NAN);
+                write!(f, "peak {:+.1} dB at {:.5} fs, ", db(peak_hz), ratio)?
+            }
+            None => write!(f, "no peak, ")?,
+        }
+        write!(f, "Nyquist {:+.1} dB", db(sample_rate_hz / cast(2.0)))
+    }
+}
+
+/// The parameters a set of `IIR2Coefficients` was designed from
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR2Design<T = f32> {
+    pub filter_type: IIR2FilterType,
+    pub cutoff_hz: T,
+    pub gain_db: T,
+    pub q_value: T,
+    pub sample_rate_hz: T,
+}
+
+impl<T: Float> IIR2Design<T> {
+    #[inline]
+    pub fn coefficients(&self) -> IIR2Coefficients<T> {
+        IIR2Coefficients::new(
+            self.filter_type,
+            self.cutoff_hz,
+            self.gain_db,
+            self.q_value,
+            self.sample_rate_hz,
+        )
+    }
+}
+
+/// Coefficients that remember their design parameters, so they can be displayed, saved and
+/// rebuilt for another sample rate. Derefs to the raw `IIR2Coefficients`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesignedIIR2<T = f32> {
+    design: IIR2Design<T>,
+    coeffs: IIR2Coefficients<T>,
+}
+
+impl<T: Float> DesignedIIR2<T> {
+    #[inline]
+    pub fn new(
+        filter_type: IIR2FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+    ) -> Self {
+        DesignedIIR2::from_design(IIR2Design {
+            filter_type,
+            cutoff_hz,
+            gain_db,
+            q_value,
+            sample_rate_hz,
+        })
+    }
+
+    #[inline]
+    pub fn from_design(design: IIR2Design<T>) -> Self {
+        DesignedIIR2 {
+            design,
+            coeffs: design.coefficients(),
+        }
+    }
+
+    #[inline]
+    pub fn design(&self) -> IIR2Design<T> {
+        self.design
+    }
+
+    #[inline]
+    pub fn coefficients(&self) -> IIR2Coefficients<T> {
+        self.coeffs
+    }
+
+    /// Redesigns the same filter for another sample rate. The cutoff is clamped against the new
+    /// Nyquist, but the stored design keeps the requested cutoff, so moving back restores it.
+    #[inline]
+    pub fn with_sample_rate(&self, sample_rate_hz: T) -> Self {
+        DesignedIIR2::from_design(IIR2Design {
+            sample_rate_hz,
+            ..self.design
+        })
+    }
+
+    /// Changes the gain, recomputing only the coefficients that depend on it. Pass filters only
+    /// rescale their mix, the bell and constant Q bell keep g and skip the `tan()`, while the
+    /// shelves and tilt scale g by the gain and are fully redesigned. The result is bit identical
+    /// to designing the filter from scratch.
+    pub fn set_gain_db(&mut self, gain_db: T) {
+        self.design.gain_db = gain_db;
+        if sanitize_cutoff(self.design.cutoff_hz, self.design.sample_rate_hz).is_none() {
+            return;
+        }
+        let gain_db = sanitize_gain(gain_db);
+        let coeffs = &mut self.coeffs;
+        let k = coeffs.k;
+        let gain = || cast::<T>(10.0).powf(gain_db / cast(20.0));
+        let shelf_a = || cast::<T>(10.0).powf(gain_db / cast(40.0));
+        match self.design.filter_type {
+            IIR2FilterType::LowPass => coeffs.m2 = gain(),
+            IIR2FilterType::HighPass => {
+                let gain = gain();
+                coeffs.m0 = gain;
+                coeffs.m1 = -k * gain;
+                coeffs.m2 = -gain;
+            }
+            IIR2FilterType::BandPass => coeffs.m1 = gain(),
+            IIR2FilterType::Notch => {
+                let gain = gain();
+                coeffs.m0 = gain;
+                coeffs.m1 = -k * gain;
+            }
+            IIR2FilterType::AllPass => {
+                let gain = gain();
+                coeffs.m0 = gain;
+                coeffs.m1 = -cast::<T>(2.0) * k * gain;
+            }
+            IIR2FilterType::Bell => {
+                let a = shelf_a();
+                let g = coeffs.g;
+                let k = T::one() / (sanitize_q(self.design.q_value) * a);
+                let a1 = T::one() / (T::one() + g * (g + k));
+                coeffs.a = a;
+                coeffs.k = k;
+                coeffs.a1 = a1;
+                coeffs.a2 = g * a1;
+                coeffs.a3 = g * coeffs.a2;
+                coeffs.m1 = k * (a * a - T::one());
+            }
+            IIR2FilterType::ConstantQBell => {
+                let a = shelf_a();
+                coeffs.a = a;
+                coeffs.m1 = k * (a * a - T::one());
+            }
+            IIR2FilterType::LowShelf | IIR2FilterType::HighShelf | IIR2FilterType::Tilt => {
+                *coeffs = self.design.coefficients();
+            }
+        }
+    }
+
+    /// Moves the cutoff, recomputing only g and the terms derived from it. a, k and the mix do
+    /// not depend on the cutoff for any type. The result is bit identical to designing the filter
+    /// from scratch.
+    pub fn set_cutoff(&mut self, cutoff_hz: T) {
+        self.design.cutoff_hz = cutoff_hz;
+        let Some(cutoff_hz) = sanitize_cutoff(cutoff_hz, self.design.sample_rate_hz) else {
+            return;
+        };
+        let coeffs = &mut self.coeffs;
+        let prewarped = (cast::<T>(PI) * cutoff_hz / self.design.sample_rate_hz).tan();
+        let g = match self.design.filter_type {
+            IIR2FilterType::LowShelf => prewarped / coeffs.a.sqrt(),
+            IIR2FilterType::HighShelf | IIR2FilterType::Tilt => prewarped * coeffs.a.sqrt(),
+            _ => prewarped,
+        };
+        let a1 = T::one() / (T::one() + g * (g + coeffs.k));
+        coeffs.g = g;
+        coeffs.gpow2 = g * g;
+        coeffs.a1 = a1;
+        coeffs.a2 = g * a1;
+        coeffs.a3 = g * coeffs.a2;
+    }
+}
+
+/// Writes the design parameters as given, like `Bell @ 1000 Hz, +6.0 dB, Q 1.00 (fs 48000)`, so
+/// a cutoff past Nyquist shows unclamped
+impl<T: Float> fmt::Display for IIR2Design<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |x: T| x.to_f64().unwrap_or(f64::NAN);
+        let cutoff_hz = value(self.cutoff_hz);
+        // Whole Hz are plenty above 100 Hz, but not for sub bass
+        let decimals = if cutoff_hz.abs() < 100.0 { 1 } else { 0 };
+        write!(
+            f,
+            "{} @ {:.*} Hz, {:+.1} dB, Q {:.2} (fs {:.0})",
+            self.filter_type,
+            decimals,
+            cutoff_hz,
+            value(self.gain_db),
+            value(self.q_value),
+            value(self.sample_rate_hz)
+        )
+    }
+}
+
+impl<T: Float> fmt::Display for DesignedIIR2<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.design.fmt(f)
+    }
+}
+
+impl<T> Deref for DesignedIIR2<T> {
+    type Target = IIR2Coefficients<T>;
+
+    fn deref(&self) -> &IIR2Coefficients<T> {
+        &self.coeffs
+    }
+}
+
+/// Simultaneous outputs of the SVF, see `IIR2::process_multi`
+#[derive(Copy, Clone, Debug)]
+pub struct SvfOutputs<T = f32> {
+    pub lowpass: T,
+    pub bandpass: T,
+    pub highpass: T,
+}
+
+/// Snapshot of the internal state of an `IIR2`
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IIR2State<T = f32> {
+    pub ic1eq: T,
+    pub ic2eq: T,
+}
+
+/// Internal states and coefficients of the SVF form
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2<T = f32> {
+    ic1eq: T,
+    ic2eq: T,
+    pub coeffs: IIR2Coefficients<T>,
+}
+
+/// The SVF step shared by every processing loop of `IIR2` and the filters built on it, so a
+/// change to the core arithmetic reaches all of them and they stay bit identical to each other
+impl<T: Float> IIR2Coefficients<T> {
+    /// Advances the integrator states ic1eq and ic2eq by one input sample and returns the band
+    /// pass and low pass outputs, v1 and v2
+    #[inline(always)]
+    pub(crate) fn tick(&self, ic1eq: &mut T, ic2eq: &mut T, input_sample: T) -> (T, T) {
+        let v3 = input_sample - *ic2eq;
+        let v1 = self.a1 * *ic1eq + self.a2 * v3;
+        let v2 = *ic2eq + self.a2 * *ic1eq + self.a3 * v3;
+        *ic1eq = flush_denormal(cast::<T>(2.0) * v1 - *ic1eq);
+        *ic2eq = flush_denormal(cast::<T>(2.0) * v2 - *ic2eq);
+        (v1, v2)
+    }
+
+    /// Mixes the input and the outputs of a `tick` into the filter output
+    #[inline(always)]
+    pub(crate) fn output(&self, input_sample: T, v1: T, v2: T) -> T {
+        self.m0 * input_sample + self.m1 * v1 + self.m2 * v2
+    }
+}
+
+impl<T: Float> IIR2<T> {
+    /// Creates a SVF from a set of filter coefficients
+    #[inline]
+    pub fn from(coefficients: IIR2Coefficients<T>) -> Self {
+        IIR2 {
+            ic1eq: T::zero(),
+            ic2eq: T::zero(),
+            coeffs: coefficients,
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let (v1, v2) = self
+            .coeffs
+            .tick(&mut self.ic1eq, &mut self.ic2eq, input_sample);
+        self.coeffs.output(input_sample, v1, v2)
+    }
+
+    /// Advances the state once and returns the lowpass, bandpass and highpass outputs of the SVF.
+    /// These only depend on the cutoff and Q of the coefficients, the m0/m1/m2 mix is ignored.
+    #[inline]
+    pub fn process_multi(&mut self, input_sample: T) -> SvfOutputs<T> {
+        let (v1, v2) = self
+            .coeffs
+            .tick(&mut self.ic1eq, &mut self.ic2eq, input_sample);
+        SvfOutputs {
+            lowpass: v2,
+            bandpass: v1,
+            highpass: input_sample - self.coeffs.k * v1 - v2,
+        }
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+        let mut ic2eq = self.ic2eq;
+
+        for sample in buffer.iter_mut() {
+            let input_sample = *sample;
+            let (v1, v2) = coeffs.tick(&mut ic1eq, &mut ic2eq, input_sample);
+            *sample = coeffs.output(input_sample, v1, v2);
+        }
+
+        self.ic1eq = ic1eq;
+        self.ic2eq = ic2eq;
+    }
+
+    /// Processes input into output, leaving input untouched. Output is identical to calling
+    /// `process` on each sample. Both can not be the same buffer, use `process_block` to process
+    /// in place.
+    ///
+    /// # Panics
+    ///
+    /// If input and output have different lengths.
+    #[inline]
+    pub fn process_to(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input and output lengths differ");
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+        let mut ic2eq = self.ic2eq;
+
+        for (&input_sample, sample) in input.iter().zip(output.iter_mut()) {
+            let (v1, v2) = coeffs.tick(&mut ic1eq, &mut ic2eq, input_sample);
+            *sample = coeffs.output(input_sample, v1, v2);
+        }
+
+        self.ic1eq = ic1eq;
+        self.ic2eq = ic2eq;
+    }
+
+    /// Filters input and adds the result times gain onto output_accum, for summing bands into a
+    /// mix buffer without a temporary one. The state advances the same as with `process`, also
+    /// when gain is 0.
+    ///
+    /// # Panics
+    ///
+    /// If input and output_accum have different lengths.
+    #[inline]
+    pub fn process_block_adding(&mut self, input: &[T], output_accum: &mut [T], gain: T) {
+        assert_eq!(
+            input.len(),
+            output_accum.len(),
+            "input and output lengths differ"
+        );
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+        let mut ic2eq = self.ic2eq;
+
+        for (&input_sample, sample) in input.iter().zip(output_accum.iter_mut()) {
+            let (v1, v2) = coeffs.tick(&mut ic1eq, &mut ic2eq, input_sample);
+            *sample = *sample + gain * coeffs.output(input_sample, v1, v2);
+        }
+
+        self.ic1eq = ic1eq;
+        self.ic2eq = ic2eq;
+    }
+
+    /// Processes a buffer in place, blending the filtered signal with the input by mix, 0 for
+    /// dry and 1 for fully filtered, and scaling the blend by output_gain. The state always
+    /// advances as if fully wet, so raising mix later continues without a discontinuity.
+    #[inline]
+    pub fn process_block_mix(&mut self, buffer: &mut [T], mix: T, output_gain: T) {
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+        let mut ic2eq = self.ic2eq;
+        let dry_gain = (T::one() - mix) * output_gain;
+        let wet_gain = mix * output_gain;
+
+        for sample in buffer.iter_mut() {
+            let input_sample = *sample;
+            let (v1, v2) = coeffs.tick(&mut ic1eq, &mut ic2eq, input_sample);
+            let wet = coeffs.output(input_sample, v1, v2);
+            *sample = dry_gain * input_sample + wet_gain * wet;
+        }
+
+        self.ic1eq = ic1eq;
+        self.ic2eq = ic2eq;
+    }
+
+    /// Like `process_block`, with the output passed through `soft_clip` in the same loop so it
+    /// stays within ±ceiling. Samples below `SOFT_CLIP_KNEE` times the ceiling are unchanged.
+    #[inline]
+    pub fn process_block_clipped(&mut self, buffer: &mut [T], ceiling: T) {
+        let coeffs = self.coeffs;
+        let mut ic1eq = self.ic1eq;
+        let mut ic2eq = self.ic2eq;
+
+        for sample in buffer.iter_mut() {
+            let input_sample = *sample;
+            let (v1, v2) = coeffs.tick(&mut ic1eq, &mut ic2eq, input_sample);
+            *sample = soft_clip(coeffs.output(input_sample, v1, v2), ceiling);
+        }
+
+        self.ic1eq = ic1eq;
+        self.ic2eq = ic2eq;
+    }
+
+    /// Processes a buffer in place, switching to the coefficients of each event exactly at its
+    /// sample offset, for sample accurate host automation. Events must be sorted by offset, and
+    /// several at the same offset leave the last one in effect. The coefficients in effect at the
+    /// end stay for the next block. Nothing is processed if an offset is out of order or not
+    /// inside the buffer.
+    pub fn process_block_with_events(
+        &mut self,
+        buffer: &mut [T],
+        events: &[(usize, IIR2Coefficients<T>)],
+    ) -> Result<(), EventOffsetError> {
+        let mut last_offset = 0;
+        for &(offset, _) in events {
+            if offset >= buffer.len() {
+                return Err(EventOffsetError::OutOfRange);
+            }
+            if offset < last_offset {
+                return Err(EventOffsetError::Unsorted);
+            }
+            last_offset = offset;
+        }
+
+        let mut start = 0;
+        for &(offset, coefficients) in events {
+            self.process_block(&mut buffer[start..offset]);
+            self.update(coefficients);
+            start = offset;
+        }
+        self.process_block(&mut buffer[start..]);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn update(&mut self, new_coefficients: IIR2Coefficients<T>) {
+        self.coeffs = new_coefficients;
+    }
+
+    /// Clears the internal state, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        self.ic1eq = T::zero();
+        self.ic2eq = T::zero();
+    }
+
+    #[inline]
+    pub fn state(&self) -> IIR2State<T> {
+        IIR2State {
+            ic1eq: self.ic1eq,
+            ic2eq: self.ic2eq,
+        }
+    }
+
+    /// Restores a state saved with `state`, processing continues exactly where it left off
+    #[inline]
+    pub fn set_state(&mut self, state: IIR2State<T>) {
+        self.ic1eq = state.ic1eq;
+        self.ic2eq = state.ic2eq;
+    }
+
+    /// Resets the state if it has become NaN or infinite, returning true if it did.
+    /// A single non-finite input would otherwise keep the filter outputting NaN forever.
+    #[inline]
+    pub fn check_and_recover(&mut self) -> bool {
+        if self.ic1eq.is_finite() && self.ic2eq.is_finite() {
+            false
+        } else {
+            self.reset();
+            true
+        }
+    }
+
+    /// Like `process_block`, but checks the state once at the end of the block. If it had to be
+    /// recovered the buffer is silenced, as it contains non-finite samples, and true is returned.
+    #[inline]
+    pub fn process_block_protected(&mut self, buffer: &mut [T]) -> bool {
+        self.process_block(buffer);
+        let recovered = self.check_and_recover();
+        if recovered {
+            buffer.fill(T::zero());
+        }
+        recovered
+    }
+
+    /// Sets the state the filter reaches after processing dc_value forever, so starting on a
+    /// constant signal gives no transient. At DC the band pass integrator settles to zero and the
+    /// low pass integrator to the input.
+    #[inline]
+    pub fn settle_to_dc(&mut self, dc_value: T) {
+        self.ic1eq = T::zero();
+        self.ic2eq = dc_value;
+    }
+
+    /// Processes one sample with the cutoff moved to cutoff_hz, keeping k and the mix of the
+    /// current coefficients. Only valid for types whose g is the plain prewarped cutoff: low pass,
+    /// high pass, band pass, notch, all pass, bell and constant Q bell. The shelves and tilt scale
+    /// g by the gain and would end up with a shifted cutoff.
+    #[inline]
+    pub fn process_modulated(&mut self, input_sample: T, cutoff_hz: T, sample_rate_hz: T) -> T {
+        let cutoff_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+        let g = (cast::<T>(PI) * cutoff_hz / sample_rate_hz).tan();
+        self.process_modulated_g(input_sample, g)
+    }
+
+    /// Like `process_modulated` but uses `fast_prewarp` instead of the exact tan
+    #[inline]
+    pub fn process_modulated_fast(
+        &mut self,
+        input_sample: T,
+        cutoff_hz: T,
+        sample_rate_hz: T,
+    ) -> T {
+        self.process_modulated_g(input_sample, fast_prewarp(cutoff_hz, sample_rate_hz))
+    }
+
+    /// Like `process_modulated` but takes a precomputed g = tan(PI * cutoff_hz / sample_rate_hz)
+    #[inline]
+    pub fn process_modulated_g(&mut self, input_sample: T, g: T) -> T {
+        let coeffs = &mut self.coeffs;
+        coeffs.g = g;
+        coeffs.gpow2 = g * g;
+        coeffs.a1 = T::one() / (T::one() + g * (g + coeffs.k));
+        coeffs.a2 = g * coeffs.a1;
+        coeffs.a3 = g * coeffs.a2;
+        self.process(input_sample)
+    }
+
+    /// Response of the current coefficients to a unit impulse, starting from a cleared state.
+    /// The state of this filter is not touched.
+    pub fn impulse_response(&self, length: usize) -> Vec<T> {
+        let mut filter = IIR2::from(self.coeffs);
+        (0..length)
+            .map(|i| filter.process(if i == 0 { T::one() } else { T::zero() }))
+            .collect()
+    }
+
+    /// Response of the current coefficients to a unit step, starting from a cleared state.
+    /// The state of this filter is not touched.
+    pub fn step_response(&self, length: usize) -> Vec<T> {
+        let mut filter = IIR2::from(self.coeffs);
+        (0..length).map(|_| filter.process(T::one())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::Biquad;
+    use crate::filter::Filter;
+    use crate::first_order_iir::{IIR1Coefficients, IIR1};
+    use crate::log_frequencies;
+    use alloc::boxed::Box;
+
+    #[allow(clippy::excessive_precision)]
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43758.5453).fract()
+    }
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
+    fn test_iir2() {
+        let mut audio: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
+
+        let sample_rate_hz = 48000.0;
+        let cutoff_hz = 1000.0;
+        let gain_db = 6.0;
+        let q_value = 1.0;
+
+        let coeffs = IIR2Coefficients::highshelf(cutoff_hz, gain_db, q_value, sample_rate_hz);
+
+        let mut filter = IIR2::from(coeffs);
+
+        for i in 0..1000 {
+            audio[i] = filter.process(audio[i]);
+        }
+
+        assert_eq!(audio[500], -0.5090322)
+    }
+
+    #[test]
+    fn test_iir2_f64() {
+        let coeffs32 = IIR2Coefficients::<f32>::bell(1000.0, 6.0, 1.0, 48000.0);
+        let coeffs64 = IIR2Coefficients::<f64>::bell(1000.0, 6.0, 1.0, 48000.0);
+        let mut filter32 = IIR2::from(coeffs32);
+        let mut filter64 = IIR2::from(coeffs64);
+
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            let y32 = filter32.process(x);
+            let y64 = filter64.process(x as f64);
+            assert!((y32 as f64 - y64).abs() < 1e-5);
+        }
+
+        let bode32 = coeffs32.get_bode_sample(1000.0, 48000.0).norm();
+        let bode64 = coeffs64.get_bode_sample(1000.0, 48000.0).norm();
+        assert!((bode32 as f64 - bode64).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_iir2_process_block() {
+        let audio: Vec<f32> = (0..4096).map(|x| rand(x as f32)).collect();
+
+        let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+
+        let mut scalar_filter = IIR2::from(coeffs);
+        let expected: Vec<f32> = audio.iter().map(|&x| scalar_filter.process(x)).collect();
+
+        let mut block_filter = IIR2::from(coeffs);
+        let mut block = audio.clone();
+        block_filter.process_block(&mut block[..0]);
+        block_filter.process_block(&mut block[..1000]);
+        block_filter.process_block(&mut block[1000..]);
+
+        assert_eq!(block, expected);
+        assert_eq!(block_filter.process(1.0), scalar_filter.process(1.0));
+    }
+
+    #[test]
+    fn test_iir2_process_to() {
+        let audio: Vec<f32> = (0..4096).map(|x| rand(x as f32)).collect();
+
+        let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+
+        let mut scalar_filter = IIR2::from(coeffs);
+        let expected: Vec<f32> = audio.iter().map(|&x| scalar_filter.process(x)).collect();
+
+        let mut block_filter = IIR2::from(coeffs);
+        let mut output = vec![0.0; audio.len()];
+        block_filter.process_to(&audio[..1000], &mut output[..1000]);
+        block_filter.process_to(&audio[1000..], &mut output[1000..]);
+
+        assert_eq!(output, expected);
+        assert_eq!(block_filter.process(1.0), scalar_filter.process(1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "input and output lengths differ")]
+    fn test_iir2_process_to_length_mismatch() {
+        let mut filter = IIR2::from(IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0));
+        filter.process_to(&[0.0; 64], &mut [0.0; 63]);
+    }
+
+    #[test]
+    fn test_iir2_process_block_adding() {
+        let audio: Vec<f32> = (0..2048).map(|x| rand(x as f32)).collect();
+        let low = IIR2Coefficients::lowpass(500.0, 0.0, 0.707, 48000.0);
+        let high = IIR2Coefficients::highpass(500.0, 0.0, 0.707, 48000.0);
+
+        let mut low_filter = IIR2::from(low);
+        let mut high_filter = IIR2::from(high);
+        let mut mix = vec![0.0; audio.len()];
+        low_filter.process_block_adding(&audio, &mut mix, 0.5);
+        high_filter.process_block_adding(&audio, &mut mix, 2.0);
+
+        let (mut low_buffer, mut high_buffer) = (audio.clone(), audio.clone());
+        IIR2::from(low).process_block(&mut low_buffer);
+        IIR2::from(high).process_block(&mut high_buffer);
+        for i in 0..audio.len() {
+            assert!((mix[i] - (0.5 * low_buffer[i] + 2.0 * high_buffer[i])).abs() < 1e-6);
+        }
+
+        // A silent band leaves the mix alone but keeps running
+        let mut silent = IIR2::from(low);
+        let before = mix.clone();
+        silent.process_block_adding(&audio, &mut mix, 0.0);
+        assert_eq!(mix, before);
+        let mut reference = IIR2::from(low);
+        reference.process_block(&mut audio.clone());
+        assert_eq!(silent.process(1.0), reference.process(1.0));
+    }
+
+    #[test]
+    fn test_iir2_process_block_mix() {
+        let audio: Vec<f32> = (0..2048).map(|x| rand(x as f32)).collect();
+        let coeffs = IIR2Coefficients::lowpass(800.0, 0.0, 1.0, 48000.0);
+        let mut wet = audio.clone();
+        IIR2::from(coeffs).process_block(&mut wet);
+
+        let mut filter = IIR2::from(coeffs);
+        let mut mixed = audio.clone();
+        filter.process_block_mix(&mut mixed, 0.25, 2.0);
+        for i in 0..audio.len() {
+            let expected = 2.0 * (0.75 * audio[i] + 0.25 * wet[i]);
+            assert!((mixed[i] - expected).abs() < 1e-6);
+        }
+
+        // Fully dry passes the input, but the state keeps following the wet signal, so turning
+        // the mix up halfway gives exactly the filtered signal from there on
+        let mut filter = IIR2::from(coeffs);
+        let mut mixed = audio.clone();
+        filter.process_block_mix(&mut mixed[..1000], 0.0, 1.0);
+        filter.process_block_mix(&mut mixed[1000..], 1.0, 1.0);
+        assert_eq!(mixed[..1000], audio[..1000]);
+        for i in 1000..audio.len() {
+            assert!((mixed[i] - wet[i]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_iir2_process_block_clipped() {
+        let fs = 48000.0;
+        let sine = |amplitude: f64| -> Vec<f64> {
+            (0..4800)
+                .map(|i| amplitude * (core::f64::consts::TAU * 1000.0 * i as f64 / fs).sin())
+                .collect()
+        };
+
+        // A +20 dB boost on a full scale sine would reach 10
+        let boost = IIR2Coefficients::bell(1000.0, 20.0, 1.0, fs);
+        let mut buffer = sine(1.0);
+        IIR2::from(boost).process_block_clipped(&mut buffer, 1.0);
+        assert!(buffer.iter().all(|y| y.abs() <= 1.0));
+        assert!(buffer[2400..].iter().any(|y| y.abs() > 0.99));
+
+        // -20 dBFS through +6 dB stays below the knee and passes unchanged
+        let bell = IIR2Coefficients::bell(1000.0, 6.0, 1.0, fs);
+        let mut clipped = sine(0.1);
+        IIR2::from(bell).process_block_clipped(&mut clipped, 1.0);
+        let mut plain = sine(0.1);
+        IIR2::from(bell).process_block(&mut plain);
+        assert_eq!(clipped, plain);
+
+        // The trait default and the IIR1 loop clip the same way
+        let mut through_trait = sine(1.0);
+        let mut generic: Box<dyn Filter<Sample = f64>> = Box::new(Biquad::from_svf(boost));
+        generic.process_block_clipped(&mut through_trait, 0.8);
+        let mut reference = sine(1.0);
+        Biquad::from_svf(boost).process_block(&mut reference);
+        for (clipped, y) in through_trait.iter().zip(&reference) {
+            assert_eq!(*clipped, soft_clip(*y, 0.8));
+        }
+        let mut first_order = sine(1.0);
+        IIR1::from(IIR1Coefficients::highshelf(100.0, 20.0, fs))
+            .process_block_clipped(&mut first_order, 0.5);
+        assert!(first_order.iter().all(|y| y.abs() <= 0.5));
+    }
+
+    #[test]
+    fn test_iir2_process_block_with_events() {
+        let fs = 48000.0;
+        let audio: Vec<f32> = (0..1024).map(|x| rand(x as f32)).collect();
+        let start = IIR2Coefficients::lowpass(500.0, 0.0, 0.7, fs);
+        let events = [
+            (0, IIR2Coefficients::lowpass(800.0, 0.0, 0.7, fs)),
+            (100, IIR2Coefficients::bell(2000.0, 9.0, 2.0, fs)),
+            (100, IIR2Coefficients::notch(3000.0, 0.0, 1.0, fs)),
+            (517, IIR2Coefficients::highpass(40.0, 0.0, 0.7, fs)),
+            (1023, start),
+        ];
+
+        let mut expected = audio.clone();
+        let mut reference = IIR2::from(start);
+        let bounds = [0, 100, 517, 1023, 1024];
+        let segment_coeffs = [events[0].1, events[2].1, events[3].1, events[4].1];
+        for (range, coeffs) in bounds.windows(2).zip(segment_coeffs) {
+            reference.update(coeffs);
+            reference.process_block(&mut expected[range[0]..range[1]]);
+        }
+
+        let mut filter = IIR2::from(start);
+        let mut buffer = audio.clone();
+        assert_eq!(
+            filter.process_block_with_events(&mut buffer, &events),
+            Ok(())
+        );
+        assert_eq!(buffer, expected);
+        assert_eq!(filter.state(), reference.state());
+
+        // Rejected events leave the buffer and the filter alone
+        let mut buffer = audio.clone();
+        let unsorted = [events[3], events[1]];
+        let out_of_range = [events[0], (1024, start)];
+        assert_eq!(
+            filter.process_block_with_events(&mut buffer, &unsorted),
+            Err(EventOffsetError::Unsorted)
+        );
+        assert_eq!(
+            filter.process_block_with_events(&mut buffer, &out_of_range),
+            Err(EventOffsetError::OutOfRange)
+        );
+        assert_eq!(buffer, audio);
+        assert_eq!(filter.state(), reference.state());
+    }
+
+    #[test]
+    fn test_iir2_process_multi() {
+        let sample_rate_hz = 48000.0;
+        let mut lowpass = IIR2::from(IIR2Coefficients::lowpass(1000.0, 0.0, 2.0, sample_rate_hz));
+        let mut highpass = IIR2::from(IIR2Coefficients::highpass(1000.0, 0.0, 2.0, sample_rate_hz));
+        let mut bandpass = IIR2::from(IIR2Coefficients::bandpass(1000.0, 0.0, 2.0, sample_rate_hz));
+        let mut multi = IIR2::from(lowpass.coeffs);
+
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            let outputs = multi.process_multi(x);
+            assert!((outputs.lowpass - lowpass.process(x)).abs() < 1e-6);
+            assert!((outputs.highpass - highpass.process(x)).abs() < 1e-6);
+            assert!((outputs.bandpass - bandpass.process(x)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_iir2_bypass() {
+        let coeffs = IIR2Coefficients::bypass();
+        let mut filter = IIR2::from(coeffs);
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            assert_eq!(filter.process(x), x);
+        }
+        for &f in &[0.0, 1000.0, 24000.0] {
+            assert_eq!(coeffs.get_bode_sample(f, 48000.0).norm(), 1.0);
+        }
+
+        let mut empty = IIR2::from(IIR2Coefficients::empty());
+        assert_eq!(empty.process(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_iir2_impulse_response() {
+        let coefficients = [
+            IIR2Coefficients::lowpass(1000.0, 0.0, 0.7, 48000.0),
+            IIR2Coefficients::highshelf(1000.0, -6.0, 0.7, 48000.0),
+        ];
+        for coeffs in coefficients.iter() {
+            let mut filter = IIR2::from(*coeffs);
+            filter.process(1.0);
+            let mut before = filter;
+
+            let impulse_response = filter.impulse_response(48000);
+            let dc_gain = coeffs.get_bode_sample(0.01, 48000.0).norm();
+            let sum: f32 = impulse_response.iter().sum();
+            assert!((sum - dc_gain).abs() < 1e-3);
+            assert_eq!(filter.process(0.0), before.process(0.0));
+        }
+    }
+
+    #[test]
+    fn test_iir2_step_response() {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, -6.0, 0.7, 48000.0);
+        let filter = IIR2::from(coeffs);
+        let step_response = filter.step_response(48000);
+        let dc_gain = coeffs.get_bode_sample(0.0, 48000.0).norm();
+
+        assert_eq!(step_response.len(), 48000);
+        assert!((step_response[47999] - dc_gain).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_iir2_reset() {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 4.0, 48000.0);
+        let mut filter = IIR2::from(coeffs);
+
+        for i in 0..100 {
+            filter.process(rand(i as f32) * 10.0);
+        }
+
+        filter.reset();
+
+        for _ in 0..100 {
+            assert_eq!(filter.process(0.0), 0.0);
+        }
+    }
+
+    fn bode_db(coeffs: IIR2Coefficients, frequency_hz: f32, sample_rate_hz: f32) -> f32 {
+        20.0 * coeffs
+            .get_bode_sample(frequency_hz, sample_rate_hz)
+            .norm()
+            .log10()
+    }
+
+    #[test]
+    fn test_iir2_constant_q_bell() {
+        let sample_rate_hz = 48000.0;
+        for &gain_db in &[12.0, -12.0] {
+            let coeffs = IIR2Coefficients::constant_q_bell(1000.0, gain_db, 0.7, sample_rate_hz);
+            assert!(bode_db(coeffs, 0.0, sample_rate_hz).abs() < 0.01);
+            assert!(bode_db(coeffs, sample_rate_hz * 0.5, sample_rate_hz).abs() < 0.01);
+            assert!((bode_db(coeffs, 1000.0, sample_rate_hz) - gain_db).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_iir2_bandshelf() {
+        let sample_rate_hz = 48000.0;
+        for &gain_db in &[12.0, -12.0] {
+            let sections = IIR2Coefficients::bandshelf(4000.0, 125.0, gain_db, sample_rate_hz);
+            let db = |f: f64| {
+                20.0 * sections
+                    .iter()
+                    .fold(Complex::new(1.0, 0.0), |acc, c| {
+                        acc * c.get_bode_sample(f, sample_rate_hz)
+                    })
+                    .norm()
+                    .log10()
+            };
+            assert!(db(0.0).abs() < 0.01);
+            assert!(db(sample_rate_hz * 0.5).abs() < 0.01);
+            // Half the gain at the corners
+            assert!((db(125.0) - gain_db * 0.5).abs() < 0.1);
+            assert!((db(4000.0) - gain_db * 0.5).abs() < 0.1);
+            // Within 1 dB of the plateau an octave inside each corner, and flat to 0.1 dB from
+            // two octaves inside, 500 Hz to 1 kHz
+            for point in log_frequencies(250.0, 2000.0, 50) {
+                assert!((db(point) - gain_db).abs() < 1.0);
+            }
+            for point in log_frequencies(500.0, 1000.0, 50) {
+                assert!((db(point) - gain_db).abs() < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_passband_gain() {
+        let sample_rate_hz = 48000.0;
+        let nyquist_hz = sample_rate_hz * 0.5;
+        let gain_db = -6.0;
+
+        let lowpass = IIR2Coefficients::lowpass(1000.0, gain_db, 0.5, sample_rate_hz);
+        let highpass = IIR2Coefficients::highpass(1000.0, gain_db, 0.5, sample_rate_hz);
+        let bandpass = IIR2Coefficients::bandpass(1000.0, gain_db, 1.0, sample_rate_hz);
+        let notch = IIR2Coefficients::notch(1000.0, gain_db, 1.0, sample_rate_hz);
+        let allpass = IIR2Coefficients::allpass(1000.0, gain_db, 1.0, sample_rate_hz);
+
+        assert!((bode_db(lowpass, 0.0, sample_rate_hz) - gain_db).abs() < 0.01);
+        assert!((bode_db(highpass, nyquist_hz, sample_rate_hz) - gain_db).abs() < 0.01);
+        assert!((bode_db(bandpass, 1000.0, sample_rate_hz) - gain_db).abs() < 0.01);
+        assert!((bode_db(notch, 0.0, sample_rate_hz) - gain_db).abs() < 0.01);
+        for &f in &[0.0, 100.0, 1000.0, 10000.0] {
+            assert!((bode_db(allpass, f, sample_rate_hz) - gain_db).abs() < 0.01);
+        }
+    }
+
+    type Constructor<T = f32> = fn(T, T, T, T) -> IIR2Coefficients<T>;
+
+    fn constructors<T: Float>() -> [Constructor<T>; 10] {
+        [
+            IIR2Coefficients::lowpass,
+            IIR2Coefficients::highpass,
+            IIR2Coefficients::bandpass,
+            IIR2Coefficients::notch,
+            IIR2Coefficients::allpass,
+            IIR2Coefficients::lowshelf,
+            IIR2Coefficients::highshelf,
+            IIR2Coefficients::bell,
+            IIR2Coefficients::constant_q_bell,
+            IIR2Coefficients::tilt,
+        ]
+    }
+
+    #[test]
+    fn test_iir2_cutoff_limits() {
+        let sample_rate_hz = 48000.0;
+        for constructor in constructors::<f32>().iter() {
+            for &cutoff_hz in &[sample_rate_hz * 0.5, 0.0, -10.0] {
+                let c = constructor(cutoff_hz, 6.0, 1.0, sample_rate_hz);
+                let fields = [c.a, c.g, c.gpow2, c.k, c.a1, c.a2, c.a3, c.m0, c.m1, c.m2];
+                assert!(fields.iter().all(|x| x.is_finite()));
+
+                let mut filter = IIR2::from(c);
+                for i in 0..1000 {
+                    assert!(filter.process(rand(i as f32)).is_finite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_filter_type() {
+        let names = [
+            "LowPass",
+            "high_pass",
+            "bandpass",
+            "Notch",
+            "all-pass",
+            "LOWSHELF",
+            "HighShelf",
+            "bell",
+            "ConstantQBell",
+            "tilt",
+        ];
+        for ((filter_type, constructor), name) in IIR2FilterType::ALL
+            .iter()
+            .zip(constructors::<f32>().iter())
+            .zip(names.iter())
+        {
+            let expected = constructor(1000.0, 6.0, 2.0, 48000.0);
+            let coeffs = IIR2Coefficients::<f32>::new(*filter_type, 1000.0, 6.0, 2.0, 48000.0);
+            assert_eq!(format!("{:?}", coeffs), format!("{:?}", expected));
+            assert_eq!(name.parse::<IIR2FilterType>(), Ok(*filter_type));
+        }
+        assert!("lowpas".parse::<IIR2FilterType>().is_err());
+    }
+
+    #[test]
+    fn test_iir2_frequency_response() {
+        let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+        let response = coeffs.frequency_response(50.0, 20000.0, 5, 48000.0);
+
+        assert_eq!(response.len(), 5);
+        assert!((response[0].frequency_hz - 50.0).abs() < 1e-3);
+        assert!((response[2].frequency_hz - 1000.0).abs() < 1e-1);
+        assert!((response[2].magnitude_db - 6.0).abs() < 0.01);
+        assert!((response[4].frequency_hz - 20000.0).abs() < 1.0);
+
+        // Sweeps past Nyquist end just below it
+        let response = coeffs.frequency_response(10.0, 100000.0, 5, 48000.0);
+        let nyquist_hz = 48000.0 * crate::MAX_CUTOFF_RATIO;
+        assert!((response[4].frequency_hz - nyquist_hz).abs() < 1.0);
+
+        let response = coeffs.frequency_response(0.0, 20000.0, 3, 48000.0);
+        assert_eq!(response[0].frequency_hz, crate::MIN_RESPONSE_HZ);
+        assert!(coeffs
+            .frequency_response(20.0, 20000.0, 0, 48000.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_iir2_group_delay() {
+        let coeffs = IIR2Coefficients::allpass(1000.0, 0.0, 2.0, 48000.0);
+        let response = coeffs.frequency_response(100.0, 10000.0, 401, 48000.0);
+        let (peak_hz, _) = response
+            .iter()
+            .map(|p| {
+                (
+                    p.frequency_hz,
+                    coeffs.group_delay_at(p.frequency_hz, 48000.0),
+                )
+            })
+            .fold((0.0, 0.0), |max, x| if x.1 > max.1 { x } else { max });
+        assert!((peak_hz - 1000.0).abs() < 50.0);
+
+        assert!(coeffs.group_delay_at(0.0, 48000.0).is_finite());
+        assert!(coeffs.group_delay_at(24000.0, 48000.0).is_finite());
+    }
+
+    #[test]
+    fn test_iir2_to_biquad() {
+        let sample_rate_hz = 48000.0;
+        for constructor in constructors::<f64>().iter() {
+            for &cutoff_hz in &[50.0, 1000.0, 15000.0] {
+                let coeffs = constructor(cutoff_hz, 6.0, 0.8, sample_rate_hz);
+                let biquad = coeffs.to_biquad();
+                for p in coeffs.frequency_response(10.0, 23000.0, 50, sample_rate_hz) {
+                    let svf = coeffs.get_bode_sample(p.frequency_hz, sample_rate_hz);
+                    let direct = biquad.get_bode_sample(p.frequency_hz, sample_rate_hz);
+                    assert!((svf - direct).norm() < 1e-9 * svf.norm().max(1.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_from_biquad() {
+        let sample_rate_hz = 48000.0;
+        for constructor in constructors::<f64>().iter() {
+            for &cutoff_hz in &[50.0, 1000.0, 15000.0] {
+                let coeffs = constructor(cutoff_hz, -6.0, 3.0, sample_rate_hz);
+                let b = coeffs.to_biquad();
+                let round_trip = IIR2Coefficients::from_biquad(b.b0, b.b1, b.b2, b.a1, b.a2)
+                    .expect("built-in filters are stable");
+                for p in coeffs.frequency_response(10.0, 23000.0, 50, sample_rate_hz) {
+                    let expected = coeffs.get_bode_sample(p.frequency_hz, sample_rate_hz);
+                    let actual = round_trip.get_bode_sample(p.frequency_hz, sample_rate_hz);
+                    assert!((expected - actual).norm() < 1e-9 * expected.norm().max(1.0));
+                }
+            }
+        }
+
+        // Pole outside the unit circle
+        assert!(IIR2Coefficients::from_biquad(1.0, 0.0, 0.0, 0.0, 1.1).is_none());
+        // Real pole below -1
+        assert!(IIR2Coefficients::from_biquad(1.0, 0.0, 0.0, 2.5, 0.9).is_none());
+        // Poles on the unit circle
+        assert!(IIR2Coefficients::from_biquad(1.0, 0.0, 0.0, -1.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_iir2_tilt() {
+        let sample_rate_hz = 48000.0;
+        let nyquist_hz = sample_rate_hz * 0.5;
+
+        let flat = IIR2Coefficients::tilt(1000.0, 0.0, 0.5, sample_rate_hz);
+        for &f in &[0.0, 100.0, 1000.0, 10000.0, nyquist_hz] {
+            assert_eq!(flat.get_bode_sample(f, sample_rate_hz).norm(), 1.0);
+        }
+
+        let up = IIR2Coefficients::tilt(1000.0, 6.0, 0.5, sample_rate_hz);
+        let down = IIR2Coefficients::tilt(1000.0, -6.0, 0.5, sample_rate_hz);
+        assert!((bode_db(up, 0.0, sample_rate_hz) + 3.0).abs() < 0.01);
+        assert!((bode_db(up, nyquist_hz, sample_rate_hz) - 3.0).abs() < 0.01);
+        assert!(bode_db(up, 1000.0, sample_rate_hz).abs() < 0.01);
+        for &f in &[0.0, 100.0, 1000.0, 10000.0, nyquist_hz] {
+            let sum = bode_db(up, f, sample_rate_hz) + bode_db(down, f, sample_rate_hz);
+            assert!(sum.abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_iir2_state() {
+        let input: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
+        let coeffs = IIR2Coefficients::bell(1000.0, 12.0, 4.0, 48000.0);
+
+        let mut straight = IIR2::from(coeffs);
+        let expected: Vec<f32> = input.iter().map(|&x| straight.process(x)).collect();
+
+        let mut first = IIR2::from(coeffs);
+        let mut output: Vec<f32> = input[..500].iter().map(|&x| first.process(x)).collect();
+        let saved = first.state();
+        let mut second = IIR2::from(coeffs);
+        second.set_state(saved);
+        output.extend(input[500..].iter().map(|&x| second.process(x)));
+
+        assert_eq!(output, expected);
+        assert_eq!(second.state(), straight.state());
+    }
+
+    /// Frequencies on both sides of cutoff_hz where the magnitude crosses level_db
+    fn band_edges(
+        coeffs: IIR2Coefficients<f64>,
+        cutoff_hz: f64,
+        level_db: f64,
+        fs: f64,
+    ) -> [f64; 2] {
+        let db = |f: f64| 20.0 * coeffs.get_bode_sample(f, fs).norm().log10();
+        let above = db(cutoff_hz) > level_db;
+        [(1e-3, cutoff_hz), (cutoff_hz, fs / 2.0)].map(|(mut lo, mut hi)| {
+            let lower_side = lo < cutoff_hz;
+            for _ in 0..100 {
+                let middle = (lo + hi) / 2.0;
+                // Between the edge and the cutoff the magnitude is on the same side of level_db as
+                // at the cutoff
+                let towards_cutoff = (db(middle) > level_db) == above;
+                if towards_cutoff == lower_side {
+                    hi = middle;
+                } else {
+                    lo = middle;
+                }
+            }
+            (lo + hi) / 2.0
+        })
+    }
+
+    #[test]
+    fn test_iir2_bandwidth() {
+        for (cutoff_hz, fs) in [(1000.0, 48000.0), (10000.0, 44100.0), (15000.0, 48000.0)] {
+            for bandwidth in [0.3, 1.0, 2.0] {
+                let notch = IIR2Coefficients::notch_bw(cutoff_hz, 0.0, bandwidth, fs);
+                let [lower, upper] = band_edges(notch, cutoff_hz, -3.0103, fs);
+                assert!(((upper / lower).log2() - bandwidth).abs() < 1e-3);
+
+                let bell = IIR2Coefficients::bell_bw(cutoff_hz, 12.0, bandwidth, fs);
+                let [lower, upper] = band_edges(bell, cutoff_hz, 6.0, fs);
+                assert!(((upper / lower).log2() - bandwidth).abs() < 1e-3);
+
+                let q_value = q_from_bandwidth(cutoff_hz, bandwidth, fs);
+                assert!((bandwidth_from_q(cutoff_hz, q_value, fs) - bandwidth).abs() < 1e-9);
+            }
+        }
+        // Far below Nyquist this is the analog relation, one octave is Q = sqrt(2)
+        assert!((q_from_bandwidth(100.0, 1.0, 96000.0) - core::f64::consts::SQRT_2).abs() < 1e-4);
+        // Three octaves above 10 kHz do not fit below Nyquist, the widest band is used instead
+        let q_value = q_from_bandwidth(10000.0, 3.0, 44100.0);
+        assert!(q_value.is_finite() && q_value > 0.0);
+    }
+
+    #[test]
+    fn test_iir2_bandpass_variants() {
+        let fs = 48000.0;
+        let frequencies: Vec<f32> = log_frequencies(10.0, 23000.0, 20000).collect();
+        for q_value in [0.5, 2.0, 20.0] {
+            let normalized = IIR2Coefficients::bandpass_peak_normalized(1000.0, q_value, fs);
+            let peak = frequencies
+                .iter()
+                .map(|&f| normalized.get_bode_sample(f, fs).norm())
+                .fold(0.0, f32::max);
+            assert!((peak - 1.0).abs() < 0.01);
+
+            let skirt = IIR2Coefficients::bandpass_constant_skirt(1000.0, q_value, fs);
+            let reference = crate::rbj::bandpass_constant_skirt(1000.0, q_value, fs).unwrap();
+            for f in [100.0, 1000.0, 5000.0] {
+                let expected = reference.get_bode_sample(f, fs).norm();
+                assert!((skirt.get_bode_sample(f, fs).norm() - expected).abs() < 1e-4 * q_value);
+            }
+            assert!((skirt.get_bode_sample(1000.0, fs).norm() - q_value).abs() < 1e-3 * q_value);
+        }
+    }
+
+    #[test]
+    fn test_iir2_resonator() {
+        let fs = 48000.0f64;
+        let peak = |x: &[f64]| x.iter().fold(0.0, |m: f64, y| m.max(y.abs()));
+        for (center_hz, decay_seconds) in [(440.0, 0.5), (3000.0, 2.0), (80.0, 0.2)] {
+            let coeffs = IIR2Coefficients::resonator(center_hz, decay_seconds, fs);
+            let period = (fs / center_hz) as usize + 1;
+            let decay_samples = (decay_seconds * fs) as usize;
+
+            let response = IIR2::from(coeffs).impulse_response(decay_samples + 4 * period);
+            let start = peak(&response[period..2 * period]);
+            let end = peak(&response[decay_samples + period..decay_samples + 2 * period]);
+            assert!((20.0 * (end / start).log10() + 60.0).abs() < 0.5);
+
+            let mut filter = IIR2::from(coeffs);
+            let length = 4 * decay_samples;
+            let output: Vec<f64> = (0..length)
+                .map(|i| filter.process((TAU * center_hz * i as f64 / fs).sin()))
+                .collect();
+            assert!((peak(&output[length - period..]) - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_iir2_linkwitz_transform() {
+        let fs = 48000.0f64;
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        for (f0, q0, f1, q1) in [
+            (60.0, 1.1, 25.0, 0.707),
+            (40.0, 0.9, 20.0, 0.5),
+            (80.0, 0.6, 100.0, 0.707),
+        ] {
+            let transform = IIR2Coefficients::linkwitz_transform(f0, q0, f1, q1, fs);
+            let driver = IIR2Coefficients::highpass(f0, 0.0, q0, fs);
+            let target = IIR2Coefficients::highpass(f1, 0.0, q1, fs);
+            for f in log_frequencies(5.0, 20000.0, 200) {
+                let combined = transform.get_bode_sample(f, fs) * driver.get_bode_sample(f, fs);
+                assert!((db(combined) - db(target.get_bode_sample(f, fs))).abs() < 0.2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_bell_decramped() {
+        let fs = 44100.0f64;
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        let f = 0.9 * fs / 2.0;
+        for (f0, gain_db, q) in [
+            (16000.0, 6.0, 0.707),
+            (12000.0, 9.0, 1.0),
+            (16000.0, -6.0, 2.0),
+        ] {
+            let a = 10.0f64.powf(gain_db / 40.0);
+            let s = Complex::new(0.0, f / f0);
+            let expected = db((s * s + s * a / q + 1.0) / (s * s + s / (q * a) + 1.0));
+            let decramped = IIR2Coefficients::bell_decramped(f0, gain_db, q, fs).unwrap();
+            let bilinear = IIR2Coefficients::bell(f0, gain_db, q, fs);
+            let decramped_error = (db(decramped.get_bode_sample(f, fs)) - expected).abs();
+            let bilinear_error = (db(bilinear.get_bode_sample(f, fs)) - expected).abs();
+            assert!(decramped_error + 2.0 < bilinear_error);
+            assert!((db(decramped.get_bode_sample(f0, fs)) - gain_db).abs() < 1e-6);
+        }
+
+        // Far below Nyquist it is the same as the bilinear bell
+        let decramped = IIR2Coefficients::bell_decramped(200.0, 6.0, 1.0, fs).unwrap();
+        let bilinear = IIR2Coefficients::bell(200.0, 6.0, 1.0, fs);
+        for f in log_frequencies(20.0, 2000.0, 50) {
+            let difference =
+                db(decramped.get_bode_sample(f, fs)) - db(bilinear.get_bode_sample(f, fs));
+            assert!(difference.abs() < 0.01);
+        }
+
+        // f32 gets the f64 design rounded, even for narrow bells close to DC where an f32
+        // design was off by 10 dB
+        for (f0, gain_db, q) in [(20.0, -12.0, 10.0), (5.0, 6.0, 0.5), (16000.0, 6.0, 0.707)] {
+            let single = IIR2Coefficients::bell_decramped(f0, gain_db, q, 192000.0f32).unwrap();
+            let double =
+                IIR2Coefficients::bell_decramped(f0 as f64, gain_db as f64, q as f64, 192000.0)
+                    .unwrap();
+            assert_eq!(single, IIR2Coefficients::from_f64(double));
+        }
+        assert_eq!(
+            IIR2Coefficients::bell_decramped(1000.0f32, 0.0, 1.0, 48000.0),
+            Some(IIR2Coefficients::bypass())
+        );
+        assert!(IIR2Coefficients::bell_decramped(1000.0f32, 6.0, 1.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_iir2_matched() {
+        let fs = 44100.0f64;
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        let analog = |numerator: &dyn Fn(Complex<f64>) -> Complex<f64>, q: f64, f0: f64, f: f64| {
+            let s = Complex::new(0.0, f / f0);
+            db(numerator(s) / (s * s + s / q + 1.0))
+        };
+
+        let bilinear = IIR2Coefficients::lowpass(15000.0, 0.0, 0.707, fs);
+        let matched = IIR2Coefficients::lowpass_matched(15000.0, 0.0, 0.707, fs).unwrap();
+        let expected = analog(&|_| Complex::new(1.0, 0.0), 0.707, 15000.0, 20000.0);
+        assert!((db(bilinear.get_bode_sample(20000.0, fs)) - expected).abs() > 3.0);
+        assert!((db(matched.get_bode_sample(20000.0, fs)) - expected).abs() < 0.5);
+
+        for (f0, q) in [(15000.0, 0.707), (8000.0, 2.0), (200.0, 0.5)] {
+            let lowpass = IIR2Coefficients::lowpass_matched(f0, 0.0, q, fs).unwrap();
+            let highpass = IIR2Coefficients::highpass_matched(f0, 0.0, q, fs).unwrap();
+            for f in log_frequencies(20.0, 21000.0, 100) {
+                let expected = analog(&|_| Complex::new(1.0, 0.0), q, f0, f);
+                assert!((db(lowpass.get_bode_sample(f, fs)) - expected).abs() < 1.0);
+                let expected = analog(&|s| s * s, q, f0, f);
+                assert!((db(highpass.get_bode_sample(f, fs)) - expected).abs() < 1.0);
+            }
+        }
+
+        for (f0, gain_db, q) in [
+            (16000.0, 6.0, 0.707),
+            (12000.0, -9.0, 2.0),
+            (500.0, 4.0, 1.0),
+        ] {
+            let bell = IIR2Coefficients::bell_matched(f0, gain_db, q, fs).unwrap();
+            let a = 10.0f64.powf(gain_db / 40.0);
+            for f in log_frequencies(20.0, 21000.0, 100) {
+                let expected = analog(&|s| s * s + s * a / q + 1.0, q * a, f0, f);
+                assert!((db(bell.get_bode_sample(f, fs)) - expected).abs() < 1.2);
+            }
+            assert!((db(bell.get_bode_sample(f0, fs)) - gain_db).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_iir2_matched_f32() {
+        // The f32 designs are the f64 ones rounded, down to cutoffs a few Hz above DC, where an
+        // f32 fit was off by up to 190 dB or found no stable biquad at all. The responses are
+        // compared in f64, as evaluating them in f32 is too coarse here.
+        let widen = |c: IIR2Coefficients<f32>| IIR2Coefficients::<f64> {
+            a: c.a as f64,
+            g: c.g as f64,
+            gpow2: c.gpow2 as f64,
+            k: c.k as f64,
+            a1: c.a1 as f64,
+            a2: c.a2 as f64,
+            a3: c.a3 as f64,
+            m0: c.m0 as f64,
+            m1: c.m1 as f64,
+            m2: c.m2 as f64,
+        };
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        for fs in [44100.0f32, 96000.0, 192000.0] {
+            for f0 in [5.0f32, 20.0, 200.0, 1000.0, 15000.0] {
+                for q in [0.5f32, 0.707, 10.0] {
+                    let (f0_64, q_64, fs_64) = (f0 as f64, q as f64, fs as f64);
+                    let designs = [
+                        (
+                            IIR2Coefficients::lowpass_matched(f0, 0.0, q, fs),
+                            IIR2Coefficients::lowpass_matched(f0_64, 0.0, q_64, fs_64),
+                        ),
+                        (
+                            IIR2Coefficients::highpass_matched(f0, 0.0, q, fs),
+                            IIR2Coefficients::highpass_matched(f0_64, 0.0, q_64, fs_64),
+                        ),
+                        (
+                            IIR2Coefficients::bell_matched(f0, 6.0, q, fs),
+                            IIR2Coefficients::bell_matched(f0_64, 6.0, q_64, fs_64),
+                        ),
+                    ];
+                    for (single, double) in designs {
+                        let (single, double) = (widen(single.unwrap()), double.unwrap());
+                        for f in log_frequencies(20.0, 20000.0, 50) {
+                            let difference = db(single.get_bode_sample(f, fs_64))
+                                - db(double.get_bode_sample(f, fs_64));
+                            assert!(difference.abs() < 0.01);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(IIR2Coefficients::lowpass_matched(1000.0f32, 0.0, 0.7, f32::NAN).is_none());
+    }
+
+    #[test]
+    fn test_iir2_notch_depth() {
+        // f64, as the f32 response is too coarse to evaluate a narrow 60 Hz notch at 96 kHz
+        let bode_db = |coeffs: IIR2Coefficients<f64>, f: f64, fs: f64| {
+            20.0 * coeffs.get_bode_sample(f, fs).norm().log10()
+        };
+        for fs in [44100.0, 48000.0, 96000.0] {
+            for (cutoff_hz, q_value) in [(60.0, 10.0), (1000.0, 2.0), (12000.0, 0.7)] {
+                for depth_db in [-3.0, -12.0, -40.0] {
+                    let coeffs = IIR2Coefficients::notch_depth(cutoff_hz, depth_db, q_value, fs);
+                    assert!((bode_db(coeffs, cutoff_hz, fs) - depth_db).abs() < 0.2);
+                    assert!(bode_db(coeffs, 0.0, fs).abs() < 1e-4);
+                    assert!(bode_db(coeffs, fs / 2.0, fs).abs() < 1e-4);
+                }
+            }
+        }
+        let flat = IIR2Coefficients::notch_depth(1000.0, 6.0, 1.0, 48000.0);
+        assert!(bode_db(flat, 1000.0, 48000.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_iir2_shelf_slope() {
+        let fs = 48000.0;
+        let frequencies: Vec<f64> = log_frequencies(10.0, 23000.0, 400).collect();
+        let bode_db = |coeffs: IIR2Coefficients<f64>, f: f64, fs: f64| {
+            20.0 * coeffs.get_bode_sample(f, fs).norm().log10()
+        };
+        for gain_db in [-12.0, 12.0] {
+            for slope in [0.3, 1.0, 4.0] {
+                let low = IIR2Coefficients::lowshelf_slope(1000.0, gain_db, slope, fs);
+                let high = IIR2Coefficients::highshelf_slope(1000.0, gain_db, slope, fs);
+                for coeffs in [low, high] {
+                    let db: Vec<f64> = frequencies
+                        .iter()
+                        .map(|&f| bode_db(coeffs, f, fs))
+                        .collect();
+                    // Monotonic from one plateau to the other, never past either
+                    let rising = db[db.len() - 1] > db[0];
+                    for pair in db.windows(2) {
+                        let step = if rising {
+                            pair[1] - pair[0]
+                        } else {
+                            pair[0] - pair[1]
+                        };
+                        assert!(step > -1e-9);
+                    }
+                    assert!((bode_db(coeffs, 1000.0, fs) - gain_db / 2.0).abs() < 0.01);
+                }
+            }
+        }
+        assert_eq!(q_from_slope(6.0, 1.0), q_from_slope(-6.0, 1.0));
+        assert!((q_from_slope(6.0, 1.0) - core::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert_eq!(q_from_slope(6.0, 4.0), q_from_slope(6.0, 1.0));
+    }
+
+    #[test]
+    fn test_iir2_try_constructors() {
+        use FilterDesignError::*;
+        let fs = 48000.0;
+        assert!(IIR2Coefficients::try_bell(1000.0, 6.0, 1.0, fs).is_ok());
+        for filter_type in IIR2FilterType::ALL {
+            let checked = |cutoff_hz: f32, gain_db: f32, q_value: f32, fs: f32| {
+                IIR2Coefficients::try_new(filter_type, cutoff_hz, gain_db, q_value, fs).err()
+            };
+            assert_eq!(checked(f32::NAN, 0.0, 1.0, fs), Some(InvalidCutoff));
+            assert_eq!(checked(-100.0, 0.0, 1.0, fs), Some(InvalidCutoff));
+            assert_eq!(checked(24000.0, 0.0, 1.0, fs), Some(InvalidCutoff));
+            assert_eq!(checked(1000.0, 0.0, 0.0, fs), Some(InvalidQ));
+            assert_eq!(checked(1000.0, 0.0, -1.0, fs), Some(InvalidQ));
+            assert_eq!(checked(1000.0, 0.0, f32::INFINITY, fs), Some(InvalidQ));
+            assert_eq!(checked(1000.0, f32::NAN, 1.0, fs), Some(InvalidGain));
+            assert_eq!(checked(1000.0, 500.0, 1.0, fs), Some(InvalidGain));
+            assert_eq!(checked(1000.0, 0.0, 1.0, 0.0), Some(InvalidSampleRate));
+            assert_eq!(checked(1000.0, 0.0, 1.0, -48000.0), Some(InvalidSampleRate));
+            assert_eq!(checked(1000.0, 0.0, 1.0, f32::NAN), Some(InvalidSampleRate));
+        }
+        assert_eq!(
+            IIR2Coefficients::try_lowpass(1000.0, 0.0, 0.0, fs).unwrap_err(),
+            InvalidQ
+        );
+        assert_eq!(
+            IIR2Coefficients::try_highshelf(1000.0, 0.0, 1.0, f32::INFINITY).unwrap_err(),
+            InvalidSampleRate
+        );
+    }
+
+    #[test]
+    fn test_iir2_constructors_sanitize() {
+        use crate::{MIN_CUTOFF_HZ, MIN_Q};
+        let fs = 48000.0;
+        for filter_type in IIR2FilterType::ALL {
+            for (cutoff_hz, gain_db, q_value, fs) in [
+                (f32::NAN, 0.0, 1.0, fs),
+                (-100.0, 6.0, 1.0, fs),
+                (f32::INFINITY, 6.0, 1.0, fs),
+                (1000.0, 6.0, 0.0, fs),
+                (1000.0, 6.0, -1.0, fs),
+                (1000.0, 6.0, f32::NAN, fs),
+                (1000.0, f32::NAN, 1.0, fs),
+                (1000.0, f32::INFINITY, 1.0, fs),
+                (1000.0, f32::NEG_INFINITY, 1.0, fs),
+                (1000.0, 6.0, 1.0, 0.0),
+                (1000.0, 6.0, 1.0, f32::NAN),
+            ] {
+                let coeffs = IIR2Coefficients::new(filter_type, cutoff_hz, gain_db, q_value, fs);
+                assert!(coeffs.is_finite(), "{:?}", filter_type);
+            }
+            // A NaN cutoff is the lowest cutoff, NaN gain is 0 dB and Q = 0 is raised to MIN_Q
+            let lowest = IIR2Coefficients::new(filter_type, MIN_CUTOFF_HZ, 6.0, 1.0, fs);
+            assert_eq!(
+                IIR2Coefficients::new(filter_type, f32::NAN, 6.0, 1.0, fs).g,
+                lowest.g
+            );
+            let flat = IIR2Coefficients::new(filter_type, 1000.0, 0.0, 1.0, fs);
+            let nan_gain = IIR2Coefficients::new(filter_type, 1000.0, f32::NAN, 1.0, fs);
+            assert_eq!(
+                (nan_gain.m0, nan_gain.m1, nan_gain.m2),
+                (flat.m0, flat.m1, flat.m2)
+            );
+            let min_q = IIR2Coefficients::new(filter_type, 1000.0, 6.0, MIN_Q, fs);
+            assert_eq!(
+                IIR2Coefficients::new(filter_type, 1000.0, 6.0, 0.0, fs).k,
+                min_q.k
+            );
+            let bypass = IIR2Coefficients::new(filter_type, 1000.0, 6.0, 1.0, -1.0);
+            assert_eq!(bypass.m0, 1.0);
+            assert_eq!((bypass.m1, bypass.m2), (0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_iir2_nan_recovery() {
+        let sample_rate_hz = 48000.0;
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, sample_rate_hz);
+        assert!(coeffs.is_finite());
+        let mut poisoned = coeffs;
+        poisoned.a2 = f32::NAN;
+        assert!(!poisoned.is_finite());
+
+        let mut filter = IIR2::from(coeffs);
+        assert!(!filter.check_and_recover());
+        filter.process(f32::NAN);
+        assert!(filter.process(0.0).is_nan());
+        assert!(filter.check_and_recover());
+        let mut clean = IIR2::from(coeffs);
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            assert_eq!(filter.process(x), clean.process(x));
+        }
+
+        let mut buffer: Vec<f32> = (0..64).map(|i| rand(i as f32)).collect();
+        buffer[10] = f32::INFINITY;
+        assert!(filter.process_block_protected(&mut buffer));
+        assert!(buffer.iter().all(|&x| x == 0.0));
+        let mut buffer: Vec<f32> = (0..64).map(|i| rand(i as f32)).collect();
+        assert!(!filter.process_block_protected(&mut buffer));
+        assert!(buffer.iter().all(|x| x.is_finite()));
+    }
+
+    #[cfg(feature = "denormal-protection")]
+    #[test]
+    fn test_iir2_denormal_protection() {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, 48000.0);
+        let is_normal_or_zero = |x: f32| x == 0.0 || x.abs() >= f32::MIN_POSITIVE;
+        let mut filter = IIR2::from(coeffs);
+        let mut block = IIR2::from(coeffs);
+        filter.process(1.0);
+        let mut buffer = vec![0.0; 100_000];
+        buffer[0] = 1.0;
+        for chunk in buffer.chunks_mut(64) {
+            block.process_block(chunk);
+            let state = block.state();
+            assert!(is_normal_or_zero(state.ic1eq) && is_normal_or_zero(state.ic2eq));
+        }
+        for _ in 0..100_000 {
+            filter.process(0.0);
+            let state = filter.state();
+            assert!(is_normal_or_zero(state.ic1eq) && is_normal_or_zero(state.ic2eq));
+        }
+        assert_eq!(
+            filter.state(),
+            IIR2State {
+                ic1eq: 0.0,
+                ic2eq: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_iir2_settle_to_dc() {
+        let sample_rate_hz = 48000.0;
+        for coeffs in [
+            IIR2Coefficients::lowshelf(200.0, 12.0, 0.5, sample_rate_hz),
+            IIR2Coefficients::highpass(50.0, 0.0, 2.0, sample_rate_hz),
+            IIR2Coefficients::bell(30.0, -6.0, 1.0, sample_rate_hz),
+        ] {
+            let dc_gain = coeffs.get_bode_sample(0.0, sample_rate_hz).re;
+            let mut filter = IIR2::from(coeffs);
+            filter.settle_to_dc(0.5);
+            for _ in 0..1000 {
+                assert!((filter.process(0.5) - 0.5 * dc_gain).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_designed() {
+        let designed = DesignedIIR2::new(IIR2FilterType::Bell, 1000.0, 6.0, 1.0, 48000.0);
+        let design = designed.design();
+        assert_eq!(design.filter_type, IIR2FilterType::Bell);
+        assert_eq!(design.cutoff_hz, 1000.0);
+        assert_eq!(
+            designed.g,
+            IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0).g
+        );
+
+        let moved = designed.with_sample_rate(96000.0);
+        assert_eq!(moved.design().sample_rate_hz, 96000.0);
+        assert_eq!(moved.design().gain_db, 6.0);
+        let before = bode_db(*designed, 1000.0, 48000.0);
+        let after = bode_db(*moved, 1000.0, 96000.0);
+        assert!((before - 6.0).abs() < 1e-3 && (after - 6.0).abs() < 1e-3);
+
+        // Clamped at a lower rate, the requested cutoff survives the round trip
+        let high = DesignedIIR2::new(IIR2FilterType::LowPass, 30000.0, 0.0, 0.707, 96000.0);
+        let back = high.with_sample_rate(44100.0).with_sample_rate(96000.0);
+        assert_eq!(back.g, high.g);
+    }
+
+    #[test]
+    fn test_iir2_designed_partial_updates() {
+        let bits = |c: &IIR2Coefficients<f32>| {
+            [c.a, c.g, c.gpow2, c.k, c.a1, c.a2, c.a3, c.m0, c.m1, c.m2].map(f32::to_bits)
+        };
+        for filter_type in IIR2FilterType::ALL {
+            let mut designed = DesignedIIR2::new(filter_type, 1000.0, 6.0, 0.9, 48000.0);
+            for (cutoff_hz, gain_db) in
+                [(1000.0, -9.5), (250.0, -9.5), (250.0, 18.0), (23990.0, 0.0)]
+            {
+                designed.set_gain_db(gain_db);
+                designed.set_cutoff(cutoff_hz);
+                let fresh = IIR2Coefficients::new(filter_type, cutoff_hz, gain_db, 0.9, 48000.0);
+                assert_eq!(bits(&designed), bits(&fresh), "{:?}", filter_type);
+                assert_eq!(designed.design().cutoff_hz, cutoff_hz);
+                assert_eq!(designed.design().gain_db, gain_db);
+            }
+        }
+
+        // An unusable sample rate stays bypassed
+        let mut broken = DesignedIIR2::new(IIR2FilterType::Bell, 1000.0, 6.0, 1.0, 0.0);
+        broken.set_gain_db(3.0);
+        broken.set_cutoff(500.0);
+        assert_eq!(bits(&broken), bits(&IIR2Coefficients::bypass()));
+    }
+
+    #[test]
+    fn test_iir2_coefficients_comparison() {
+        let fs = 48000.0;
+        let bell = IIR2Coefficients::bell(1000.0, 6.0, 1.0, fs);
+        assert_eq!(bell, IIR2Coefficients::bell(1000.0, 6.0, 1.0, fs));
+
+        // 0.01 Hz off is a different set of coefficients, but not an audible one
+        let nudged = IIR2Coefficients::bell(1000.01, 6.0, 1.0, fs);
+        assert_ne!(bell, nudged);
+        assert!(bell.approx_eq(&nudged, 1e-4) && !bell.approx_eq(&nudged, 1e-7));
+        assert!(bell.max_response_deviation_db(&nudged, fs) < 0.001);
+
+        let moved = IIR2Coefficients::bell(1100.0, 6.0, 1.0, fs);
+        let deviation = bell.max_response_deviation_db(&moved, fs);
+        assert!(deviation > 0.5 && deviation < 6.0);
+    }
+
+    #[test]
+    fn test_iir2_display() {
+        let bell = DesignedIIR2::new(IIR2FilterType::Bell, 1000.0, 6.0, 1.0, 48000.0);
+        assert_eq!(
+            bell.to_string(),
+            "Bell @ 1000 Hz, +6.0 dB, Q 1.00 (fs 48000)"
+        );
+        let rumble = DesignedIIR2::new(IIR2FilterType::HighPass, 31.5, 0.0, 0.707, 44100.0);
+        assert_eq!(
+            rumble.to_string(),
+            "High Pass @ 31.5 Hz, +0.0 dB, Q 0.71 (fs 44100)"
+        );
+        for filter_type in IIR2FilterType::ALL {
+            assert_eq!(filter_type.to_string().parse(), Ok(filter_type));
+        }
+
+        // Plain coefficients describe their response instead, 1 kHz at 48 kHz is 0.02083 fs
+        assert_eq!(
+            bell.coefficients().to_string(),
+            "DC +0.0 dB, peak +6.0 dB at 0.02083 fs, Nyquist +0.0 dB"
+        );
+        let lowpass = IIR2Coefficients::lowpass(2400.0, -3.0, 0.5, 48000.0);
+        assert_eq!(lowpass.to_string(), "DC -3.0 dB, no peak, Nyquist -inf dB");
+    }
+
+    #[test]
+    fn test_iir2_note_constructors() {
+        let fs = 48000.0;
+        for note in [21.0, 57.5, 69.0, 96.0, 140.0] {
+            let cutoff_hz = crate::units::note_to_hz(note);
+            let lowpass = IIR2Coefficients::lowpass(cutoff_hz, 0.0, 2.0, fs);
+            assert_eq!(IIR2Coefficients::lowpass_note(note, 2.0, fs), lowpass);
+            let highpass = IIR2Coefficients::highpass(cutoff_hz, 0.0, 0.7, fs);
+            assert_eq!(IIR2Coefficients::highpass_note(note, 0.7, fs), highpass);
+        }
+
+        // A4 at 432 Hz puts note 81 at 864 Hz
+        let tuned = IIR2Coefficients::lowpass_note_tuned(81.0, 432.0, 0.7, fs);
+        assert_eq!(tuned, IIR2Coefficients::lowpass(864.0, 0.0, 0.7, fs));
+        let tuned = IIR2Coefficients::highpass_note_tuned(57.0, 432.0, 0.7, fs);
+        assert_eq!(tuned, IIR2Coefficients::highpass(216.0, 0.0, 0.7, fs));
+    }
+
+    #[test]
+    fn test_iir2_at_sample_rate() {
+        for filter_type in IIR2FilterType::ALL {
+            let coeffs = IIR2Coefficients::new(filter_type, 1000.0, 6.0, 1.0, 48000.0);
+            for new_sample_rate_hz in [44100.0, 96000.0, 192000.0] {
+                let moved = coeffs.at_sample_rate(48000.0, new_sample_rate_hz);
+                for f in [50.0, 200.0, 1000.0, 2000.0] {
+                    let before = bode_db(coeffs, f, 48000.0);
+                    let after = bode_db(moved, f, new_sample_rate_hz);
+                    // Skips the notch centre and deep stop bands, where tiny differences are large in dB
+                    if before > -40.0 {
+                        assert!((before - after).abs() < 0.1, "{:?} {}", filter_type, f);
+                    }
+                }
+            }
+        }
+
+        // At the cutoff only the shelves and tilt move, by how much depends on the cutoff
+        let db = |coeffs: IIR2Coefficients<f64>, f: f64, fs: f64| {
+            20.0 * coeffs.get_bode_sample(f, fs).norm().log10()
+        };
+        let moved_db = |filter_type, cutoff_hz, gain_db, q_value| {
+            let coeffs =
+                IIR2Coefficients::<f64>::new(filter_type, cutoff_hz, gain_db, q_value, 48000.0);
+            let before = db(coeffs, cutoff_hz, 48000.0);
+            [44100.0, 96000.0, 192000.0]
+                .iter()
+                .map(|&fs| (db(coeffs.at_sample_rate(48000.0, fs), cutoff_hz, fs) - before).abs())
+                .fold(0.0, f64::max)
+        };
+        for filter_type in IIR2FilterType::ALL {
+            // The notch has no finite response at its cutoff
+            if filter_type == IIR2FilterType::Notch {
+                continue;
+            }
+            let gain_dependent = matches!(
+                filter_type,
+                IIR2FilterType::LowShelf | IIR2FilterType::HighShelf | IIR2FilterType::Tilt
+            );
+            for gain_db in [-18.0, -6.0, 6.0, 18.0] {
+                for q_value in [0.5, 1.0, 4.0] {
+                    for cutoff_hz in [100.0, 300.0, 1000.0] {
+                        let moved = moved_db(filter_type, cutoff_hz, gain_db, q_value);
+                        let bound = if gain_dependent { 0.1 } else { 1e-9 };
+                        assert!(moved < bound, "{:?} {} {}", filter_type, gain_db, q_value);
+                    }
+                }
+            }
+        }
+        assert!(moved_db(IIR2FilterType::HighShelf, 10000.0, 18.0, 4.0) > 3.0);
+
+        // Above the new Nyquist the cutoff is clamped instead of producing NaN
+        for cutoff_hz in [20000.0, 40000.0] {
+            let bell = IIR2Coefficients::bell(cutoff_hz, 6.0, 1.0, 96000.0);
+            let moved = bell.at_sample_rate(96000.0, 44100.0);
+            assert!(moved.is_finite() && moved.is_stable());
+        }
+        let bell = IIR2Coefficients::bell(20000.0, 6.0, 1.0, 96000.0);
+        let moved = bell.at_sample_rate(96000.0, 44100.0);
+        assert!((bode_db(moved, 20000.0, 44100.0) - 6.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_iir2_effective_cutoff() {
+        let sample_rate_hz = 48000.0;
+        let lowpass = IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, sample_rate_hz);
+        assert!((lowpass.effective_cutoff_hz(sample_rate_hz).unwrap() - 1000.0).abs() < 2.0);
+        let highpass = IIR2Coefficients::highpass(80.0, 0.0, 0.707, sample_rate_hz);
+        assert!((highpass.effective_cutoff_hz(sample_rate_hz).unwrap() - 80.0).abs() < 0.5);
+        // A resonant low pass reports the edge above its peak
+        let resonant = IIR2Coefficients::lowpass(1000.0, 0.0, 4.0, sample_rate_hz);
+        assert!(resonant.effective_cutoff_hz(sample_rate_hz).unwrap() > 1000.0);
+
+        let allpass = IIR2Coefficients::allpass(1000.0, 0.0, 1.0, sample_rate_hz);
+        assert_eq!(allpass.effective_cutoff_hz(sample_rate_hz), None);
+        let bandpass = IIR2Coefficients::bandpass(1000.0, 0.0, 1.0, sample_rate_hz);
+        assert_eq!(bandpass.effective_cutoff_hz(sample_rate_hz), None);
+    }
+
+    #[test]
+    fn test_iir2_peak_frequency() {
+        let sample_rate_hz = 48000.0;
+        let bell = IIR2Coefficients::bell(2000.0, 12.0, 1.0, sample_rate_hz);
+        assert!((bell.peak_frequency_hz(sample_rate_hz).unwrap() - 2000.0).abs() < 1.0);
+        let bandpass = IIR2Coefficients::bandpass(150.0, 0.0, 3.0, sample_rate_hz);
+        assert!((bandpass.peak_frequency_hz(sample_rate_hz).unwrap() - 150.0).abs() < 0.1);
+        // The peak of a resonant low pass is slightly below the cutoff
+        let resonant = IIR2Coefficients::lowpass(1000.0, 0.0, 4.0, sample_rate_hz);
+        let peak_hz = resonant.peak_frequency_hz(sample_rate_hz).unwrap();
+        assert!(peak_hz > 980.0 && peak_hz < 1000.0);
+
+        let lowpass = IIR2Coefficients::lowpass(1000.0, 0.0, 0.5, sample_rate_hz);
+        assert_eq!(lowpass.peak_frequency_hz(sample_rate_hz), None);
+        let cut = IIR2Coefficients::bell(2000.0, -12.0, 1.0, sample_rate_hz);
+        assert_eq!(cut.peak_frequency_hz(sample_rate_hz), None);
+    }
+
+    #[test]
+    fn test_iir2_stability() {
+        let sample_rate_hz = 48000.0;
+        let cutoffs: Vec<f32> = log_frequencies(10.0, 23000.0, 12).collect();
+        let q_values: Vec<f32> = log_frequencies(1.0, 1000.0, 12).map(|q| q * 0.1).collect();
+        for filter_type in IIR2FilterType::ALL {
+            for &cutoff_hz in &cutoffs {
+                for &q_value in &q_values {
+                    for gain_db in [-24.0, 0.0, 24.0] {
+                        let coeffs = IIR2Coefficients::new(
+                            filter_type,
+                            cutoff_hz,
+                            gain_db,
+                            q_value,
+                            sample_rate_hz,
+                        );
+                        assert!(
+                            coeffs.is_stable(),
+                            "{:?} {} {}",
+                            filter_type,
+                            cutoff_hz,
+                            q_value
+                        );
+                        let (poles, _) = coeffs.poles_zeros();
+                        assert!(poles.iter().all(|p| p.norm() < 1.0 + 1e-6));
+                    }
+                }
+            }
+        }
+
+        let mut unstable = IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, sample_rate_hz);
+        unstable.k = -0.1;
+        assert!(!unstable.is_stable());
+        assert!(unstable.poles_zeros().0.iter().all(|p| p.norm() > 1.0));
+        unstable.k = f32::NAN;
+        assert!(!unstable.is_stable());
+        // Poles essentially on the circle are still reported as stable
+        assert!(IIR2Coefficients::bandpass(10.0, 0.0, 1e6, sample_rate_hz).is_stable());
+    }
+
+    #[test]
+    fn test_iir2_poles_zeros() {
+        let sample_rate_hz = 48000.0f64;
+        // Q = 0.5 is critically damped, a repeated real pole
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 0.5, sample_rate_hz);
+        let (poles, zeros) = coeffs.poles_zeros();
+        let g = (PI * 1000.0 / sample_rate_hz).tan();
+        let expected = (1.0 - g) / (1.0 + g);
+        for p in poles {
+            assert!((p - expected).norm() < 1e-6);
+        }
+        for z in zeros {
+            assert!((z + 1.0).norm() < 1e-6);
+        }
+
+        // Notch zeros sit on the unit circle at the cutoff, poles at the same angle inside
+        let coeffs = IIR2Coefficients::notch(3000.0, 0.0, 2.0, sample_rate_hz);
+        let (poles, zeros) = coeffs.poles_zeros();
+        let angle = TAU * 3000.0 / sample_rate_hz;
+        assert!((zeros[0] - Complex::from_polar(1.0, angle)).norm() < 1e-9);
+        assert!((zeros[1] - Complex::from_polar(1.0, -angle)).norm() < 1e-9);
+        assert!(poles[0].norm() < 1.0 && poles[0].im > 0.0);
+        assert!((poles[0] - poles[1].conj()).norm() < 1e-12);
+
+        // Every pole and zero is a root of the biquad polynomials
+        let coeffs = IIR2Coefficients::bell(500.0, 9.0, 3.0, sample_rate_hz);
+        let b = coeffs.to_biquad();
+        let (poles, zeros) = coeffs.poles_zeros();
+        for p in poles {
+            assert!((p * p + p * b.a1 + b.a2).norm() < 1e-12);
+        }
+        for z in zeros {
+            assert!((z * z * b.b0 + z * b.b1 + b.b2).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_iir2_get_bode_samples() {
+        let sample_rate_hz = 48000.0;
+        let frequencies: Vec<f64> = log_frequencies(1.0, 20000.0, 512).collect();
+        let mut out = vec![Complex::new(0.0, 0.0); frequencies.len()];
+        let db = |x: Complex<f64>| 20.0 * x.norm().log10();
+        for filter_type in IIR2FilterType::ALL {
+            for cutoff_hz in [20.0, 1000.0, 15000.0] {
+                let coeffs =
+                    IIR2Coefficients::new(filter_type, cutoff_hz, 12.0, 10.0, sample_rate_hz);
+                coeffs.get_bode_samples(&frequencies, sample_rate_hz, &mut out);
+                for (&f, batched) in frequencies.iter().zip(out.iter()) {
+                    let scalar = coeffs.get_bode_sample(f, sample_rate_hz);
+                    assert!((db(*batched) - db(scalar)).abs() < 0.01);
+                    assert!((batched.arg() - scalar.arg()).abs().to_degrees() < 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_get_bode_samples_f32() {
+        // Steep filters at both ends, where the f32 scalar method is off by up to 10 dB above
+        // -60 dB, while the batched one stays with the f64 response
+        let frequencies: Vec<f32> = log_frequencies(1.0, 23900.0, 512).collect();
+        let mut out = vec![Complex::new(0.0, 0.0); frequencies.len()];
+        for filter_type in IIR2FilterType::ALL {
+            for cutoff_hz in [20.0, 20000.0] {
+                let coeffs = IIR2Coefficients::new(filter_type, cutoff_hz, 12.0, 10.0, 48000.0);
+                let exact =
+                    IIR2Coefficients::new(filter_type, cutoff_hz as f64, 12.0, 10.0, 48000.0);
+                coeffs.get_bode_samples(&frequencies, 48000.0, &mut out);
+                for (&f, batched) in frequencies.iter().zip(out.iter()) {
+                    let expected = exact.get_bode_sample(f as f64, 48000.0);
+                    let expected_db = 20.0 * expected.norm().log10();
+                    if expected_db < -60.0 {
+                        continue;
+                    }
+                    let batched_db = 20.0 * (batched.norm() as f64).log10();
+                    let phase_error = (batched.arg() as f64 - expected.arg()).abs();
+                    assert!((batched_db - expected_db).abs() < 0.01);
+                    assert!(phase_error.to_degrees() < 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2_lerp() {
+        let sample_rate_hz = 48000.0;
+        let start = IIR2Coefficients::lowpass(200.0, 0.0, 2.0, sample_rate_hz);
+        let end = IIR2Coefficients::lowpass(8000.0, 0.0, 2.0, sample_rate_hz);
+        assert_eq!(
+            format!("{:?}", start.lerp(&end, 0.0)),
+            format!("{:?}", start)
+        );
+
+        let mut filter = IIR2::from(start);
+        let len = 4800;
+        for i in 0..=len {
+            let t = i as f32 / len as f32;
+            filter.update(start.lerp(&end, t));
+            let output = filter.process(rand(i as f32) * 2.0 - 1.0);
+            assert!(output.is_finite() && output.abs() < 10.0);
+        }
+        let last = start.lerp(&end, 1.0);
+        assert!((last.g - end.g).abs() < 1e-6 && (last.a1 - end.a1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_iir2_process_modulated() {
+        let sample_rate_hz = 48000.0;
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 4.0, sample_rate_hz);
+
+        // An unmodulated cutoff reproduces the plain filter
+        let mut plain = IIR2::from(coeffs);
+        let mut modulated = IIR2::from(coeffs);
+        for i in 0..1000 {
+            let x = rand(i as f32);
+            assert_eq!(
+                plain.process(x),
+                modulated.process_modulated(x, 1000.0, sample_rate_hz)
+            );
+        }
+
+        let mut filter = IIR2::from(coeffs);
+        let len = 48000;
+        for i in 0..len {
+            let cutoff_hz = 100.0 * 100.0f32.powf(i as f32 / len as f32);
+            let output =
+                filter.process_modulated(rand(i as f32) * 2.0 - 1.0, cutoff_hz, sample_rate_hz);
+            assert!(output.is_finite() && output.abs() < 10.0);
+        }
+        assert!((filter.coeffs.g - (PI as f32 * 10000.0 / sample_rate_hz).tan()).abs() < 1e-3);
+
+        let mut filter = IIR2::from(coeffs);
+        for i in 0..len {
+            let cutoff_hz = 100.0 * 100.0f32.powf(i as f32 / len as f32);
+            let x = rand(i as f32) * 2.0 - 1.0;
+            let output = filter.process_modulated_fast(x, cutoff_hz, sample_rate_hz);
+            assert!(output.is_finite() && output.abs() < 10.0);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_iir2_serde() {
+        let coeffs = IIR2Coefficients::<f32>::bell(1000.0, 6.0, 0.5, 48000.0);
+        let json = serde_json::to_string(&coeffs).unwrap();
+        let restored: IIR2Coefficients = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", coeffs), format!("{:?}", restored));
+
+        let json = serde_json::to_string(&IIR2FilterType::ConstantQBell).unwrap();
+        assert_eq!(json, "\"ConstantQBell\"");
+        let restored: IIR2FilterType = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, IIR2FilterType::ConstantQBell);
+
+        // Saved presets must keep loading, so the field names are part of the format
+        let saved = r#"{"a":1.0,"g":1.0,"gpow2":1.0,"k":2.0,"a1":0.0,"a2":0.0,"a3":0.0,"m0":1.0,"m1":0.0,"m2":0.0}"#;
+        let restored: IIR2Coefficients = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            format!("{:?}", restored),
+            format!("{:?}", IIR2Coefficients::<f32>::bypass())
+        );
     }
 }
@@ -0,0 +1,177 @@
+use wide::{f32x4, f32x8};
+
+use crate::second_order_iir::IIR2Coefficients;
+
+macro_rules! simd_iir2 {
+    ($name:ident, $simd:ident, $lanes:literal) => {
+        #[doc = concat!("Independent SVFs processed in ", stringify!($lanes), " parallel SIMD lanes")]
+        #[derive(Copy, Clone, Debug)]
+        pub struct $name {
+            ic1eq: $simd,
+            ic2eq: $simd,
+            pub a1: $simd,
+            pub a2: $simd,
+            pub a3: $simd,
+            pub m0: $simd,
+            pub m1: $simd,
+            pub m2: $simd,
+        }
+
+        impl $name {
+            /// Creates the SVFs from a set of filter coefficients per lane
+            #[inline]
+            pub fn from(coefficients: [IIR2Coefficients; $lanes]) -> Self {
+                let mut filter = $name {
+                    ic1eq: $simd::ZERO,
+                    ic2eq: $simd::ZERO,
+                    a1: $simd::ZERO,
+                    a2: $simd::ZERO,
+                    a3: $simd::ZERO,
+                    m0: $simd::ZERO,
+                    m1: $simd::ZERO,
+                    m2: $simd::ZERO,
+                };
+                filter.update(coefficients);
+                filter
+            }
+
+            #[inline]
+            pub fn process(&mut self, input_sample: $simd) -> $simd {
+                let v3 = input_sample - self.ic2eq;
+                let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+                let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+                self.ic1eq = Self::flush_denormal($simd::splat(2.0) * v1 - self.ic1eq);
+                self.ic2eq = Self::flush_denormal($simd::splat(2.0) * v2 - self.ic2eq);
+
+                self.m0 * input_sample + self.m1 * v1 + self.m2 * v2
+            }
+
+            #[inline]
+            pub fn update(&mut self, new_coefficients: [IIR2Coefficients; $lanes]) {
+                for (lane, coeffs) in new_coefficients.iter().enumerate() {
+                    self.replace_lane(lane, *coeffs);
+                }
+            }
+
+            /// Replaces the coefficients of a single lane, leaving the other lanes and all states
+            /// untouched
+            #[inline]
+            pub fn replace_lane(&mut self, lane: usize, coeffs: IIR2Coefficients) {
+                self.a1.as_mut_array()[lane] = coeffs.a1;
+                self.a2.as_mut_array()[lane] = coeffs.a2;
+                self.a3.as_mut_array()[lane] = coeffs.a3;
+                self.m0.as_mut_array()[lane] = coeffs.m0;
+                self.m1.as_mut_array()[lane] = coeffs.m1;
+                self.m2.as_mut_array()[lane] = coeffs.m2;
+            }
+
+            /// Lane wise `flush_denormal`, so the lanes match `IIR2` bit for bit
+            #[inline(always)]
+            fn flush_denormal(value: $simd) -> $simd {
+                if cfg!(feature = "denormal-protection") {
+                    let denormal = value.abs().simd_lt($simd::splat(f32::MIN_POSITIVE));
+                    denormal.bitselect($simd::ZERO, value)
+                } else {
+                    value
+                }
+            }
+
+            /// Clears the internal state of every lane, keeping the current coefficients
+            #[inline]
+            pub fn reset(&mut self) {
+                self.ic1eq = $simd::ZERO;
+                self.ic2eq = $simd::ZERO;
+            }
+        }
+    };
+}
+
+simd_iir2!(IIR2x4, f32x4, 4);
+simd_iir2!(IIR2x8, f32x8, 8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::second_order_iir::IIR2;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    fn lane_coefficients(lane: usize) -> IIR2Coefficients {
+        let cutoff_hz = 100.0 * 2.0f32.powi(lane as i32);
+        match lane % 4 {
+            0 => IIR2Coefficients::lowpass(cutoff_hz, 0.0, 0.7, 48000.0),
+            1 => IIR2Coefficients::bell(cutoff_hz, 6.0, 2.0, 48000.0),
+            2 => IIR2Coefficients::highshelf(cutoff_hz, -3.0, 1.0, 48000.0),
+            _ => IIR2Coefficients::notch(cutoff_hz, 0.0, 4.0, 48000.0),
+        }
+    }
+
+    #[test]
+    fn test_iir2x4() {
+        let mut coeffs = [0, 1, 2, 3].map(lane_coefficients);
+        let mut filter = IIR2x4::from(coeffs);
+        let mut scalar = coeffs.map(IIR2::from);
+
+        for i in 0..4000 {
+            if i == 2000 {
+                coeffs[1] = IIR2Coefficients::bandpass(3000.0, 0.0, 1.0, 48000.0);
+                filter.replace_lane(1, coeffs[1]);
+                scalar[1].update(coeffs[1]);
+            }
+            let input = [0, 1, 2, 3].map(|lane| rand((i * 4 + lane) as f32));
+            let output = filter.process(f32x4::new(input)).to_array();
+            for lane in 0..4 {
+                assert_eq!(output[lane], scalar[lane].process(input[lane]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir2x8() {
+        let lanes = [0, 1, 2, 3, 4, 5, 6, 7];
+        let mut coeffs = lanes.map(lane_coefficients);
+        let mut filter = IIR2x8::from(coeffs);
+        let mut scalar = coeffs.map(IIR2::from);
+
+        for i in 0..4000 {
+            if i == 2000 {
+                coeffs[6] = IIR2Coefficients::bandpass(3000.0, 0.0, 1.0, 48000.0);
+                filter.replace_lane(6, coeffs[6]);
+                scalar[6].update(coeffs[6]);
+            }
+            let input = lanes.map(|lane| rand((i * 8 + lane) as f32));
+            let output = filter.process(f32x8::new(input)).to_array();
+            for lane in 0..8 {
+                assert_eq!(output[lane], scalar[lane].process(input[lane]));
+            }
+        }
+
+        filter.reset();
+        assert_eq!(filter.process(f32x8::ZERO).to_array(), [0.0; 8]);
+    }
+
+    #[test]
+    fn test_simd_iir2_denormal_tail() {
+        // The tail after an impulse decays through the denormal range in every lane, which
+        // is flushed to zero like in `IIR2`
+        let coeffs = [0, 1, 2, 3].map(lane_coefficients);
+        let mut filter = IIR2x4::from(coeffs);
+        let mut scalar = coeffs.map(IIR2::from);
+        filter.process(f32x4::ONE);
+        for filter in scalar.iter_mut() {
+            filter.process(1.0);
+        }
+        for _ in 0..200_000 {
+            let output = filter.process(f32x4::ZERO).to_array();
+            for lane in 0..4 {
+                assert_eq!(output[lane], scalar[lane].process(0.0));
+            }
+        }
+        let state = [filter.ic1eq.to_array(), filter.ic2eq.to_array()];
+        if cfg!(feature = "denormal-protection") {
+            assert!(state.iter().flatten().all(|&x| x == 0.0 || x.is_normal()));
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use alloc::vec::Vec;
+
+use num_complex::Complex;
+use num_traits::Float;
+
+use crate::cascade_design::{CascadeDesign, FilterFamily, FilterKind};
+use crate::first_order_iir::IIR1;
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+use crate::{cast, response_frequencies, sanitize_cutoff, BodePoint};
+
+/// Slopes `SlopeFilter` supports, in dB per octave
+pub const SLOPES_DB_PER_OCTAVE: [u32; 6] = [6, 12, 18, 24, 36, 48];
+
+/// Second order sections of the steepest slope
+const MAX_SLOPE_SECTIONS: usize = 4;
+
+/// Length of the crossfade after a slope change
+const SLOPE_FADE_SECONDS: f64 = 0.01;
+
+/// The Butterworth sections of one slope
+#[derive(Copy, Clone, Debug)]
+struct SlopeSections<T> {
+    slope_db_per_octave: u32,
+    sections: [IIR2<T>; MAX_SLOPE_SECTIONS],
+    num_sections: usize,
+    first_order: Option<IIR1<T>>,
+}
+
+impl<T: Float> SlopeSections<T> {
+    /// Switches to the sections of design. Sections both configurations have keep their state,
+    /// which the SVF form carries over to new coefficients without a jump.
+    fn configure(&mut self, slope_db_per_octave: u32, design: &CascadeDesign<T>) {
+        self.slope_db_per_octave = slope_db_per_octave;
+        for (i, &coeffs) in design.sections().iter().enumerate() {
+            if i < self.num_sections {
+                self.sections[i].update(coeffs);
+            } else {
+                self.sections[i] = IIR2::from(coeffs);
+            }
+        }
+        self.num_sections = design.sections().len();
+        self.first_order = match (self.first_order, design.first_order()) {
+            (Some(mut filter), Some(coeffs)) => {
+                filter.update(coeffs);
+                Some(filter)
+            }
+            (None, Some(coeffs)) => Some(IIR1::from(coeffs)),
+            (_, None) => None,
+        };
+    }
+
+    #[inline]
+    fn process(&mut self, input_sample: T) -> T {
+        let output = self.sections[..self.num_sections]
+            .iter_mut()
+            .fold(input_sample, |x, section| section.process(x));
+        match &mut self.first_order {
+            Some(filter) => filter.process(output),
+            None => output,
+        }
+    }
+
+    fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+        if let Some(filter) = &mut self.first_order {
+            filter.reset();
+        }
+    }
+}
+
+/// Butterworth low or high pass with a slope of 6 to 48 dB per octave, like the single slope
+/// knob of DJ filters and channel strips.
+///
+/// Each slope is a cascade of up to four second order sections plus a first order one for odd
+/// multiples of 6 dB. On a slope change the new cascade takes over the state of the sections
+/// the old one had and is crossfaded in over 10 ms, while the old one keeps running, so the
+/// switch does not click. Changes during a crossfade never cut it short, see `set_slope`.
+#[derive(Copy, Clone, Debug)]
+pub struct SlopeFilter<T = f32> {
+    kind: FilterKind,
+    slope_db_per_octave: u32,
+    cutoff_hz: T,
+    sample_rate_hz: T,
+    active: SlopeSections<T>,
+    previous: Option<SlopeSections<T>>,
+    fade_length: usize,
+    fade_position: usize,
+}
+
+impl<T: Float> SlopeFilter<T> {
+    /// Creates a filter, with the slope snapped like `set_slope`, `None` if the sample rate is
+    /// not usable
+    pub fn new(
+        kind: FilterKind,
+        slope_db_per_octave: u32,
+        cutoff_hz: T,
+        sample_rate_hz: T,
+    ) -> Option<Self> {
+        sanitize_cutoff(cutoff_hz, sample_rate_hz)?;
+        let fade_length = (sample_rate_hz * cast(SLOPE_FADE_SECONDS))
+            .round()
+            .to_usize()
+            .unwrap_or(0)
+            .max(1);
+        let empty = SlopeSections {
+            slope_db_per_octave: 0,
+            sections: [IIR2::from(IIR2Coefficients::bypass()); MAX_SLOPE_SECTIONS],
+            num_sections: 0,
+            first_order: None,
+        };
+        let mut filter = SlopeFilter {
+            kind,
+            slope_db_per_octave: snap_slope(slope_db_per_octave),
+            cutoff_hz,
+            sample_rate_hz,
+            active: empty,
+            previous: None,
+            fade_length,
+            fade_position: 0,
+        };
+        let slope_db_per_octave = filter.slope_db_per_octave;
+        filter
+            .active
+            .configure(slope_db_per_octave, &filter.design(slope_db_per_octave));
+        Some(filter)
+    }
+
+    fn design(&self, slope_db_per_octave: u32) -> CascadeDesign<T> {
+        CascadeDesign::new(
+            FilterFamily::Butterworth,
+            self.kind,
+            (slope_db_per_octave / 6) as usize,
+            self.cutoff_hz,
+            self.sample_rate_hz,
+        )
+        .expect("the sample rate was checked in SlopeFilter::new")
+    }
+
+    pub fn kind(&self) -> FilterKind {
+        self.kind
+    }
+
+    /// The slope last set, which may still be waiting for a crossfade to finish
+    pub fn slope(&self) -> u32 {
+        self.slope_db_per_octave
+    }
+
+    pub fn cutoff_hz(&self) -> T {
+        self.cutoff_hz
+    }
+
+    /// Changes the slope to the nearest of `SLOPES_DB_PER_OCTAVE`, crossfading from the current
+    /// one. During a crossfade, going back to the slope being faded out reverses the fade from
+    /// its current mix, and any other slope is faded to once the running crossfade is done, so
+    /// the output stays continuous however fast the slope moves.
+    pub fn set_slope(&mut self, slope_db_per_octave: u32) {
+        let slope_db_per_octave = snap_slope(slope_db_per_octave);
+        self.slope_db_per_octave = slope_db_per_octave;
+        match &mut self.previous {
+            Some(previous) if previous.slope_db_per_octave == slope_db_per_octave => {
+                core::mem::swap(previous, &mut self.active);
+                self.fade_position = self.fade_length - self.fade_position;
+                if self.fade_position >= self.fade_length {
+                    self.previous = None;
+                }
+            }
+            Some(_) => {}
+            None => self.start_fade(),
+        }
+    }
+
+    /// Starts a crossfade to the set slope if the active sections have another one
+    fn start_fade(&mut self) {
+        if self.active.slope_db_per_octave == self.slope_db_per_octave {
+            return;
+        }
+        self.previous = Some(self.active);
+        self.fade_position = 0;
+        let design = self.design(self.slope_db_per_octave);
+        self.active.configure(self.slope_db_per_octave, &design);
+    }
+
+    /// Moves the cutoff, updating the sections in place. This is smooth without a crossfade, so
+    /// it can follow a knob every block.
+    pub fn set_cutoff(&mut self, cutoff_hz: T) {
+        self.cutoff_hz = cutoff_hz;
+        let slope_db_per_octave = self.active.slope_db_per_octave;
+        let design = self.design(slope_db_per_octave);
+        self.active.configure(slope_db_per_octave, &design);
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let output = self.active.process(input_sample);
+        let Some(previous) = &mut self.previous else {
+            return output;
+        };
+        let faded_out = previous.process(input_sample);
+        self.fade_position += 1;
+        let mix = cast::<T>(self.fade_position as f64 / self.fade_length as f64);
+        if self.fade_position >= self.fade_length {
+            self.previous = None;
+            self.start_fade();
+        }
+        faded_out + (output - faded_out) * mix
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the state of every section and ends a crossfade, switching straight to the set
+    /// slope
+    pub fn reset(&mut self) {
+        let design = self.design(self.slope_db_per_octave);
+        self.active.configure(self.slope_db_per_octave, &design);
+        self.active.reset();
+        self.previous = None;
+    }
+
+    /// Response of the current slope, the one being faded in during a crossfade
+    pub fn get_bode_sample(&self, frequency_hz: T, sample_rate_hz: T) -> Complex<T> {
+        let response = self.active.sections[..self.active.num_sections]
+            .iter()
+            .fold(Complex::new(T::one(), T::zero()), |acc, section| {
+                acc * section.coeffs.get_bode_sample(frequency_hz, sample_rate_hz)
+            });
+        match self.active.first_order {
+            Some(filter) => response * filter.coeffs.get_bode_sample(frequency_hz, sample_rate_hz),
+            None => response,
+        }
+    }
+
+    /// Samples the response at num_points logarithmically spaced frequencies from start_hz to
+    /// end_hz. start_hz is raised to at least `MIN_RESPONSE_HZ` and end_hz lowered to at most
+    /// `MAX_CUTOFF_RATIO` of the sample rate.
+    pub fn frequency_response(
+        &self,
+        start_hz: T,
+        end_hz: T,
+        num_points: usize,
+        sample_rate_hz: T,
+    ) -> Vec<BodePoint<T>> {
+        response_frequencies(start_hz, end_hz, num_points, sample_rate_hz)
+            .map(|f| BodePoint::from_complex(f, self.get_bode_sample(f, sample_rate_hz)))
+            .collect()
+    }
+}
+
+/// The supported slope closest to slope_db_per_octave
+fn snap_slope(slope_db_per_octave: u32) -> u32 {
+    SLOPES_DB_PER_OCTAVE
+        .iter()
+        .copied()
+        .min_by_key(|&slope| slope.abs_diff(slope_db_per_octave))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    #[test]
+    fn test_slope_filter_slopes() {
+        for &slope in &SLOPES_DB_PER_OCTAVE {
+            let lowpass =
+                SlopeFilter::<f64>::new(FilterKind::LowPass, slope, 500.0, 48000.0).unwrap();
+            let highpass =
+                SlopeFilter::<f64>::new(FilterKind::HighPass, slope, 2000.0, 48000.0).unwrap();
+            let db = |filter: &SlopeFilter<f64>, f: f64| {
+                20.0 * filter.get_bode_sample(f, 48000.0).norm().log10()
+            };
+            // One octave beyond the cutoff the response is still bending, so the tolerance is
+            // widest for the gentle slopes
+            let lowpass_slope = db(&lowpass, 1000.0) - db(&lowpass, 2000.0);
+            let highpass_slope = db(&highpass, 1000.0) - db(&highpass, 500.0);
+            assert!((lowpass_slope - slope as f64).abs() < 1.0);
+            assert!((highpass_slope - slope as f64).abs() < 1.0);
+
+            let response = lowpass.frequency_response(20.0, 20000.0, 10, 48000.0);
+            assert_eq!(response.len(), 10);
+            assert!(response[0].magnitude_db.abs() < 0.01);
+        }
+        assert_eq!(
+            SlopeFilter::<f32>::new(FilterKind::LowPass, 30, 1000.0, 48000.0)
+                .unwrap()
+                .slope(),
+            24
+        );
+        assert_eq!(
+            SlopeFilter::<f32>::new(FilterKind::LowPass, 100, 1000.0, 48000.0)
+                .unwrap()
+                .slope(),
+            48
+        );
+        assert!(SlopeFilter::<f32>::new(FilterKind::LowPass, 24, 1000.0, 0.0).is_none());
+        assert!(SlopeFilter::<f32>::new(FilterKind::HighPass, 24, 1000.0, f32::NAN).is_none());
+    }
+
+    #[test]
+    fn test_slope_filter_switch_is_smooth() {
+        let sample_rate_hz = 48000.0;
+        let max_step = |slopes: &[u32], interval: usize| {
+            let mut filter =
+                SlopeFilter::<f64>::new(FilterKind::LowPass, 6, 2000.0, sample_rate_hz).unwrap();
+            let mut last = 0.0;
+            let mut largest = 0.0f64;
+            for i in 0..48000 {
+                if i % interval == interval / 2 {
+                    filter.set_slope(slopes[(i / interval) % slopes.len()]);
+                }
+                let x = (TAU * 150.0 * i as f64 / sample_rate_hz).sin();
+                let y = filter.process(x);
+                if i > 1000 {
+                    largest = largest.max((y - last).abs());
+                }
+                last = y;
+            }
+            largest
+        };
+        // A 150 Hz sine moves by at most 0.02 per sample, switching slopes keeps it near that
+        let steady = max_step(&[6], 4800);
+        let switching = max_step(&[48, 18, 6, 36, 12, 24], 4800);
+        assert!(steady < 0.0197);
+        assert!(switching < steady * 1.2);
+
+        // Changes 1 to 3 ms apart land in running crossfades, both going back to the slope being
+        // faded out and on to new ones
+        for &interval in &[48, 100, 144] {
+            let switching = max_step(&[48, 6, 48, 18, 36, 12, 24, 12], interval);
+            assert!(switching < steady * 1.2);
+        }
+        let mut filter =
+            SlopeFilter::<f64>::new(FilterKind::LowPass, 6, 2000.0, sample_rate_hz).unwrap();
+        filter.set_slope(48);
+        filter.process_block(&mut [0.0; 100]);
+        filter.set_slope(12);
+        filter.set_slope(24);
+        assert_eq!(filter.slope(), 24);
+        filter.process_block(&mut [0.0; 400]);
+        assert_eq!(filter.active.slope_db_per_octave, 24);
+        assert!(filter.previous.is_some());
+        filter.process_block(&mut [0.0; 960]);
+        assert!(filter.previous.is_none());
+
+        let mut filter = SlopeFilter::<f32>::new(FilterKind::HighPass, 18, 100.0, 48000.0).unwrap();
+        let mut block = [1.0f32; 64];
+        filter.set_slope(36);
+        filter.process_block(&mut block);
+        filter.reset();
+        assert_eq!(filter.process(0.0), 0.0);
+    }
+}
@@ -0,0 +1,515 @@
+use num_traits::Float;
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2FilterType, IIR2};
+use crate::{cast, clamp_cutoff};
+
+/// IIR2 that ramps to new coefficients over a fixed number of samples using
+/// `IIR2Coefficients::lerp`
+#[derive(Copy, Clone, Debug)]
+pub struct SmoothedIIR2<T = f32> {
+    filter: IIR2<T>,
+    start: IIR2Coefficients<T>,
+    target: IIR2Coefficients<T>,
+    smoothing_samples: usize,
+    position: usize,
+}
+
+impl<T: Float> SmoothedIIR2<T> {
+    /// Creates a filter settled on coeffs, later targets are reached after smoothing_samples
+    /// calls to `process`. A smoothing time of 0 applies new targets immediately.
+    pub fn new(coeffs: IIR2Coefficients<T>, smoothing_samples: usize) -> Self {
+        SmoothedIIR2 {
+            filter: IIR2::from(coeffs),
+            start: coeffs,
+            target: coeffs,
+            smoothing_samples,
+            position: smoothing_samples,
+        }
+    }
+
+    /// Starts a ramp from the current, possibly interpolated, coefficients to target
+    pub fn set_target(&mut self, target: IIR2Coefficients<T>) {
+        self.start = self.filter.coeffs;
+        self.target = target;
+        self.position = 0;
+        if self.smoothing_samples == 0 {
+            self.filter.update(target);
+        }
+    }
+
+    /// Jumps straight to coeffs, cancelling any ramp in progress
+    pub fn set_immediate(&mut self, coeffs: IIR2Coefficients<T>) {
+        self.start = coeffs;
+        self.target = coeffs;
+        self.position = self.smoothing_samples;
+        self.filter.update(coeffs);
+    }
+
+    #[inline]
+    pub fn is_smoothing(&self) -> bool {
+        self.position < self.smoothing_samples
+    }
+
+    /// Coefficients currently in use
+    #[inline]
+    pub fn coeffs(&self) -> IIR2Coefficients<T> {
+        self.filter.coeffs
+    }
+
+    #[inline]
+    pub fn target(&self) -> IIR2Coefficients<T> {
+        self.target
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        if self.is_smoothing() {
+            self.position += 1;
+            let coeffs = if self.position == self.smoothing_samples {
+                self.target
+            } else {
+                let t = cast::<T>(self.position as f64 / self.smoothing_samples as f64);
+                self.start.lerp(&self.target, t)
+            };
+            self.filter.update(coeffs);
+        }
+        self.filter.process(input_sample)
+    }
+
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the filter state, keeping the current coefficients and ramp
+    #[inline]
+    pub fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+/// How the incoming filter of `CrossfadingIIR2` starts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrossfadeStart {
+    /// Takes over the state of the outgoing filter, which suits related designs
+    CopyState,
+    /// Starts from silence, for designs whose states have nothing in common
+    Fresh,
+}
+
+/// IIR2 that switches coefficients by running the old and new filter side by side and
+/// crossfading between their outputs. Interpolating coefficients like `SmoothedIIR2` is fine for
+/// sweeps, but a change of type, say from a bell to a notch, passes through odd filters in
+/// between and the state of one makes no sense for the other. Outside of a crossfade only one
+/// filter runs.
+#[derive(Copy, Clone, Debug)]
+pub struct CrossfadingIIR2<T = f32> {
+    current: IIR2<T>,
+    outgoing: IIR2<T>,
+    start: CrossfadeStart,
+    fade_length: usize,
+    fade_position: usize,
+}
+
+impl<T: Float> CrossfadingIIR2<T> {
+    /// Creates a filter on coeffs, with incoming filters taking over the state
+    pub fn new(coeffs: IIR2Coefficients<T>) -> Self {
+        CrossfadingIIR2 {
+            current: IIR2::from(coeffs),
+            outgoing: IIR2::from(coeffs),
+            start: CrossfadeStart::CopyState,
+            fade_length: 0,
+            fade_position: 0,
+        }
+    }
+
+    pub fn set_crossfade_start(&mut self, start: CrossfadeStart) {
+        self.start = start;
+    }
+
+    pub fn crossfade_start(&self) -> CrossfadeStart {
+        self.start
+    }
+
+    /// Starts a crossfade to coeffs over crossfade_samples calls to `process`, 0 switches
+    /// immediately. A switch during a crossfade drops the filter being faded out and starts a
+    /// new crossfade from the one being faded in.
+    pub fn switch_to(&mut self, coeffs: IIR2Coefficients<T>, crossfade_samples: usize) {
+        self.outgoing = self.current;
+        self.current = IIR2::from(coeffs);
+        if self.start == CrossfadeStart::CopyState {
+            self.current.set_state(self.outgoing.state());
+        }
+        self.fade_length = crossfade_samples;
+        self.fade_position = 0;
+    }
+
+    #[inline]
+    pub fn is_crossfading(&self) -> bool {
+        self.fade_position < self.fade_length
+    }
+
+    /// Coefficients of the filter being faded in, or the only one running
+    #[inline]
+    pub fn coeffs(&self) -> IIR2Coefficients<T> {
+        self.current.coeffs
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        let output = self.current.process(input_sample);
+        if !self.is_crossfading() {
+            return output;
+        }
+        let faded_out = self.outgoing.process(input_sample);
+        self.fade_position += 1;
+        let mix = cast::<T>(self.fade_position as f64 / self.fade_length as f64);
+        faded_out + (output - faded_out) * mix
+    }
+
+    /// Processes a buffer in place, output is identical to calling `process` on each sample.
+    /// The rest of the buffer after a crossfade runs through `IIR2::process_block`.
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        let fading = (self.fade_length - self.fade_position).min(buffer.len());
+        let (fade, rest) = buffer.split_at_mut(fading);
+        for sample in fade.iter_mut() {
+            *sample = self.process(*sample);
+        }
+        self.current.process_block(rest);
+    }
+
+    /// Clears the filter state and ends a crossfade
+    pub fn reset(&mut self) {
+        self.current.reset();
+        self.fade_position = self.fade_length;
+    }
+}
+
+/// IIR2 whose cutoff glides in pitch rather than in Hz, so a sweep from 100 Hz to 10 kHz
+/// spends as long on every octave. The cutoff follows start * (target / start)^(t / ramp), and
+/// the coefficients are redesigned only every control_interval samples, and at the end of the
+/// ramp, which amortizes the `tan()` of the design. Gain, Q and the filter type stay fixed.
+#[derive(Copy, Clone, Debug)]
+pub struct CutoffSmoother<T = f32> {
+    filter: IIR2<T>,
+    filter_type: IIR2FilterType,
+    gain_db: T,
+    q_value: T,
+    sample_rate_hz: T,
+    start_hz: T,
+    target_hz: T,
+    ramp_samples: usize,
+    position: usize,
+    control_interval: usize,
+}
+
+impl<T: Float> CutoffSmoother<T> {
+    /// Creates a filter settled at cutoff_hz, redesigning every control_interval samples during
+    /// a ramp. An interval of 0 counts as 1.
+    pub fn new(
+        filter_type: IIR2FilterType,
+        cutoff_hz: T,
+        gain_db: T,
+        q_value: T,
+        sample_rate_hz: T,
+        control_interval: usize,
+    ) -> Self {
+        let cutoff_hz = clamp_cutoff(cutoff_hz, sample_rate_hz);
+        CutoffSmoother {
+            filter: IIR2::from(IIR2Coefficients::new(
+                filter_type,
+                cutoff_hz,
+                gain_db,
+                q_value,
+                sample_rate_hz,
+            )),
+            filter_type,
+            gain_db,
+            q_value,
+            sample_rate_hz,
+            start_hz: cutoff_hz,
+            target_hz: cutoff_hz,
+            ramp_samples: 0,
+            position: 0,
+            control_interval: control_interval.max(1),
+        }
+    }
+
+    pub fn set_control_interval(&mut self, control_interval: usize) {
+        self.control_interval = control_interval.max(1);
+    }
+
+    pub fn control_interval(&self) -> usize {
+        self.control_interval
+    }
+
+    /// Starts a glide from the current cutoff to target_hz over ramp_samples calls to `process`,
+    /// 0 jumps immediately
+    pub fn set_target(&mut self, target_hz: T, ramp_samples: usize) {
+        self.start_hz = self.cutoff_hz();
+        self.target_hz = clamp_cutoff(target_hz, self.sample_rate_hz);
+        self.ramp_samples = ramp_samples;
+        self.position = 0;
+        if ramp_samples == 0 {
+            self.redesign(self.target_hz);
+        }
+    }
+
+    /// Starts a glide to target_hz at a constant rate in octaves per second, rounded to whole
+    /// samples. A rate that is not positive jumps immediately.
+    pub fn set_target_octaves_per_second(&mut self, target_hz: T, octaves_per_second: T) {
+        let target_hz = clamp_cutoff(target_hz, self.sample_rate_hz);
+        let octaves = (target_hz / self.cutoff_hz()).log2().abs();
+        let samples = octaves / octaves_per_second * self.sample_rate_hz;
+        let ramp_samples = if octaves_per_second > T::zero() {
+            samples.round().to_usize().unwrap_or(usize::MAX)
+        } else {
+            0
+        };
+        self.set_target(target_hz, ramp_samples);
+    }
+
+    /// Cutoff the glide is at, which the coefficients lag by up to control_interval samples
+    pub fn cutoff_hz(&self) -> T {
+        if !self.is_ramping() {
+            return self.target_hz;
+        }
+        let t = cast::<T>(self.position as f64 / self.ramp_samples as f64);
+        self.start_hz * (self.target_hz / self.start_hz).powf(t)
+    }
+
+    pub fn target_hz(&self) -> T {
+        self.target_hz
+    }
+
+    #[inline]
+    pub fn is_ramping(&self) -> bool {
+        self.position < self.ramp_samples
+    }
+
+    /// Coefficients currently in use
+    #[inline]
+    pub fn coeffs(&self) -> IIR2Coefficients<T> {
+        self.filter.coeffs
+    }
+
+    fn redesign(&mut self, cutoff_hz: T) {
+        self.filter.update(IIR2Coefficients::new(
+            self.filter_type,
+            cutoff_hz,
+            self.gain_db,
+            self.q_value,
+            self.sample_rate_hz,
+        ));
+    }
+
+    #[inline]
+    pub fn process(&mut self, input_sample: T) -> T {
+        if self.is_ramping() {
+            self.position += 1;
+            if self.position == self.ramp_samples
+                || self.position.is_multiple_of(self.control_interval)
+            {
+                self.redesign(self.cutoff_hz());
+            }
+        }
+        self.filter.process(input_sample)
+    }
+
+    #[inline]
+    pub fn process_block(&mut self, buffer: &mut [T]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the filter state, keeping the current coefficients and glide
+    #[inline]
+    pub fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::TAU;
+
+    #[test]
+    fn test_smoothed_iir2_converges() {
+        let start = IIR2Coefficients::lowpass(200.0, 0.0, 1.0, 48000.0);
+        let end = IIR2Coefficients::lowpass(8000.0, 0.0, 1.0, 48000.0);
+        let mut filter = SmoothedIIR2::new(start, 100);
+        assert!(!filter.is_smoothing());
+
+        filter.set_target(end);
+        for _ in 0..99 {
+            assert!(filter.is_smoothing());
+            filter.process(0.0);
+        }
+        assert!(filter.is_smoothing());
+        filter.process(0.0);
+        assert!(!filter.is_smoothing());
+        assert_eq!(format!("{:?}", filter.coeffs()), format!("{:?}", end));
+    }
+
+    #[test]
+    fn test_smoothed_iir2_retarget_mid_ramp() {
+        let low = IIR2Coefficients::lowpass(200.0, 0.0, 1.0, 48000.0);
+        let high = IIR2Coefficients::lowpass(8000.0, 0.0, 1.0, 48000.0);
+        let mut filter = SmoothedIIR2::new(low, 100);
+
+        filter.set_target(high);
+        for _ in 0..50 {
+            filter.process(0.0);
+        }
+        let before = filter.coeffs();
+        filter.set_target(low);
+        assert_eq!(format!("{:?}", filter.coeffs()), format!("{:?}", before));
+        filter.process(0.0);
+        assert!((filter.coeffs().g - before.g).abs() < (high.g - low.g).abs() / 50.0);
+    }
+
+    #[test]
+    fn test_smoothed_iir2_no_clicks() {
+        let sample_rate_hz = 48000.0;
+        let flat = IIR2Coefficients::bell(1000.0, 0.0, 1.0, sample_rate_hz);
+        let boost = IIR2Coefficients::bell(1000.0, 12.0, 1.0, sample_rate_hz);
+        let mut filter = SmoothedIIR2::new(flat, 480);
+
+        // A 1 kHz sine boosted by 12 dB never moves more than about 0.52 per sample
+        let sine = |i: usize| (TAU * 1000.0 * i as f32 / sample_rate_hz).sin();
+        let mut last = 0.0;
+        for i in 0..4800 {
+            if i == 2400 {
+                filter.set_target(boost);
+            }
+            let output = filter.process(sine(i));
+            if i > 0 {
+                assert!((output - last).abs() < 0.6);
+            }
+            last = output;
+        }
+        assert!(!filter.is_smoothing());
+    }
+
+    #[test]
+    fn test_crossfading_iir2_no_discontinuity() {
+        let sample_rate_hz = 48000.0;
+        let boost = IIR2Coefficients::bell(1000.0, 12.0, 1.0, sample_rate_hz);
+        let notch = IIR2Coefficients::notch(1000.0, 0.0, 4.0, sample_rate_hz);
+        let highpass = IIR2Coefficients::highpass(8000.0, 0.0, 0.7, sample_rate_hz);
+        let sine = |i: usize| (TAU * 1000.0 * i as f32 / sample_rate_hz).sin();
+
+        for start in [CrossfadeStart::CopyState, CrossfadeStart::Fresh] {
+            let mut filter = CrossfadingIIR2::new(boost);
+            filter.set_crossfade_start(start);
+            // Blocks of 2412 samples switch at a peak of the sine
+            let mut output: Vec<f32> = (0..7236).map(sine).collect();
+            for (block, targets) in output
+                .chunks_mut(2412)
+                .zip([None, Some(notch), Some(highpass)])
+            {
+                if let Some(coeffs) = targets {
+                    filter.switch_to(coeffs, 240);
+                }
+                filter.process_block(block);
+            }
+            // The boosted sine moves up to about 0.52 per sample
+            assert!(output
+                .windows(2)
+                .all(|pair| (pair[1] - pair[0]).abs() < 0.53));
+        }
+
+        // Running the old filter up to the switch and a fresh one after it jumps
+        let mut old = IIR2::from(boost);
+        let mut new = IIR2::from(notch);
+        let before: f32 = (0..2412).map(|i| old.process(sine(i))).last().unwrap();
+        assert!((new.process(sine(2412)) - before).abs() > 2.0);
+    }
+
+    #[test]
+    fn test_crossfading_iir2_matches_plain_filter() {
+        let sample_rate_hz = 48000.0;
+        let bell = IIR2Coefficients::bell(300.0, 6.0, 2.0, sample_rate_hz);
+        let notch = IIR2Coefficients::notch(2000.0, 0.0, 1.0, sample_rate_hz);
+        let input = |i: usize| (TAU * 440.0 * i as f32 / sample_rate_hz).sin();
+
+        let mut filter = CrossfadingIIR2::new(bell);
+        let mut reference = IIR2::from(bell);
+        for i in 0..1000 {
+            assert_eq!(filter.process(input(i)), reference.process(input(i)));
+        }
+
+        // The incoming filter continues from the state of the outgoing one, so after the
+        // crossfade it is exactly a plain IIR2 that switched coefficients
+        filter.switch_to(notch, 480);
+        reference.update(notch);
+        let mut output: Vec<f32> = (1000..3000).map(input).collect();
+        filter.process_block(&mut output);
+        assert!(!filter.is_crossfading());
+        for (i, y) in output.iter().enumerate() {
+            let expected = reference.process(input(1000 + i));
+            if i >= 480 {
+                assert_eq!(*y, expected);
+            }
+        }
+
+        // A fresh start matches an IIR2 created at the switch
+        filter.set_crossfade_start(CrossfadeStart::Fresh);
+        filter.switch_to(bell, 0);
+        let mut reference = IIR2::from(bell);
+        for i in 0..1000 {
+            assert_eq!(filter.process(input(i)), reference.process(input(i)));
+        }
+    }
+
+    #[test]
+    fn test_cutoff_smoother_glides_in_octaves() {
+        let sample_rate_hz = 48000.0;
+        let mut filter = CutoffSmoother::<f64>::new(
+            IIR2FilterType::LowPass,
+            100.0,
+            0.0,
+            8.0,
+            sample_rate_hz,
+            32,
+        );
+        filter.set_target(10000.0, 48000);
+
+        let mut peak = 0.0f64;
+        for i in 0..48000 {
+            if i == 24000 {
+                // Halfway is the geometric mean, 1 kHz, a decade from both ends
+                assert!((filter.cutoff_hz() - 1000.0).abs() < 1e-9);
+                assert!(
+                    (filter.coeffs().g
+                        - IIR2Coefficients::lowpass(1000.0, 0.0, 8.0, sample_rate_hz).g)
+                        .abs()
+                        < 1e-12
+                );
+            }
+            let x = if i % 50 < 25 { 1.0 } else { -1.0 };
+            let y = filter.process(x);
+            assert!(y.is_finite());
+            peak = peak.max(y.abs());
+        }
+        assert!(!filter.is_ramping() && peak < 100.0);
+        assert_eq!(filter.cutoff_hz(), 10000.0);
+        let end = IIR2Coefficients::lowpass(10000.0, 0.0, 8.0, sample_rate_hz);
+        assert_eq!(filter.coeffs().g, end.g);
+
+        // Four octaves at 8 octaves per second take half a second
+        filter.set_target_octaves_per_second(625.0, 8.0);
+        for _ in 0..23999 {
+            filter.process(0.0);
+        }
+        assert!(filter.is_ramping());
+        filter.process(0.0);
+        assert!(!filter.is_ramping());
+        assert_eq!(filter.cutoff_hz(), 625.0);
+    }
+}
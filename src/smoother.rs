@@ -0,0 +1,121 @@
+use num_traits::Float;
+
+use crate::cast;
+use crate::first_order_iir::{IIR1Coefficients, IIR1State, IIR1};
+
+/// One pole parameter smoother. After a step of the target, the value covers ~63% (1 - 1/e) of
+/// the step in one time constant and ~95% in three.
+#[derive(Copy, Clone, Debug)]
+pub struct Smoother<T = f32> {
+    filter: IIR1<T>,
+    target: T,
+    value: T,
+}
+
+impl<T: Float> Smoother<T> {
+    pub fn new(time_constant_ms: T, sample_rate_hz: T) -> Self {
+        Smoother {
+            filter: IIR1::from(time_constant_coefficients(time_constant_ms, sample_rate_hz)),
+            target: T::zero(),
+            value: T::zero(),
+        }
+    }
+
+    /// Changes the time constant, keeping the current value and target
+    pub fn set_time_constant(&mut self, time_constant_ms: T, sample_rate_hz: T) {
+        self.filter
+            .update(time_constant_coefficients(time_constant_ms, sample_rate_hz));
+    }
+
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Jumps to value without smoothing, for initialization
+    pub fn snap_to(&mut self, value: T) {
+        self.target = value;
+        self.value = value;
+        self.filter.set_state(IIR1State { ic1eq: value });
+    }
+
+    /// Advances by one sample and returns the smoothed value
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> T {
+        self.value = self.filter.process(self.target);
+        self.value
+    }
+
+    /// Last value returned by `next`
+    #[inline]
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    #[inline]
+    pub fn is_settled(&self, epsilon: T) -> bool {
+        (self.value - self.target).abs() <= epsilon
+    }
+}
+
+/// IIR1 low pass whose pole e^(-1 / (time constant * sample rate)) gives the exponential decay of
+/// an analog RC smoother. A time constant of 0 follows the input immediately.
+pub(crate) fn time_constant_coefficients<T: Float>(
+    time_constant_ms: T,
+    sample_rate_hz: T,
+) -> IIR1Coefficients<T> {
+    let samples = time_constant_ms * sample_rate_hz / cast(1000.0);
+    if !(samples.is_finite() && samples > T::zero()) {
+        return IIR1Coefficients::bypass();
+    }
+    let pole = (-T::one() / samples).exp();
+    let g = (T::one() - pole) / (T::one() + pole);
+    IIR1Coefficients {
+        a: T::one(),
+        g,
+        a1: g / (T::one() + g),
+        m0: T::zero(),
+        m1: T::one(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoother_time_constant() {
+        let sample_rate_hz = 48000.0;
+        let mut smoother = Smoother::<f64>::new(10.0, sample_rate_hz);
+        smoother.set_target(1.0);
+        let crossing = (0..10000)
+            .position(|_| smoother.next() >= 1.0 - (-1.0f64).exp())
+            .unwrap();
+        // 10 ms at 48 kHz is 480 samples, the first call to next is sample 0
+        assert!((crossing as i64 - 479).abs() <= 1);
+    }
+
+    #[test]
+    fn test_smoother_snap_and_settle() {
+        let mut smoother = Smoother::new(5.0, 48000.0);
+        smoother.snap_to(0.5);
+        assert_eq!(smoother.next(), 0.5);
+        assert!(smoother.is_settled(0.0));
+
+        smoother.set_target(1.0);
+        smoother.next();
+        assert!(!smoother.is_settled(1e-3));
+        for _ in 0..48000 {
+            smoother.next();
+        }
+        assert!(smoother.is_settled(1e-6));
+
+        let mut instant = Smoother::new(0.0, 48000.0);
+        instant.set_target(2.0);
+        assert_eq!(instant.next(), 2.0);
+    }
+}
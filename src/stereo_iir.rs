@@ -0,0 +1,302 @@
+use num_traits::Float;
+
+use crate::cast;
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+/// Internal states of two channels sharing one set of SVF coefficients
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2Stereo<T = f32> {
+    ic1eq: [T; 2],
+    ic2eq: [T; 2],
+    pub coeffs: IIR2Coefficients<T>,
+}
+
+impl<T: Float> IIR2Stereo<T> {
+    /// Creates a stereo SVF from a set of filter coefficients
+    #[inline]
+    pub fn from(coefficients: IIR2Coefficients<T>) -> Self {
+        IIR2Stereo {
+            ic1eq: [T::zero(); 2],
+            ic2eq: [T::zero(); 2],
+            coeffs: coefficients,
+        }
+    }
+
+    #[inline]
+    fn process_channel(&mut self, channel: usize, input_sample: T) -> T {
+        let (v1, v2) = self.coeffs.tick(
+            &mut self.ic1eq[channel],
+            &mut self.ic2eq[channel],
+            input_sample,
+        );
+        self.coeffs.output(input_sample, v1, v2)
+    }
+
+    #[inline]
+    pub fn process(&mut self, left: T, right: T) -> (T, T) {
+        (
+            self.process_channel(0, left),
+            self.process_channel(1, right),
+        )
+    }
+
+    /// Processes both channels in place, the buffers must have the same length
+    #[inline]
+    pub fn process_block(&mut self, left: &mut [T], right: &mut [T]) {
+        assert_eq!(left.len(), right.len());
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let (out_l, out_r) = self.process(*l, *r);
+            *l = out_l;
+            *r = out_r;
+        }
+    }
+
+    /// Processes an interleaved buffer (LRLR...) in place, see `IIR2Multi::process_interleaved`
+    /// for other channel counts
+    ///
+    /// # Panics
+    ///
+    /// If the buffer has an odd length.
+    #[inline]
+    pub fn process_interleaved(&mut self, buffer: &mut [T]) {
+        assert!(
+            buffer.len().is_multiple_of(2),
+            "interleaved stereo buffer has an odd length"
+        );
+        for frame in buffer.chunks_exact_mut(2) {
+            let (left, right) = self.process(frame[0], frame[1]);
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+
+    #[inline]
+    pub fn update(&mut self, new_coefficients: IIR2Coefficients<T>) {
+        self.coeffs = new_coefficients;
+    }
+
+    /// Clears the internal state of both channels, keeping the current coefficients
+    #[inline]
+    pub fn reset(&mut self) {
+        self.ic1eq = [T::zero(); 2];
+        self.ic2eq = [T::zero(); 2];
+    }
+}
+
+/// Mid/side filter for mastering style EQ. Left and right are encoded to mid = (L + R) / 2 and
+/// side = (L - R) / 2, each is filtered on its own and decoded with L = M + S, R = M - S, which
+/// undoes the encode exactly. With identical mid and side filters the result is the same as
+/// filtering left and right directly, up to rounding.
+#[derive(Copy, Clone, Debug)]
+pub struct IIR2MidSide<T = f32> {
+    mid: IIR2<T>,
+    side: IIR2<T>,
+    mid_bypassed: bool,
+    side_bypassed: bool,
+}
+
+impl<T: Float> IIR2MidSide<T> {
+    #[inline]
+    pub fn from(mid: IIR2Coefficients<T>, side: IIR2Coefficients<T>) -> Self {
+        IIR2MidSide {
+            mid: IIR2::from(mid),
+            side: IIR2::from(side),
+            mid_bypassed: false,
+            side_bypassed: false,
+        }
+    }
+
+    #[inline]
+    pub fn set_mid(&mut self, coefficients: IIR2Coefficients<T>) {
+        self.mid.update(coefficients);
+    }
+
+    #[inline]
+    pub fn set_side(&mut self, coefficients: IIR2Coefficients<T>) {
+        self.side.update(coefficients);
+    }
+
+    #[inline]
+    pub fn mid_coeffs(&self) -> IIR2Coefficients<T> {
+        self.mid.coeffs
+    }
+
+    #[inline]
+    pub fn side_coeffs(&self) -> IIR2Coefficients<T> {
+        self.side.coeffs
+    }
+
+    /// Passes the mid channel through unfiltered. Its filter does not run while bypassed and
+    /// starts from a cleared state when enabled again.
+    pub fn set_mid_bypassed(&mut self, bypassed: bool) {
+        if bypassed && !self.mid_bypassed {
+            self.mid.reset();
+        }
+        self.mid_bypassed = bypassed;
+    }
+
+    /// Passes the side channel through unfiltered, see `set_mid_bypassed`
+    pub fn set_side_bypassed(&mut self, bypassed: bool) {
+        if bypassed && !self.side_bypassed {
+            self.side.reset();
+        }
+        self.side_bypassed = bypassed;
+    }
+
+    #[inline]
+    pub fn mid_bypassed(&self) -> bool {
+        self.mid_bypassed
+    }
+
+    #[inline]
+    pub fn side_bypassed(&self) -> bool {
+        self.side_bypassed
+    }
+
+    #[inline]
+    pub fn process(&mut self, left: T, right: T) -> (T, T) {
+        let half = cast::<T>(0.5);
+        let mut mid = (left + right) * half;
+        let mut side = (left - right) * half;
+        if !self.mid_bypassed {
+            mid = self.mid.process(mid);
+        }
+        if !self.side_bypassed {
+            side = self.side.process(side);
+        }
+        (mid + side, mid - side)
+    }
+
+    /// Processes both channels in place, the buffers must have the same length
+    #[inline]
+    pub fn process_block(&mut self, left: &mut [T], right: &mut [T]) {
+        assert_eq!(left.len(), right.len());
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let (out_l, out_r) = self.process(*l, *r);
+            *l = out_l;
+            *r = out_r;
+        }
+    }
+
+    /// Clears the state of both filters, keeping the coefficients and bypass flags
+    #[inline]
+    pub fn reset(&mut self) {
+        self.mid.reset();
+        self.side.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rand(x: f32) -> f32 {
+        ((x * 12.9898).sin() * 43_758.547).fract()
+    }
+
+    #[test]
+    fn test_iir2_stereo_independent_channels() {
+        let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+        let mut filter = IIR2Stereo::from(coeffs);
+
+        let (_, right) = filter.process(1.0, 0.0);
+        assert_eq!(right, 0.0);
+        for _ in 0..1000 {
+            let (_, right) = filter.process(0.0, 0.0);
+            assert_eq!(right, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_iir2_stereo_matches_mono() {
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 1.0, 48000.0);
+        let mut left: Vec<f32> = (0..1000).map(|x| rand(x as f32)).collect();
+        let mut right: Vec<f32> = (0..1000).map(|x| rand(x as f32 + 0.5)).collect();
+
+        let mut mono_left = IIR2::from(coeffs);
+        let mut mono_right = IIR2::from(coeffs);
+        let expected_left: Vec<f32> = left.iter().map(|&x| mono_left.process(x)).collect();
+        let expected_right: Vec<f32> = right.iter().map(|&x| mono_right.process(x)).collect();
+
+        let mut filter = IIR2Stereo::from(coeffs);
+        filter.process_block(&mut left, &mut right);
+
+        assert_eq!(left, expected_left);
+        assert_eq!(right, expected_right);
+    }
+
+    #[test]
+    fn test_iir2_stereo_interleaved() {
+        let coeffs = IIR2Coefficients::bell(2000.0, -6.0, 2.0, 48000.0);
+        let mut interleaved: Vec<f32> = (0..2000).map(|x| rand(x as f32)).collect();
+
+        let mut left: Vec<f32> = interleaved.iter().step_by(2).copied().collect();
+        let mut right: Vec<f32> = interleaved.iter().skip(1).step_by(2).copied().collect();
+        IIR2::from(coeffs).process_block(&mut left);
+        IIR2::from(coeffs).process_block(&mut right);
+        let expected: Vec<f32> = left
+            .iter()
+            .zip(right.iter())
+            .flat_map(|(&l, &r)| [l, r])
+            .collect();
+
+        let mut filter = IIR2Stereo::from(coeffs);
+        filter.process_interleaved(&mut interleaved[..600]);
+        filter.process_interleaved(&mut interleaved[600..]);
+        assert_eq!(interleaved, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "odd length")]
+    fn test_iir2_stereo_interleaved_odd_length() {
+        let coeffs = IIR2Coefficients::bell(2000.0, -6.0, 2.0, 48000.0);
+        IIR2Stereo::from(coeffs).process_interleaved(&mut [0.0; 7]);
+    }
+
+    #[test]
+    fn test_iir2_mid_side_matches_stereo() {
+        let coeffs = IIR2Coefficients::highshelf(3000.0, 4.5, 0.8, 48000.0);
+        let left: Vec<f32> = (0..2000).map(|x| rand(x as f32)).collect();
+        let right: Vec<f32> = (0..2000).map(|x| rand(x as f32 + 0.5)).collect();
+
+        let (mut expected_left, mut expected_right) = (left.clone(), right.clone());
+        IIR2Stereo::from(coeffs).process_block(&mut expected_left, &mut expected_right);
+        let (mut out_left, mut out_right) = (left.clone(), right.clone());
+        IIR2MidSide::from(coeffs, coeffs).process_block(&mut out_left, &mut out_right);
+        for i in 0..left.len() {
+            assert!((out_left[i] - expected_left[i]).abs() < 1e-5);
+            assert!((out_right[i] - expected_right[i]).abs() < 1e-5);
+        }
+
+        // Both bypassed is just the encode and decode
+        let mut filter = IIR2MidSide::from(coeffs, coeffs);
+        filter.set_mid_bypassed(true);
+        filter.set_side_bypassed(true);
+        for (&l, &r) in left.iter().zip(&right) {
+            let (out_l, out_r) = filter.process(l, r);
+            assert!((out_l - l).abs() < 1e-7 && (out_r - r).abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_iir2_mid_side_independent_channels() {
+        let cut = IIR2Coefficients::highpass(200.0, 0.0, 0.7, 48000.0);
+        let flat = IIR2Coefficients::bell(200.0, 0.0, 0.7, 48000.0);
+        let mut filter = IIR2MidSide::from(flat, cut);
+
+        // Only the side of a constant, partly out of phase signal is removed
+        let mut output = (0.0, 0.0);
+        for _ in 0..48000 {
+            output = filter.process(1.0, 0.5);
+        }
+        assert!((output.0 - 0.75).abs() < 1e-4 && (output.1 - 0.75).abs() < 1e-4);
+
+        // Bypassing the side brings it back
+        filter.set_side_bypassed(true);
+        assert_eq!(filter.process(1.0, 0.5), (1.0, 0.5));
+        filter.set_side_bypassed(false);
+        filter.set_side(flat);
+        let (l, r) = filter.process(1.0, 0.5);
+        assert!((l - 1.0).abs() < 1e-6 && (r - 0.5).abs() < 1e-6);
+    }
+}
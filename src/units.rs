@@ -0,0 +1,76 @@
+//! Musical units for specifying cutoffs: MIDI notes, where 69 is A4 and each step is a
+//! semitone, and cents, hundredths of a semitone. Fractional notes are fine, so a note can carry
+//! pitch bend or keyboard tracking amounts directly.
+
+use num_traits::Float;
+
+use crate::cast;
+
+/// Standard concert pitch of A4, the reference of `note_to_hz` and `hz_to_note`
+pub const A4_HZ: f32 = 440.0;
+
+/// MIDI note number of A4
+pub const A4_NOTE: f32 = 69.0;
+
+/// Frequency of a MIDI note with A4 at 440 Hz
+#[inline]
+pub fn note_to_hz<T: Float>(note: T) -> T {
+    note_to_hz_tuned(note, cast(A4_HZ as f64))
+}
+
+/// Frequency of a MIDI note with A4 at a4_hz, for other tunings like 432 or 442 Hz
+#[inline]
+pub fn note_to_hz_tuned<T: Float>(note: T, a4_hz: T) -> T {
+    a4_hz * ((note - cast(A4_NOTE as f64)) / cast(12.0)).exp2()
+}
+
+/// MIDI note of a frequency with A4 at 440 Hz, the inverse of `note_to_hz`
+#[inline]
+pub fn hz_to_note<T: Float>(frequency_hz: T) -> T {
+    hz_to_note_tuned(frequency_hz, cast(A4_HZ as f64))
+}
+
+/// MIDI note of a frequency with A4 at a4_hz, the inverse of `note_to_hz_tuned`
+#[inline]
+pub fn hz_to_note_tuned<T: Float>(frequency_hz: T, a4_hz: T) -> T {
+    cast::<T>(A4_NOTE as f64) + cast::<T>(12.0) * (frequency_hz / a4_hz).log2()
+}
+
+/// Frequency cents above frequency_hz, or below for negative cents
+#[inline]
+pub fn cents_offset<T: Float>(frequency_hz: T, cents: T) -> T {
+    frequency_hz * (cents / cast(1200.0)).exp2()
+}
+
+/// Interval from from_hz to to_hz in cents, negative when to_hz is lower
+#[inline]
+pub fn cents_between<T: Float>(from_hz: T, to_hz: T) -> T {
+    cast::<T>(1200.0) * (to_hz / from_hz).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes() {
+        assert_eq!(note_to_hz(69.0f32), 440.0);
+        assert_eq!(note_to_hz(81.0f32), 880.0);
+        assert_eq!(note_to_hz(57.0f32), 220.0);
+        assert!((note_to_hz(60.0f64) - 261.625_565_300_598_6).abs() < 1e-9);
+        assert_eq!(note_to_hz_tuned(69.0f32, 432.0), 432.0);
+        for note in [0.0, 21.0, 60.5, 69.0, 108.0, 127.0] {
+            assert!((hz_to_note(note_to_hz(note)) - note).abs() < 1e-10);
+            assert!((hz_to_note_tuned(note_to_hz_tuned(note, 442.0), 442.0) - note).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_cents() {
+        assert_eq!(cents_offset(440.0f32, 1200.0), 880.0);
+        assert_eq!(cents_offset(440.0f32, -1200.0), 220.0);
+        assert!((cents_offset(440.0f64, 100.0) - note_to_hz(70.0)).abs() < 1e-9);
+        assert!((cents_between(440.0f64, 880.0) - 1200.0).abs() < 1e-9);
+        assert!((cents_between(1000.0f64, cents_offset(1000.0, -7.5)) + 7.5).abs() < 1e-9);
+    }
+}
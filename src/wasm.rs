@@ -0,0 +1,199 @@
+//! JavaScript bindings through `wasm_bindgen`, for drawing curves and processing audio in the
+//! browser with the same coefficient math as native code.
+//!
+//! Filter types are passed as strings and parsed like `IIR2FilterType::from_str`, so "bell",
+//! "LowShelf" and "low_shelf" all work. Build with `wasm-pack build --features wasm`.
+
+use core::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use crate::second_order_iir::{IIR2Coefficients, IIR2FilterType, IIR2};
+use crate::{MAX_CUTOFF_RATIO, MIN_RESPONSE_HZ};
+
+/// Range `frequency_response_js` covers, cut off at Nyquist for low sample rates
+const RESPONSE_START_HZ: f32 = 20.0;
+const RESPONSE_END_HZ: f32 = 20000.0;
+
+fn design(
+    filter_type: &str,
+    cutoff_hz: f32,
+    gain_db: f32,
+    q_value: f32,
+    sample_rate_hz: f32,
+) -> Result<IIR2Coefficients, JsError> {
+    let filter_type = IIR2FilterType::from_str(filter_type)
+        .map_err(|_| JsError::new(&format!("unknown filter type \"{}\"", filter_type)))?;
+    Ok(IIR2Coefficients::new(
+        filter_type,
+        cutoff_hz,
+        gain_db,
+        q_value,
+        sample_rate_hz,
+    ))
+}
+
+/// Coefficients of a second order filter
+#[wasm_bindgen(js_name = IIR2Coefficients)]
+#[derive(Copy, Clone, Debug)]
+pub struct WasmIIR2Coefficients(IIR2Coefficients);
+
+#[wasm_bindgen(js_class = IIR2Coefficients)]
+impl WasmIIR2Coefficients {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        filter_type: &str,
+        cutoff_hz: f32,
+        gain_db: f32,
+        q_value: f32,
+        sample_rate_hz: f32,
+    ) -> Result<WasmIIR2Coefficients, JsError> {
+        design(filter_type, cutoff_hz, gain_db, q_value, sample_rate_hz).map(WasmIIR2Coefficients)
+    }
+
+    /// Magnitude in dB at frequency_hz
+    #[wasm_bindgen(js_name = magnitudeDb)]
+    pub fn magnitude_db(&self, frequency_hz: f32, sample_rate_hz: f32) -> f32 {
+        20.0 * self
+            .0
+            .get_bode_sample(frequency_hz, sample_rate_hz)
+            .norm()
+            .log10()
+    }
+
+    /// Interleaved frequency and magnitude in dB pairs at num_points log spaced frequencies from
+    /// start_hz to end_hz
+    #[wasm_bindgen(js_name = frequencyResponse)]
+    pub fn frequency_response(
+        &self,
+        start_hz: f32,
+        end_hz: f32,
+        num_points: usize,
+        sample_rate_hz: f32,
+    ) -> Vec<f32> {
+        self.0
+            .frequency_response(start_hz, end_hz, num_points, sample_rate_hz)
+            .iter()
+            .flat_map(|point| [point.frequency_hz, point.magnitude_db])
+            .collect()
+    }
+}
+
+/// Interleaved frequency and magnitude in dB pairs of a filter at num_points log spaced
+/// frequencies from 20 Hz to 20 kHz, or to just below Nyquist when that is lower
+#[wasm_bindgen]
+pub fn frequency_response_js(
+    filter_type: &str,
+    cutoff_hz: f32,
+    gain_db: f32,
+    q_value: f32,
+    sample_rate_hz: f32,
+    num_points: usize,
+) -> Result<Vec<f32>, JsError> {
+    let coeffs =
+        WasmIIR2Coefficients::new(filter_type, cutoff_hz, gain_db, q_value, sample_rate_hz)?;
+    let end_hz = RESPONSE_END_HZ.min(sample_rate_hz * MAX_CUTOFF_RATIO);
+    let start_hz = RESPONSE_START_HZ.max(MIN_RESPONSE_HZ).min(end_hz);
+    Ok(coeffs.frequency_response(start_hz, end_hz, num_points, sample_rate_hz))
+}
+
+/// Second order filter processing audio
+#[wasm_bindgen(js_name = IIR2)]
+#[derive(Copy, Clone, Debug)]
+pub struct WasmIIR2(IIR2);
+
+#[wasm_bindgen(js_class = IIR2)]
+impl WasmIIR2 {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        filter_type: &str,
+        cutoff_hz: f32,
+        gain_db: f32,
+        q_value: f32,
+        sample_rate_hz: f32,
+    ) -> Result<WasmIIR2, JsError> {
+        design(filter_type, cutoff_hz, gain_db, q_value, sample_rate_hz)
+            .map(|coeffs| WasmIIR2(IIR2::from(coeffs)))
+    }
+
+    #[wasm_bindgen(js_name = fromCoefficients)]
+    pub fn from_coefficients(coeffs: &WasmIIR2Coefficients) -> WasmIIR2 {
+        WasmIIR2(IIR2::from(coeffs.0))
+    }
+
+    /// Switches to new coefficients, keeping the state
+    pub fn update(&mut self, coeffs: &WasmIIR2Coefficients) {
+        self.0.update(coeffs.0);
+    }
+
+    pub fn process(&mut self, input_sample: f32) -> f32 {
+        self.0.process(input_sample)
+    }
+
+    /// Filters a Float32Array in place
+    #[wasm_bindgen(js_name = processBlock)]
+    pub fn process_block(&mut self, buffer: &mut [f32]) {
+        self.0.process_block(buffer);
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_wasm_bell_response() {
+        let response = frequency_response_js("bell", 1000.0, 6.0, 1.0, 48000.0, 301).unwrap();
+        assert_eq!(response.len(), 602);
+        assert_eq!(response[0], 20.0);
+        assert!((response[600] - 20000.0).abs() < 0.01);
+
+        let coeffs = IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0);
+        for pair in response.chunks_exact(2) {
+            let expected = 20.0 * coeffs.get_bode_sample(pair[0], 48000.0).norm().log10();
+            assert_eq!(pair[1], expected);
+        }
+        let peak = response
+            .chunks_exact(2)
+            .max_by(|a, b| a[1].partial_cmp(&b[1]).unwrap())
+            .unwrap();
+        assert!((peak[0] - 1000.0).abs() < 30.0);
+        assert!((peak[1] - 6.0).abs() < 0.01);
+
+        // Low sample rates stop short of Nyquist
+        let response = frequency_response_js("LowPass", 1000.0, 0.0, 0.7, 8000.0, 10).unwrap();
+        assert!(response[18] < 4000.0);
+    }
+
+    #[test]
+    fn test_wasm_process_block() {
+        let coeffs = WasmIIR2Coefficients::new("low_shelf", 200.0, -6.0, 0.7, 48000.0).unwrap();
+        let mut filter = WasmIIR2::from_coefficients(&coeffs);
+        let mut native = IIR2::from(IIR2Coefficients::lowshelf(200.0, -6.0, 0.7, 48000.0));
+        let input: Vec<f32> = (0..128).map(|i| ((i * 13) % 7) as f32 / 7.0).collect();
+        let mut buffer = input.clone();
+        filter.process_block(&mut buffer);
+        for (x, y) in input.iter().zip(&buffer) {
+            assert_eq!(*y, native.process(*x));
+        }
+
+        filter.reset();
+        native.reset();
+        assert_eq!(filter.process(1.0), native.process(1.0));
+        assert!((coeffs.magnitude_db(10.0, 48000.0) + 6.0).abs() < 0.1);
+    }
+
+    // JsError needs a JavaScript host
+    #[cfg(target_arch = "wasm32")]
+    #[test]
+    fn test_wasm_unknown_filter_type() {
+        assert!(frequency_response_js("ladder", 1000.0, 0.0, 0.7, 48000.0, 10).is_err());
+        assert!(WasmIIR2::new("", 1000.0, 0.0, 0.7, 48000.0).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Frequency weighting curves used for level metering
+
+use core::f64::consts::PI;
+
+use num_traits::Float;
+
+use crate::cascade_iir::IIR2Cascade;
+use crate::cast;
+use crate::second_order_iir::IIR2Coefficients;
+
+/// Scales the output mix of a section by gain
+fn scaled<T: Float>(mut coeffs: IIR2Coefficients<T>, gain: T) -> IIR2Coefficients<T> {
+    coeffs.m0 = coeffs.m0 * gain;
+    coeffs.m1 = coeffs.m1 * gain;
+    coeffs.m2 = coeffs.m2 * gain;
+    coeffs
+}
+
+/// A-weighting (IEC 61672) normalized to 0 dB at 1 kHz. The four analog pole frequencies of the
+/// standard are realized as a high pass with a double pole at 20.6 Hz, a high pass with poles at
+/// 107.7 Hz and 737.9 Hz and a `lowpass_matched` with a double pole at 12194 Hz. Returns `None`
+/// when the matched low pass is not stable, e.g. for an unusable sample rate.
+pub fn a_weighting<T: Float>(sample_rate_hz: T) -> Option<IIR2Cascade<3, T>> {
+    const F1: f64 = 20.598_997;
+    const F2: f64 = 107.652_65;
+    const F3: f64 = 737.862_23;
+    const F4: f64 = 12_194.217;
+
+    let low = IIR2Coefficients::highpass(cast(F1), T::zero(), cast(0.5), sample_rate_hz);
+    let mid = IIR2Coefficients::highpass(
+        cast((F2 * F3).sqrt()),
+        T::zero(),
+        cast((F2 * F3).sqrt() / (F2 + F3)),
+        sample_rate_hz,
+    );
+    let high = IIR2Coefficients::lowpass_matched(cast(F4), T::zero(), cast(0.5), sample_rate_hz)?;
+
+    let mut cascade = IIR2Cascade::from([low, mid, high]);
+    let gain = T::one() / cascade.get_bode_sample(cast(1000.0), sample_rate_hz).norm();
+    cascade.update_section(0, scaled(low, gain));
+    Some(cascade)
+}
+
+/// Pre-filter of ITU-R BS.1770 loudness metering: a +4 dB high shelf modelling the head followed
+/// by the RLB high pass. The analog parameters behind the published 48 kHz coefficients are
+/// re-derived for any sample rate. Returns `None` when either section is not stable, e.g. for an
+/// unusable sample rate.
+pub fn k_weighting<T: Float>(sample_rate_hz: T) -> Option<IIR2Cascade<2, T>> {
+    let fs = sample_rate_hz.to_f64().unwrap();
+
+    let shelf = {
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / fs).tan();
+        let vh = 10.0f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        IIR2Coefficients::from_biquad(
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        )
+    };
+
+    let highpass = {
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        IIR2Coefficients::from_biquad(
+            1.0,
+            -2.0,
+            1.0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        )
+    };
+
+    Some(IIR2Cascade::from([
+        IIR2Coefficients::from_f64(shelf?),
+        IIR2Coefficients::from_f64(highpass?),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::BiquadCoefficients;
+
+    /// Nominal frequencies and A-weighting attenuations from IEC 61672-1
+    const A_WEIGHTING_TABLE: [(f64, f64); 30] = [
+        (20.0, -50.5),
+        (25.0, -44.7),
+        (31.5, -39.4),
+        (40.0, -34.6),
+        (50.0, -30.2),
+        (63.0, -26.2),
+        (80.0, -22.5),
+        (100.0, -19.1),
+        (125.0, -16.1),
+        (160.0, -13.4),
+        (200.0, -10.9),
+        (250.0, -8.6),
+        (315.0, -6.6),
+        (400.0, -4.8),
+        (500.0, -3.2),
+        (630.0, -1.9),
+        (800.0, -0.8),
+        (1000.0, 0.0),
+        (1250.0, 0.6),
+        (1600.0, 1.0),
+        (2000.0, 1.2),
+        (2500.0, 1.3),
+        (3150.0, 1.2),
+        (4000.0, 1.0),
+        (5000.0, 0.5),
+        (6300.0, -0.1),
+        (8000.0, -1.1),
+        (10000.0, -2.5),
+        (12500.0, -4.3),
+        (16000.0, -6.6),
+    ];
+
+    #[test]
+    fn test_a_weighting() {
+        for &sample_rate_hz in &[44100.0, 48000.0, 96000.0] {
+            let filter = a_weighting::<f64>(sample_rate_hz).unwrap();
+            for &(f, expected_db) in A_WEIGHTING_TABLE.iter() {
+                let db = 20.0 * filter.get_bode_sample(f, sample_rate_hz).norm().log10();
+                assert!((db - expected_db).abs() < 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_weighting() {
+        // Coefficients published in ITU-R BS.1770 for 48 kHz
+        let shelf = BiquadCoefficients {
+            b0: 1.535_124_859_586_97,
+            b1: -2.691_696_189_406_38,
+            b2: 1.198_392_810_852_85,
+            a1: -1.690_659_293_182_41,
+            a2: 0.732_480_774_215_85,
+        };
+        let highpass = BiquadCoefficients {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: -1.990_047_454_833_98,
+            a2: 0.990_072_250_366_21,
+        };
+        let filter = k_weighting::<f64>(48000.0).unwrap();
+        for &f in &[
+            10.0, 20.0, 38.0, 100.0, 500.0, 1000.0, 1500.0, 4000.0, 10000.0, 20000.0,
+        ] {
+            let expected = shelf.get_bode_sample(f, 48000.0) * highpass.get_bode_sample(f, 48000.0);
+            let actual = filter.get_bode_sample(f, 48000.0);
+            let db = |x: num_complex::Complex<f64>| 20.0 * x.norm().log10();
+            assert!((db(expected) - db(actual)).abs() < 0.05);
+        }
+
+        // The shelf tops out near +4 dB at any sample rate
+        for &sample_rate_hz in &[44100.0, 96000.0] {
+            let filter = k_weighting::<f64>(sample_rate_hz).unwrap();
+            let db = 20.0
+                * filter
+                    .get_bode_sample(15000.0, sample_rate_hz)
+                    .norm()
+                    .log10();
+            assert!((db - 4.0).abs() < 0.2);
+        }
+    }
+
+    #[test]
+    fn test_weighting_f32() {
+        // The f32 curves are the f64 ones up to the rounding of the SVF coefficients, compared in
+        // f64 as the f32 response itself is too coarse at the low end
+        let widen = |c: IIR2Coefficients<f32>| IIR2Coefficients::<f64> {
+            a: c.a as f64,
+            g: c.g as f64,
+            gpow2: c.gpow2 as f64,
+            k: c.k as f64,
+            a1: c.a1 as f64,
+            a2: c.a2 as f64,
+            a3: c.a3 as f64,
+            m0: c.m0 as f64,
+            m1: c.m1 as f64,
+            m2: c.m2 as f64,
+        };
+        for &sample_rate_hz in &[22050.0, 44100.0, 48000.0, 96000.0, 192000.0, 384000.0] {
+            let a_single = a_weighting::<f32>(sample_rate_hz as f32).unwrap();
+            let k_single = k_weighting::<f32>(sample_rate_hz as f32).unwrap();
+            let a_single = IIR2Cascade::from(a_single.sections.map(|s| widen(s.coeffs)));
+            let k_single = IIR2Cascade::from(k_single.sections.map(|s| widen(s.coeffs)));
+            let a_double = a_weighting::<f64>(sample_rate_hz).unwrap();
+            let k_double = k_weighting::<f64>(sample_rate_hz).unwrap();
+            let db = |x: num_complex::Complex<f64>| 20.0 * x.norm().log10();
+            let end_hz = (sample_rate_hz * 0.45).min(20000.0);
+            for f in crate::log_frequencies(20.0, end_hz, 60) {
+                let a_difference = db(a_single.get_bode_sample(f, sample_rate_hz))
+                    - db(a_double.get_bode_sample(f, sample_rate_hz));
+                let k_difference = db(k_single.get_bode_sample(f, sample_rate_hz))
+                    - db(k_double.get_bode_sample(f, sample_rate_hz));
+                assert!(a_difference.abs() < 0.001);
+                assert!(k_difference.abs() < 0.001);
+            }
+        }
+
+        assert!(a_weighting::<f32>(f32::NAN).is_none());
+        assert!(k_weighting::<f32>(f32::NAN).is_none());
+    }
+}
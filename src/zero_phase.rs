@@ -0,0 +1,187 @@
+//! Offline zero phase filtering, running a filter forwards and then backwards over a buffer.
+//!
+//! The phase shifts of both passes cancel and the magnitude response is squared, so a low pass
+//! at -3 dB at its cutoff ends up at -6 dB there, and a 6 dB bell boosts by 12 dB. As the
+//! backward pass needs the whole signal, this is only for analysis and offline processing.
+//!
+//! To keep the ends from ringing, the buffer is extended at both ends with its odd reflection
+//! around the first and last sample, for about as long as the filter takes to decay by 120 dB,
+//! and each pass starts from the steady state for the first sample it sees.
+
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use crate::cast;
+use crate::first_order_iir::{IIR1Coefficients, IIR1};
+use crate::second_order_iir::{IIR2Coefficients, IIR2};
+
+/// Longest padding added at each end, for filters that decay very slowly
+const MAX_PAD: usize = 1 << 16;
+
+/// Samples until the impulse response of poles with radius pole_radius has decayed by 120 dB,
+/// limited to what the buffer can reflect
+fn pad_length<T: Float>(pole_radius: T, length: usize) -> usize {
+    let limit = length.saturating_sub(1).min(MAX_PAD);
+    if !(pole_radius.is_finite() && pole_radius < T::one()) {
+        return limit;
+    }
+    if pole_radius <= T::zero() {
+        return limit.min(1);
+    }
+    let decay = (cast::<T>(1e-6).ln() / pole_radius.ln())
+        .ceil()
+        .to_usize()
+        .unwrap_or(limit);
+    decay.min(limit)
+}
+
+/// The buffer with pad samples of its odd reflection, 2 x[0] - x[i], at each end
+fn reflect_padded<T: Float>(buffer: &[T], pad: usize) -> Vec<T> {
+    let two = cast::<T>(2.0);
+    let (first, last) = (buffer[0], buffer[buffer.len() - 1]);
+    let front = buffer[1..=pad].iter().rev().map(|&x| two * first - x);
+    let back = buffer[buffer.len() - 1 - pad..buffer.len() - 1]
+        .iter()
+        .rev()
+        .map(|&x| two * last - x);
+    front.chain(buffer.iter().copied()).chain(back).collect()
+}
+
+/// Runs both passes over the padded buffer and copies the middle back. pass is called with
+/// the padded signal and then with it reversed, and has to set up its own state.
+fn forward_backward<T: Float>(buffer: &mut [T], pad: usize, mut pass: impl FnMut(&mut [T])) {
+    if buffer.is_empty() {
+        return;
+    }
+    let mut padded = reflect_padded(buffer, pad);
+    pass(&mut padded);
+    padded.reverse();
+    pass(&mut padded);
+    padded.reverse();
+    buffer.copy_from_slice(&padded[pad..pad + buffer.len()]);
+}
+
+/// Filters the buffer in place with zero phase, see the module documentation
+pub fn zero_phase_process<T: Float>(coeffs: IIR2Coefficients<T>, buffer: &mut [T]) {
+    let (poles, _) = coeffs.poles_zeros();
+    let radius = poles[0].norm().max(poles[1].norm());
+    let pad = pad_length(radius, buffer.len());
+    forward_backward(buffer, pad, |signal| {
+        let mut filter = IIR2::from(coeffs);
+        filter.settle_to_dc(signal[0]);
+        filter.process_block(signal);
+    });
+}
+
+/// Filters the buffer in place with zero phase using a first order section, see the module
+/// documentation
+pub fn zero_phase_process_iir1<T: Float>(coeffs: IIR1Coefficients<T>, buffer: &mut [T]) {
+    let (pole, _) = coeffs.poles_zeros();
+    let pad = pad_length(pole.norm(), buffer.len());
+    forward_backward(buffer, pad, |signal| {
+        let mut filter = IIR1::from(coeffs);
+        filter.settle_to_dc(signal[0]);
+        filter.process_block(signal);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::TAU;
+
+    fn sine(frequency_hz: f64, length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| (TAU * frequency_hz * i as f64 / 48000.0).sin())
+            .collect()
+    }
+
+    fn peak_lag(input: &[f64], output: &[f64], max_lag: isize) -> isize {
+        let correlation = |lag: isize| -> f64 {
+            (0..input.len() as isize)
+                .filter(|&i| i + lag >= 0 && i + lag < output.len() as isize)
+                .map(|i| input[i as usize] * output[(i + lag) as usize])
+                .sum()
+        };
+        (-max_lag..=max_lag)
+            .max_by(|&a, &b| correlation(a).partial_cmp(&correlation(b)).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_zero_phase_symmetric() {
+        // A symmetric pulse stays symmetric, which a causal filter would smear to one side
+        let length = 2001;
+        let pulse: Vec<f64> = (0..length)
+            .map(|i| {
+                let t = (i as f64 - 1000.0) / 100.0;
+                (-t * t).exp()
+            })
+            .collect();
+        let mut second = pulse.clone();
+        zero_phase_process(
+            IIR2Coefficients::lowpass(200.0, 0.0, 1.5, 48000.0),
+            &mut second,
+        );
+        let mut first = pulse.clone();
+        zero_phase_process_iir1(IIR1Coefficients::lowpass(200.0, 0.0, 48000.0), &mut first);
+        for output in [second, first] {
+            for i in 0..length {
+                assert!((output[i] - output[length - 1 - i]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_phase_no_shift() {
+        let input = sine(500.0, 9600);
+        let coeffs = IIR2Coefficients::lowpass(1000.0, 0.0, 0.707, 48000.0);
+        let mut output = input.clone();
+        zero_phase_process(coeffs, &mut output);
+        assert_eq!(peak_lag(&input, &output, 40), 0);
+
+        // For comparison, the causal filter delays the sine
+        let mut causal = input.clone();
+        IIR2::from(coeffs).process_block(&mut causal);
+        assert!(peak_lag(&input, &causal, 40) > 0);
+
+        let mut output = input.clone();
+        zero_phase_process_iir1(IIR1Coefficients::lowpass(1000.0, 0.0, 48000.0), &mut output);
+        assert_eq!(peak_lag(&input, &output, 40), 0);
+    }
+
+    #[test]
+    fn test_zero_phase_edges() {
+        // A constant passes a low pass unchanged, without a transient at either end
+        let mut buffer = vec![0.5; 500];
+        zero_phase_process(
+            IIR2Coefficients::lowpass(100.0, 0.0, 2.0, 48000.0),
+            &mut buffer,
+        );
+        assert!(buffer.iter().all(|&x| (x - 0.5).abs() < 1e-9));
+
+        // Magnitude is squared, so the gain at the cutoff is doubled in dB
+        let mut buffer = sine(1000.0, 48000);
+        zero_phase_process(
+            IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0),
+            &mut buffer,
+        );
+        let peak = buffer[10000..38000]
+            .iter()
+            .fold(0.0f64, |m, x| m.max(x.abs()));
+        assert!((20.0 * peak.log10() - 12.0).abs() < 0.01);
+
+        let mut empty: [f64; 0] = [];
+        zero_phase_process(
+            IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0),
+            &mut empty,
+        );
+        let mut single = [0.25];
+        zero_phase_process(
+            IIR2Coefficients::bell(1000.0, 6.0, 1.0, 48000.0),
+            &mut single,
+        );
+        assert!((single[0] - 0.25).abs() < 1e-9);
+    }
+}